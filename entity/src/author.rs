@@ -8,24 +8,72 @@ pub struct Model {
     /// Internal ID of author
     pub id: i64,
     #[sea_orm(unique)]
-    /// Twitter (snowflake) ID of author
+    /// Platform ID of author. Twitter snowflake for twitter, hash of `external_id`
+    /// for platforms with non-numeric ids
     pub platform_id: i64,
+    #[sea_orm(default_value = "twitter")]
+    /// Platform of author
+    pub platform: Platform,
+    /// Platform-native ID of author, used to fetch author and timeline from source
+    pub external_id: String,
+    #[sea_orm(nullable)]
+    #[serde(skip)]
+    /// Source cursor of last fetched timeline, `None` if timeline was never fetched
+    pub cursor: Option<String>,
+    #[sea_orm(default_value = 120)]
+    #[serde(skip)]
+    /// Current delay between timeline fetches in seconds, adapts to posting frequency
+    pub poll_interval: i32,
+    #[sea_orm(nullable)]
+    #[serde(skip)]
+    /// Unix time of next timeline fetch, `None` if author should be fetched as soon as
+    /// possible
+    pub next_poll_at: Option<i64>,
+    #[sea_orm(default_value = false)]
+    /// Whether self-replies of author (threads) are collected
+    pub collect_threads: bool,
+    #[sea_orm(default_value = false)]
+    /// Whether posts quoted by author are collected
+    pub collect_quotes: bool,
+    #[sea_orm(nullable)]
+    #[serde(skip)]
+    /// Unix time of last profile refresh, `None` if profile was never refreshed
+    pub refreshed_at: Option<i64>,
 
-    /// Name of twitter account (not username)
+    /// Name of account (not username)
     pub name: String,
-    /// Username of twitter account
+    /// Username of account
     pub username: String,
     #[sea_orm(nullable)]
-    /// Avatar of twitter account
+    /// Avatar of account
     pub avatar_url: Option<String>,
 }
 
+#[derive(EnumIter, Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, DeriveActiveEnum)]
+#[sea_orm(rs_type = "String", db_type = "Text")]
+#[serde(rename_all = "camelCase")]
+pub enum Platform {
+    #[sea_orm(string_value = "twitter")]
+    Twitter,
+    #[sea_orm(string_value = "rss")]
+    /// RSS/Atom/JSON feed, `external_id` and `username` are feed url
+    Rss,
+    #[sea_orm(string_value = "mastodon")]
+    /// Mastodon account, `external_id` is `account_id@instance`
+    Mastodon,
+    #[sea_orm(string_value = "bluesky")]
+    /// Bluesky account, `external_id` is DID and `username` is handle
+    Bluesky,
+}
+
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
 pub enum Relation {
     #[sea_orm(has_many = "super::post::Entity")]
     Posts,
     #[sea_orm(has_many = "super::follow::Entity")]
     Followers,
+    #[sea_orm(has_many = "super::author_username::Entity")]
+    Usernames,
 }
 
 impl Related<super::post::Entity> for Entity {
@@ -40,4 +88,10 @@ impl Related<super::follow::Entity> for Entity {
     }
 }
 
+impl Related<super::author_username::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Usernames.def()
+    }
+}
+
 impl ActiveModelBehavior for ActiveModel {}
@@ -0,0 +1,35 @@
+use sea_orm::entity::prelude::*;
+use serde::Serialize;
+
+#[derive(Clone, Serialize, Debug, DeriveEntityModel)]
+#[sea_orm(table_name = "author_usernames")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    /// Internal ID of record
+    pub id: i64,
+    /// Internal ID of author
+    pub author_id: i64,
+    /// Past username of author
+    pub username: String,
+    /// Unix time when username was replaced by new one
+    pub replaced_at: i64,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::author::Entity",
+        from = "Column::AuthorId",
+        to = "super::author::Column::Id",
+        on_delete = "Cascade"
+    )]
+    Author,
+}
+
+impl Related<super::author::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Author.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -18,13 +18,15 @@ pub enum Relation {
     #[sea_orm(
         belongs_to = "super::telegram_user::Entity",
         from = "Column::UserId",
-        to = "super::telegram_user::Column::Id"
+        to = "super::telegram_user::Column::Id",
+        on_delete = "Cascade"
     )]
     User,
     #[sea_orm(
         belongs_to = "super::author::Entity",
         from = "Column::AuthorId",
-        to = "super::author::Column::Id"
+        to = "super::author::Column::Id",
+        on_delete = "Cascade"
     )]
     Author,
 }
@@ -1,6 +1,11 @@
 pub mod author;
+pub mod author_username;
 pub mod follow;
 pub mod post;
 pub mod post_media;
+pub mod post_media_link;
+pub mod posting_schedule;
+pub mod published_post;
 pub mod scheduled_post;
+pub mod scheduled_post_media;
 pub mod telegram_user;
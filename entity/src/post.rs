@@ -1,5 +1,5 @@
-use sea_orm::entity::prelude::*;
-use serde::Serialize;
+use sea_orm::{entity::prelude::*, FromJsonQueryResult};
+use serde::{Deserialize, Serialize};
 
 #[derive(Clone, Serialize, Debug, DeriveEntityModel)]
 #[sea_orm(table_name = "posts")]
@@ -7,18 +7,89 @@ pub struct Model {
     #[sea_orm(primary_key)]
     /// Internal ID of post
     pub id: i64,
-    #[sea_orm(unique)]
-    /// Twitter (snowflake) ID of post
+    /// Platform ID of post, grows with time of post on its platform (twitter snowflake
+    /// for twitter). Posts of different platforms may share it
     pub platform_id: i64,
-    /// Internal ID of author
+    /// Platform-native ID of post, unique on its platform
+    pub external_id: String,
+    #[sea_orm(default_value = "twitter")]
+    /// Platform of post
+    pub platform: super::author::Platform,
+    /// Internal ID of author of post. Quoted posts of authors, that are not tracked, are
+    /// stored with author of quoting post, see `quoted_only`
     pub author_id: i64,
+    #[sea_orm(nullable)]
+    /// Internal ID of previous post of thread, if post is self-reply
+    pub parent_post_id: Option<i64>,
+    #[sea_orm(nullable)]
+    /// Internal ID of post quoted by this post
+    pub quoted_post_id: Option<i64>,
+    #[sea_orm(default_value = false)]
+    /// Whether post was collected only as quoted post of author, that is not tracked.
+    /// Such posts are hidden from post listings
+    pub quoted_only: bool,
 
     /// Post text
     pub text: String,
+    /// Links, mentions and hashtags in post text
+    pub entities: PostEntities,
     /// Post source url
     pub source_url: String,
     /// Post source default text
     pub source_text: String,
+    #[sea_orm(nullable)]
+    /// Unix time when post was published on platform. Posts without known time get time
+    /// of collection, so they are listed as new posts. Unknown for some posts collected
+    /// before that
+    pub posted_at: Option<i64>,
+
+    #[sea_orm(default_value = false)]
+    /// Whether post was deleted on platform
+    pub deleted: bool,
+    #[sea_orm(nullable)]
+    /// Platform ID of current version of post, if post was edited
+    pub version_id: Option<i64>,
+    /// Previous versions of edited post, oldest first
+    pub edit_history: PostEditHistory,
+    #[sea_orm(nullable)]
+    #[serde(skip)]
+    /// Unix time of last revalidation of post
+    pub checked_at: Option<i64>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize, FromJsonQueryResult)]
+/// Entities of post text, stored as json
+pub struct PostEntities(pub Vec<PostEntity>);
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+/// Link in post text. `start` and `end` are offsets in chars
+pub struct PostEntity {
+    pub kind: EntityKind,
+    pub start: usize,
+    pub end: usize,
+    /// Where entity links to
+    pub url: String,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize, FromJsonQueryResult)]
+/// Previous versions of post, stored as json
+pub struct PostEditHistory(pub Vec<PostVersion>);
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+/// Version of post, that was replaced by edit
+pub struct PostVersion {
+    /// Platform ID of version
+    pub platform_id: i64,
+    pub text: String,
+    pub entities: Vec<PostEntity>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum EntityKind {
+    Url,
+    Mention,
+    Hashtag,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -26,11 +97,14 @@ pub enum Relation {
     #[sea_orm(
         belongs_to = "super::author::Entity",
         from = "Column::AuthorId",
-        to = "super::author::Column::Id"
+        to = "super::author::Column::Id",
+        on_delete = "Cascade"
     )]
     Author,
-    #[sea_orm(has_many = "super::post_media::Entity")]
-    PostMedia,
+    #[sea_orm(has_many = "super::post_media_link::Entity")]
+    MediaLinks,
+    #[sea_orm(has_many = "super::published_post::Entity")]
+    PublishedPosts,
 }
 
 impl Related<super::author::Entity> for Entity {
@@ -39,9 +113,25 @@ impl Related<super::author::Entity> for Entity {
     }
 }
 
+impl Related<super::post_media_link::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::MediaLinks.def()
+    }
+}
+
 impl Related<super::post_media::Entity> for Entity {
     fn to() -> RelationDef {
-        Relation::PostMedia.def()
+        super::post_media_link::Relation::Media.def()
+    }
+
+    fn via() -> Option<RelationDef> {
+        Some(super::post_media_link::Relation::Post.def().rev())
+    }
+}
+
+impl Related<super::published_post::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::PublishedPosts.def()
     }
 }
 
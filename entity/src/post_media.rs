@@ -1,5 +1,6 @@
 use sea_orm::entity::prelude::*;
 use serde::Serialize;
+use sha2::{Digest, Sha256};
 
 #[derive(Clone, Serialize, Debug, DeriveEntityModel)]
 #[sea_orm(table_name = "post_media")]
@@ -8,13 +9,30 @@ pub struct Model {
     /// Internal ID of post media
     pub id: i64,
     #[sea_orm(unique)]
-    /// Internal ID of post
-    pub post_id: i64,
+    /// Hash of media url, see [`url_key`]. Media with the same key in several posts
+    /// is stored once
+    pub url_key: String,
+    #[sea_orm(nullable)]
+    /// Hex encoded SHA-256 of media file, known after posting worker downloaded it. The
+    /// same file under different urls has the same hash
+    pub content_hash: Option<String>,
 
-    /// Media type (photo or video)
+    /// Media type (photo, video or animation)
     pub media_type: MediaType,
     /// URL to media
     pub media_url: String,
+    #[sea_orm(nullable)]
+    /// Alternative text (description) of media
+    pub alt_text: Option<String>,
+    #[sea_orm(nullable)]
+    /// Width of media in pixels, if known
+    pub width: Option<i32>,
+    #[sea_orm(nullable)]
+    /// Height of media in pixels, if known
+    pub height: Option<i32>,
+    #[sea_orm(nullable)]
+    /// Duration of video or animation in milliseconds, if known
+    pub duration_ms: Option<i32>,
 }
 
 #[derive(EnumIter, Debug, Clone, PartialEq, Eq, Serialize, DeriveActiveEnum)]
@@ -25,22 +43,93 @@ pub enum MediaType {
     Photo,
     #[sea_orm(string_value = "video")]
     Video,
+    #[sea_orm(string_value = "animation")]
+    /// Silent looped video (like twitter GIFs)
+    Animation,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
 pub enum Relation {
-    #[sea_orm(
-        belongs_to = "super::post::Entity",
-        from = "Column::PostId",
-        to = "super::post::Column::Id"
-    )]
-    Post,
+    #[sea_orm(has_many = "super::post_media_link::Entity")]
+    PostLinks,
+    #[sea_orm(has_many = "super::scheduled_post_media::Entity")]
+    ScheduledPostMedia,
+}
+
+impl Related<super::post_media_link::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::PostLinks.def()
+    }
 }
 
 impl Related<super::post::Entity> for Entity {
     fn to() -> RelationDef {
-        Relation::Post.def()
+        super::post_media_link::Relation::Post.def()
+    }
+
+    fn via() -> Option<RelationDef> {
+        Some(super::post_media_link::Relation::Media.def().rev())
+    }
+}
+
+impl Related<super::scheduled_post_media::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::ScheduledPostMedia.def()
     }
 }
 
 impl ActiveModelBehavior for ActiveModel {}
+
+/// Hosts of twitter media, that select size or format of the same file by query
+const TWITTER_MEDIA_HOSTS: [&str; 2] = ["pbs.twimg.com", "video.twimg.com"];
+
+/// Returns hex encoded SHA-256 of media url. It identifies media by its url, not by its
+/// content, so the same file served by different urls gets different keys. Fragment is
+/// always dropped. Query is dropped only for twitter media (e.g. media of retweets and
+/// quotes with `?name=orig`), other hosts may serve different files by query (like
+/// `?file=1` and `?file=2`).
+pub fn url_key(media_url: &str) -> String {
+    let url = media_url.split('#').next().unwrap_or(media_url);
+    let host = url
+        .split_once("://")
+        .map_or(url, |(_, rest)| rest)
+        .split(['/', '?'])
+        .next()
+        .unwrap_or_default();
+    let url = if TWITTER_MEDIA_HOSTS.contains(&host) {
+        url.split('?').next().unwrap_or(url)
+    } else {
+        url
+    };
+
+    Sha256::digest(url.as_bytes())
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::url_key;
+
+    /// Check [`url_key`] ignores query of twitter media and fragment of url
+    #[test]
+    fn test_url_key() {
+        let key = url_key("https://pbs.twimg.com/media/a.jpg");
+
+        assert_eq!(key.len(), 64);
+        assert_eq!(key, url_key("https://pbs.twimg.com/media/a.jpg?name=orig"));
+        assert_eq!(key, url_key("https://pbs.twimg.com/media/a.jpg#1"));
+        assert_ne!(key, url_key("https://pbs.twimg.com/media/b.jpg"));
+    }
+
+    /// Check [`url_key`] keeps query of media on other hosts
+    #[test]
+    fn test_url_key_query() {
+        let key = url_key("https://example.com/download?file=1");
+
+        assert_eq!(key, url_key("https://example.com/download?file=1#top"));
+        assert_ne!(key, url_key("https://example.com/download?file=2"));
+        assert_ne!(key, url_key("https://example.com/download"));
+    }
+}
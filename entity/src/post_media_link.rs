@@ -0,0 +1,48 @@
+use sea_orm::entity::prelude::*;
+use serde::Serialize;
+
+#[derive(Clone, Serialize, Debug, DeriveEntityModel)]
+#[sea_orm(table_name = "post_media_links")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    /// Internal ID of post
+    pub post_id: i64,
+    #[sea_orm(primary_key, auto_increment = false)]
+    /// Internal ID of post media
+    pub media_id: i64,
+
+    /// Position of media in post, starting from 0
+    pub position: i32,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::post::Entity",
+        from = "Column::PostId",
+        to = "super::post::Column::Id",
+        on_delete = "Cascade"
+    )]
+    Post,
+    #[sea_orm(
+        belongs_to = "super::post_media::Entity",
+        from = "Column::MediaId",
+        to = "super::post_media::Column::Id",
+        on_delete = "Cascade"
+    )]
+    Media,
+}
+
+impl Related<super::post::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Post.def()
+    }
+}
+
+impl Related<super::post_media::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Media.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -0,0 +1,104 @@
+use sea_orm::entity::prelude::*;
+use serde::Serialize;
+
+#[derive(Clone, Serialize, Debug, DeriveEntityModel)]
+#[sea_orm(table_name = "posting_schedules")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    /// Telegram channel this schedule belongs to
+    pub channel: i64,
+
+    /// Minutes between two posts
+    pub interval: i32,
+    /// First slot of the day, in minutes after local midnight
+    pub day_start: i32,
+    /// No slots after this time, in minutes after local midnight
+    pub day_end: i32,
+    /// Offset of schedule timezone from UTC, in minutes
+    pub utc_offset: i32,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+const DAY: i64 = 24 * 60 * 60;
+
+impl Model {
+    /// Returns unix time of the first slot at or after `after` (unix time)
+    pub fn next_slot(&self, after: i64) -> i64 {
+        let offset = self.utc_offset as i64 * 60;
+        let interval = self.interval as i64 * 60;
+        let day_start = self.day_start as i64 * 60;
+        let day_end = self.day_end as i64 * 60;
+
+        let local = after + offset;
+        let midnight = local.div_euclid(DAY) * DAY;
+        let time = local - midnight;
+
+        let slot = if time <= day_start {
+            day_start
+        } else {
+            // round up to the next slot
+            day_start + (time - day_start + interval - 1) / interval * interval
+        };
+
+        let slot = if slot <= day_end {
+            midnight + slot
+        } else {
+            midnight + DAY + day_start
+        };
+
+        slot - offset
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Model;
+
+    fn schedule(utc_offset: i32) -> Model {
+        // every 45 minutes between 09:00 and 23:00
+        Model {
+            channel: -100,
+            interval: 45,
+            day_start: 9 * 60,
+            day_end: 23 * 60,
+            utc_offset,
+        }
+    }
+
+    const H: i64 = 60 * 60;
+
+    #[test]
+    /// Check [`Model::next_slot`] before first slot of the day
+    fn test_next_slot_before_start() {
+        assert_eq!(schedule(0).next_slot(3 * H), 9 * H);
+    }
+
+    #[test]
+    /// Check [`Model::next_slot`] rounds up to the slot grid
+    fn test_next_slot_rounding() {
+        let s = schedule(0);
+
+        assert_eq!(s.next_slot(9 * H), 9 * H);
+        assert_eq!(s.next_slot(9 * H + 1), 9 * H + 45 * 60);
+        assert_eq!(s.next_slot(10 * H), 10 * H + 30 * 60);
+    }
+
+    #[test]
+    /// Check [`Model::next_slot`] moves to next day after `day_end`
+    fn test_next_slot_next_day() {
+        assert_eq!(schedule(0).next_slot(23 * H + 1), 24 * H + 9 * H);
+    }
+
+    #[test]
+    /// Check [`Model::next_slot`] with non-UTC timezone
+    fn test_next_slot_timezone() {
+        // 09:00 at UTC+3 is 06:00 UTC
+        assert_eq!(schedule(180).next_slot(0), 6 * H);
+        // 00:00 UTC is 19:00 at UTC-5, so next slot is 19:30 (00:30 UTC)
+        assert_eq!(schedule(-300).next_slot(0), 30 * 60);
+    }
+}
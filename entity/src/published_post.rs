@@ -0,0 +1,78 @@
+use sea_orm::entity::prelude::*;
+use serde::Serialize;
+
+#[derive(Clone, Serialize, Debug, DeriveEntityModel)]
+#[sea_orm(table_name = "published_posts")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    /// Internal ID of published post
+    pub id: i64,
+    /// ID of user, who posted
+    pub user_id: i64,
+    #[sea_orm(nullable)]
+    /// Internal ID of source post
+    pub post_id: Option<i64>,
+    #[sea_orm(nullable)]
+    /// Internal ID of scheduled post, that was published
+    pub scheduled_post_id: Option<i64>,
+
+    /// Telegram chat where post was published
+    pub chat_id: i64,
+    /// Telegram message ids splitted by ','
+    pub message_ids: String,
+    /// Telegram message id, that contains post text
+    pub text_message_id: i32,
+    /// Whether post text is a media caption (not a text message)
+    pub text_in_caption: bool,
+
+    /// Post text
+    pub post_text: String,
+    /// Post source text
+    pub post_source: String,
+    /// Post source url
+    pub post_source_url: String,
+    /// Unix time when post was published
+    pub published_at: i64,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::telegram_user::Entity",
+        from = "Column::UserId",
+        to = "super::telegram_user::Column::Id",
+        on_delete = "Cascade"
+    )]
+    User,
+    #[sea_orm(
+        belongs_to = "super::post::Entity",
+        from = "Column::PostId",
+        to = "super::post::Column::Id",
+        on_delete = "SetNull"
+    )]
+    Post,
+}
+
+impl Related<super::telegram_user::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::User.def()
+    }
+}
+
+impl Related<super::post::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Post.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+impl Model {
+    /// Parsed telegram message ids
+    pub fn message_ids(&self) -> Vec<i32> {
+        self.message_ids
+            .split(',')
+            .flat_map(|f| f.parse())
+            .collect()
+    }
+}
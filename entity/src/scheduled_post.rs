@@ -1,4 +1,4 @@
-use sea_orm::entity::prelude::*;
+use sea_orm::{entity::prelude::*, QueryOrder};
 use serde::Serialize;
 
 #[derive(Clone, Serialize, Debug, DeriveEntityModel)]
@@ -9,15 +9,66 @@ pub struct Model {
     pub id: i64,
     /// ID of user, who posted
     pub user_id: i64,
+    #[sea_orm(nullable)]
+    /// Internal ID of source post
+    pub post_id: Option<i64>,
+    #[sea_orm(nullable)]
+    /// Internal ID of scheduled post, that this post replies to (previous post of
+    /// thread). Post waits until that post is published
+    pub reply_to_id: Option<i64>,
 
-    /// Media internal ids (`post_media`) splitted by ','
-    pub media_ids: String,
     /// Post text
     pub post_text: String,
+    /// Entities of post text, empty if text was changed by user
+    pub post_entities: super::post::PostEntities,
     /// Post source text
     pub post_source: String,
     /// Post source url
     pub post_source_url: String,
+    #[sea_orm(nullable)]
+    /// Unix time when post should be published. `None` means as soon as possible
+    pub publish_at: Option<i64>,
+
+    #[sea_orm(default_value = "pending")]
+    /// Posting status
+    pub status: PostStatus,
+    #[sea_orm(default_value = 0)]
+    /// Number of failed posting attempts
+    pub attempts: i32,
+    #[sea_orm(nullable)]
+    /// Error of last failed posting attempt
+    pub last_error: Option<String>,
+    #[sea_orm(nullable)]
+    /// Unix time of next posting attempt after failure
+    pub next_attempt_at: Option<i64>,
+    #[sea_orm(default_value = false)]
+    /// Whether channel of user already published some media of this post
+    pub duplicate: bool,
+    #[sea_orm(default_value = 0)]
+    /// Number of parts (media groups and text messages) of post, that were already sent.
+    /// Posting is resumed after them
+    pub sent_parts: i32,
+    #[sea_orm(default_value = "")]
+    /// Comma separated telegram message ids of sent parts
+    pub sent_message_ids: String,
+}
+
+#[derive(EnumIter, Debug, Clone, PartialEq, Eq, Serialize, DeriveActiveEnum)]
+#[sea_orm(rs_type = "String", db_type = "Text")]
+#[serde(rename_all = "camelCase")]
+pub enum PostStatus {
+    /// Waiting to be posted (or retried)
+    #[sea_orm(string_value = "pending")]
+    Pending,
+    /// Successfully posted
+    #[sea_orm(string_value = "sent")]
+    Sent,
+    /// Posting failed too many times, needs user action
+    #[sea_orm(string_value = "failed")]
+    Failed,
+    /// Source post was deleted, post is held back until user retries it
+    #[sea_orm(string_value = "held")]
+    Held,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -25,9 +76,12 @@ pub enum Relation {
     #[sea_orm(
         belongs_to = "super::telegram_user::Entity",
         from = "Column::UserId",
-        to = "super::telegram_user::Column::Id"
+        to = "super::telegram_user::Column::Id",
+        on_delete = "Cascade"
     )]
     User,
+    #[sea_orm(has_many = "super::scheduled_post_media::Entity")]
+    Media,
 }
 
 impl Related<super::telegram_user::Entity> for Entity {
@@ -36,4 +90,28 @@ impl Related<super::telegram_user::Entity> for Entity {
     }
 }
 
+impl Related<super::scheduled_post_media::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Media.def()
+    }
+}
+
 impl ActiveModelBehavior for ActiveModel {}
+
+impl Model {
+    /// Parsed telegram message ids of sent parts
+    pub fn sent_message_ids(&self) -> Vec<i32> {
+        self.sent_message_ids
+            .split(',')
+            .flat_map(|f| f.parse())
+            .collect()
+    }
+
+    /// Query of post media, in order of posting
+    pub fn find_media(&self) -> Select<super::post_media::Entity> {
+        super::post_media::Entity::find()
+            .inner_join(super::scheduled_post_media::Entity)
+            .filter(super::scheduled_post_media::Column::ScheduledPostId.eq(self.id))
+            .order_by_asc(super::scheduled_post_media::Column::Position)
+    }
+}
@@ -15,7 +15,7 @@ pub struct Model {
     pub power_level: i32,
     #[sea_orm(default_value = 0)]
     #[serde(skip)]
-    /// Last post ID, that user read in feed
+    /// Internal ID of last post, that user read in feed
     pub last_feed_id: i64,
 }
 
@@ -29,6 +29,8 @@ pub enum Relation {
     Posts,
     #[sea_orm(has_many = "super::follow::Entity")]
     Follows,
+    #[sea_orm(has_many = "super::published_post::Entity")]
+    PublishedPosts,
 }
 
 impl Related<super::scheduled_post::Entity> for Entity {
@@ -43,4 +45,10 @@ impl Related<super::follow::Entity> for Entity {
     }
 }
 
+impl Related<super::published_post::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::PublishedPosts.def()
+    }
+}
+
 impl ActiveModelBehavior for ActiveModel {}
@@ -0,0 +1,98 @@
+//! Raw sql helpers shared by migrations
+
+use sea_orm_migration::{
+    prelude::*,
+    sea_orm::{ConnectionTrait, Statement},
+};
+
+/// Executes raw `sql`
+pub async fn execute(manager: &SchemaManager<'_>, sql: &str) -> Result<(), DbErr> {
+    manager
+        .get_connection()
+        .execute(Statement::from_string(
+            manager.get_database_backend(),
+            sql.to_string(),
+        ))
+        .await?;
+
+    Ok(())
+}
+
+/// Executes raw `statements` in order
+pub async fn execute_all(manager: &SchemaManager<'_>, statements: &[&str]) -> Result<(), DbErr> {
+    for sql in statements {
+        execute(manager, sql).await?;
+    }
+
+    Ok(())
+}
+
+/// Disables foreign keys until [`enable_foreign_keys`]. sqlite can't change constraints
+/// of existing tables, so such tables are rebuilt (see [`rebuild_table`]), and dropping
+/// old tables must not trigger cascades
+pub async fn disable_foreign_keys(manager: &SchemaManager<'_>) -> Result<(), DbErr> {
+    execute(manager, "PRAGMA foreign_keys = OFF").await
+}
+
+/// Enables foreign keys back. Fails, if some rows violate them
+pub async fn enable_foreign_keys(manager: &SchemaManager<'_>) -> Result<(), DbErr> {
+    let violations = manager
+        .get_connection()
+        .query_all(Statement::from_string(
+            manager.get_database_backend(),
+            "PRAGMA foreign_key_check".to_string(),
+        ))
+        .await?;
+    if !violations.is_empty() {
+        return Err(DbErr::Migration(format!(
+            "{} rows violate foreign keys",
+            violations.len()
+        )));
+    }
+
+    execute(manager, "PRAGMA foreign_keys = ON").await
+}
+
+/// Replaces table `name` with table created by `statement` (without table name),
+/// copying all rows. Foreign keys must be disabled
+pub async fn rebuild_table(
+    manager: &SchemaManager<'_>,
+    name: &str,
+    mut statement: TableCreateStatement,
+) -> Result<(), DbErr> {
+    let new_name = format!("{name}_new");
+    manager
+        .create_table(statement.table(Alias::new(&new_name)).to_owned())
+        .await?;
+
+    let columns = statement
+        .get_columns()
+        .iter()
+        .map(|f| Alias::new(&f.get_column_name()))
+        .collect::<Vec<_>>();
+    let copy = Query::insert()
+        .into_table(Alias::new(&new_name))
+        .columns(columns.clone())
+        .select_from(
+            Query::select()
+                .columns(columns)
+                .from(Alias::new(name))
+                .to_owned(),
+        )
+        .map_err(|e| DbErr::Migration(e.to_string()))?
+        .to_owned();
+    let db = manager.get_connection();
+    db.execute(manager.get_database_backend().build(&copy))
+        .await?;
+
+    manager
+        .drop_table(Table::drop().table(Alias::new(name)).to_owned())
+        .await?;
+    manager
+        .rename_table(
+            Table::rename()
+                .table(Alias::new(&new_name), Alias::new(name))
+                .to_owned(),
+        )
+        .await
+}
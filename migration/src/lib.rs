@@ -1,7 +1,33 @@
 pub use sea_orm_migration::prelude::*;
 
+mod helpers;
+
 mod m20220101_000001_create_table;
 mod m20221031_135359_create_schedule_and_follows;
+mod m20221105_184210_add_publish_at;
+mod m20221109_201533_create_posting_schedules;
+mod m20221112_143027_add_scheduled_post_status;
+mod m20221116_211845_create_published_posts;
+mod m20221120_173402_add_post_entities;
+mod m20221124_120512_add_platforms;
+mod m20221128_094417_add_author_polling;
+mod m20221201_163058_add_media_details;
+mod m20221205_141203_add_threads_and_quotes;
+mod m20221209_180514_add_post_revalidation;
+mod m20221213_102840_create_author_usernames;
+mod m20221217_153720_create_scheduled_post_media;
+mod m20221221_112045_add_foreign_keys;
+mod m20221225_140311_deduplicate_post_media;
+mod m20221229_093145_add_post_posted_at;
+mod m20230103_170245_create_posts_search;
+mod m20230107_103512_add_post_external_id;
+mod m20230108_091204_order_mastodon_post_ids;
+mod m20230109_142237_order_bluesky_post_ids;
+mod m20230110_173015_read_feed_by_post_id;
+mod m20230112_120318_rename_media_url_key;
+mod m20230114_101522_add_scheduled_post_progress;
+mod m20230116_083241_add_post_quoted_only;
+mod m20230117_091533_add_post_media_content_hash;
 
 pub struct Migrator;
 
@@ -11,6 +37,30 @@ impl MigratorTrait for Migrator {
         vec![
             Box::new(m20220101_000001_create_table::Migration),
             Box::new(m20221031_135359_create_schedule_and_follows::Migration),
+            Box::new(m20221105_184210_add_publish_at::Migration),
+            Box::new(m20221109_201533_create_posting_schedules::Migration),
+            Box::new(m20221112_143027_add_scheduled_post_status::Migration),
+            Box::new(m20221116_211845_create_published_posts::Migration),
+            Box::new(m20221120_173402_add_post_entities::Migration),
+            Box::new(m20221124_120512_add_platforms::Migration),
+            Box::new(m20221128_094417_add_author_polling::Migration),
+            Box::new(m20221201_163058_add_media_details::Migration),
+            Box::new(m20221205_141203_add_threads_and_quotes::Migration),
+            Box::new(m20221209_180514_add_post_revalidation::Migration),
+            Box::new(m20221213_102840_create_author_usernames::Migration),
+            Box::new(m20221217_153720_create_scheduled_post_media::Migration),
+            Box::new(m20221221_112045_add_foreign_keys::Migration),
+            Box::new(m20221225_140311_deduplicate_post_media::Migration),
+            Box::new(m20221229_093145_add_post_posted_at::Migration),
+            Box::new(m20230103_170245_create_posts_search::Migration),
+            Box::new(m20230107_103512_add_post_external_id::Migration),
+            Box::new(m20230108_091204_order_mastodon_post_ids::Migration),
+            Box::new(m20230109_142237_order_bluesky_post_ids::Migration),
+            Box::new(m20230110_173015_read_feed_by_post_id::Migration),
+            Box::new(m20230112_120318_rename_media_url_key::Migration),
+            Box::new(m20230114_101522_add_scheduled_post_progress::Migration),
+            Box::new(m20230116_083241_add_post_quoted_only::Migration),
+            Box::new(m20230117_091533_add_post_media_content_hash::Migration),
         ]
     }
 }
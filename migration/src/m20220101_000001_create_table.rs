@@ -101,9 +101,8 @@ impl MigrationTrait for Migration {
                             .primary_key(),
                     )
                     .col(
-                        ColumnDef::new(post_media::Column::PostId)
-                            .integer()
-                            .not_null(),
+                        // moved to `post_media_links` table later
+                        ColumnDef::new(Alias::new("post_id")).integer().not_null(),
                     )
                     .col(
                         ColumnDef::new(post_media::Column::MediaType)
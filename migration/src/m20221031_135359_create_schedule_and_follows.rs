@@ -24,9 +24,8 @@ impl MigrationTrait for Migration {
                             .not_null(),
                     )
                     .col(
-                        ColumnDef::new(entity::scheduled_post::Column::MediaIds)
-                            .string()
-                            .not_null(),
+                        // moved to `scheduled_post_media` table later
+                        ColumnDef::new(Alias::new("media_ids")).string().not_null(),
                     )
                     .col(
                         ColumnDef::new(entity::scheduled_post::Column::PostText)
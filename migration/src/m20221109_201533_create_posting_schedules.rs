@@ -0,0 +1,57 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        use entity::posting_schedule;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(posting_schedule::Entity)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(posting_schedule::Column::Channel)
+                            .integer()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(posting_schedule::Column::Interval)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(posting_schedule::Column::DayStart)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(posting_schedule::Column::DayEnd)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(posting_schedule::Column::UtcOffset)
+                            .integer()
+                            .default(0)
+                            .not_null(),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(
+                Table::drop()
+                    .table(entity::posting_schedule::Entity)
+                    .to_owned(),
+            )
+            .await
+    }
+}
@@ -0,0 +1,107 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        use entity::{published_post, scheduled_post};
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(published_post::Entity)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(published_post::Column::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(published_post::Column::UserId)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(published_post::Column::PostId)
+                            .integer()
+                            .null(),
+                    )
+                    .col(
+                        ColumnDef::new(published_post::Column::ChatId)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(published_post::Column::MessageIds)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(published_post::Column::TextMessageId)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(published_post::Column::TextInCaption)
+                            .boolean()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(published_post::Column::PostText)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(published_post::Column::PostSource)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(published_post::Column::PostSourceUrl)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(published_post::Column::PublishedAt)
+                            .integer()
+                            .not_null(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(scheduled_post::Entity)
+                    .add_column(
+                        ColumnDef::new(scheduled_post::Column::PostId)
+                            .integer()
+                            .null(),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(
+                Table::drop()
+                    .table(entity::published_post::Entity)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(entity::scheduled_post::Entity)
+                    .drop_column(entity::scheduled_post::Column::PostId)
+                    .to_owned(),
+            )
+            .await
+    }
+}
@@ -0,0 +1,57 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        use entity::{post, scheduled_post};
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(post::Entity)
+                    .add_column(
+                        ColumnDef::new(post::Column::Entities)
+                            .json()
+                            .default("[]")
+                            .not_null(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(scheduled_post::Entity)
+                    .add_column(
+                        ColumnDef::new(scheduled_post::Column::PostEntities)
+                            .json()
+                            .default("[]")
+                            .not_null(),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(entity::post::Entity)
+                    .drop_column(entity::post::Column::Entities)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(entity::scheduled_post::Entity)
+                    .drop_column(entity::scheduled_post::Column::PostEntities)
+                    .to_owned(),
+            )
+            .await
+    }
+}
@@ -0,0 +1,101 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        use entity::{author, post};
+
+        let author_columns = [
+            ColumnDef::new(author::Column::Platform)
+                .string()
+                .default("twitter")
+                .not_null()
+                .to_owned(),
+            ColumnDef::new(author::Column::ExternalId)
+                .string()
+                .default("")
+                .not_null()
+                .to_owned(),
+            ColumnDef::new(author::Column::Cursor)
+                .string()
+                .null()
+                .to_owned(),
+        ];
+
+        for mut column in author_columns {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(author::Entity)
+                        .add_column(&mut column)
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(post::Entity)
+                    .add_column(
+                        ColumnDef::new(post::Column::Platform)
+                            .string()
+                            .default("twitter")
+                            .not_null(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // All existing authors are from twitter, continue their timelines from last post
+        manager
+            .exec_stmt(
+                Query::update()
+                    .table(author::Entity)
+                    .value(
+                        author::Column::ExternalId,
+                        Expr::cust("CAST(\"platform_id\" AS TEXT)"),
+                    )
+                    .value(
+                        author::Column::Cursor,
+                        Expr::cust(
+                            "(SELECT CAST(MAX(\"platform_id\") AS TEXT) FROM \"posts\" \
+                             WHERE \"posts\".\"author_id\" = \"authors\".\"id\")",
+                        ),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        use entity::{author, post};
+
+        for column in [
+            author::Column::Platform,
+            author::Column::ExternalId,
+            author::Column::Cursor,
+        ] {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(author::Entity)
+                        .drop_column(column)
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(post::Entity)
+                    .drop_column(post::Column::Platform)
+                    .to_owned(),
+            )
+            .await
+    }
+}
@@ -0,0 +1,53 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        use entity::author;
+
+        let columns = [
+            ColumnDef::new(author::Column::PollInterval)
+                .integer()
+                .default(120)
+                .not_null()
+                .to_owned(),
+            ColumnDef::new(author::Column::NextPollAt)
+                .big_integer()
+                .null()
+                .to_owned(),
+        ];
+
+        for mut column in columns {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(author::Entity)
+                        .add_column(&mut column)
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        use entity::author;
+
+        for column in [author::Column::PollInterval, author::Column::NextPollAt] {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(author::Entity)
+                        .drop_column(column)
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,65 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        use entity::post_media;
+
+        let columns = [
+            ColumnDef::new(post_media::Column::AltText)
+                .string()
+                .null()
+                .to_owned(),
+            ColumnDef::new(post_media::Column::Width)
+                .integer()
+                .null()
+                .to_owned(),
+            ColumnDef::new(post_media::Column::Height)
+                .integer()
+                .null()
+                .to_owned(),
+            ColumnDef::new(post_media::Column::DurationMs)
+                .integer()
+                .null()
+                .to_owned(),
+        ];
+
+        for mut column in columns {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(post_media::Entity)
+                        .add_column(&mut column)
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        use entity::post_media;
+
+        for column in [
+            post_media::Column::AltText,
+            post_media::Column::Width,
+            post_media::Column::Height,
+            post_media::Column::DurationMs,
+        ] {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(post_media::Entity)
+                        .drop_column(column)
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,107 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        use entity::{author, post, published_post, scheduled_post};
+
+        let statements = [
+            Table::alter()
+                .table(author::Entity)
+                .add_column(
+                    ColumnDef::new(author::Column::CollectThreads)
+                        .boolean()
+                        .default(false)
+                        .not_null(),
+                )
+                .to_owned(),
+            Table::alter()
+                .table(author::Entity)
+                .add_column(
+                    ColumnDef::new(author::Column::CollectQuotes)
+                        .boolean()
+                        .default(false)
+                        .not_null(),
+                )
+                .to_owned(),
+            Table::alter()
+                .table(post::Entity)
+                .add_column(
+                    ColumnDef::new(post::Column::ParentPostId)
+                        .big_integer()
+                        .null(),
+                )
+                .to_owned(),
+            Table::alter()
+                .table(post::Entity)
+                .add_column(
+                    ColumnDef::new(post::Column::QuotedPostId)
+                        .big_integer()
+                        .null(),
+                )
+                .to_owned(),
+            Table::alter()
+                .table(scheduled_post::Entity)
+                .add_column(
+                    ColumnDef::new(scheduled_post::Column::ReplyToId)
+                        .big_integer()
+                        .null(),
+                )
+                .to_owned(),
+            Table::alter()
+                .table(published_post::Entity)
+                .add_column(
+                    ColumnDef::new(published_post::Column::ScheduledPostId)
+                        .big_integer()
+                        .null(),
+                )
+                .to_owned(),
+        ];
+
+        for statement in statements {
+            manager.alter_table(statement).await?;
+        }
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        use entity::{author, post, published_post, scheduled_post};
+
+        let statements = [
+            Table::alter()
+                .table(author::Entity)
+                .drop_column(author::Column::CollectThreads)
+                .to_owned(),
+            Table::alter()
+                .table(author::Entity)
+                .drop_column(author::Column::CollectQuotes)
+                .to_owned(),
+            Table::alter()
+                .table(post::Entity)
+                .drop_column(post::Column::ParentPostId)
+                .to_owned(),
+            Table::alter()
+                .table(post::Entity)
+                .drop_column(post::Column::QuotedPostId)
+                .to_owned(),
+            Table::alter()
+                .table(scheduled_post::Entity)
+                .drop_column(scheduled_post::Column::ReplyToId)
+                .to_owned(),
+            Table::alter()
+                .table(published_post::Entity)
+                .drop_column(published_post::Column::ScheduledPostId)
+                .to_owned(),
+        ];
+
+        for statement in statements {
+            manager.alter_table(statement).await?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,67 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        use entity::post;
+
+        let columns = [
+            ColumnDef::new(post::Column::Deleted)
+                .boolean()
+                .default(false)
+                .not_null()
+                .to_owned(),
+            ColumnDef::new(post::Column::VersionId)
+                .big_integer()
+                .null()
+                .to_owned(),
+            ColumnDef::new(post::Column::EditHistory)
+                .json()
+                .default("[]")
+                .not_null()
+                .to_owned(),
+            ColumnDef::new(post::Column::CheckedAt)
+                .big_integer()
+                .null()
+                .to_owned(),
+        ];
+
+        for mut column in columns {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(post::Entity)
+                        .add_column(&mut column)
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        use entity::post;
+
+        for column in [
+            post::Column::Deleted,
+            post::Column::VersionId,
+            post::Column::EditHistory,
+            post::Column::CheckedAt,
+        ] {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(post::Entity)
+                        .drop_column(column)
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        Ok(())
+    }
+}
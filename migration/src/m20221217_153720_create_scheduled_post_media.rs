@@ -0,0 +1,134 @@
+use sea_orm_migration::{
+    prelude::*,
+    sea_orm::{ConnectionTrait, Statement},
+};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        use entity::{post_media, scheduled_post, scheduled_post_media};
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(scheduled_post_media::Entity)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(scheduled_post_media::Column::ScheduledPostId)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(scheduled_post_media::Column::MediaId)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(scheduled_post_media::Column::Position)
+                            .integer()
+                            .not_null(),
+                    )
+                    .primary_key(
+                        Index::create()
+                            .col(scheduled_post_media::Column::ScheduledPostId)
+                            .col(scheduled_post_media::Column::MediaId),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .from(
+                                scheduled_post_media::Entity,
+                                scheduled_post_media::Column::ScheduledPostId,
+                            )
+                            .to(scheduled_post::Entity, scheduled_post::Column::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .from(
+                                scheduled_post_media::Entity,
+                                scheduled_post_media::Column::MediaId,
+                            )
+                            .to(post_media::Entity, post_media::Column::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // move media ids of existing posts, keeping their order. Ids of media, that
+        // doesn't exist anymore, are dropped
+        let db = manager.get_connection();
+        let backend = manager.get_database_backend();
+        let rows = db
+            .query_all(Statement::from_string(
+                backend,
+                "SELECT id, media_ids FROM schedule_posts".to_string(),
+            ))
+            .await?;
+        for row in rows {
+            let id: i64 = row.try_get("", "id")?;
+            let media_ids: String = row.try_get("", "media_ids")?;
+
+            let media_ids = media_ids.split(',').flat_map(|f| f.parse::<i64>());
+            for (position, media_id) in media_ids.enumerate() {
+                db.execute(Statement::from_sql_and_values(
+                    backend,
+                    "INSERT OR IGNORE INTO scheduled_post_media \
+                     (scheduled_post_id, media_id, position) \
+                     SELECT ?, id, ? FROM post_media WHERE id = ?",
+                    [id.into(), (position as i32).into(), media_id.into()],
+                ))
+                .await?;
+            }
+        }
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(scheduled_post::Entity)
+                    .drop_column(Alias::new("media_ids"))
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(entity::scheduled_post::Entity)
+                    .add_column(
+                        ColumnDef::new(Alias::new("media_ids"))
+                            .string()
+                            .not_null()
+                            .default(""),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        let db = manager.get_connection();
+        db.execute(Statement::from_string(
+            manager.get_database_backend(),
+            "UPDATE schedule_posts SET media_ids = COALESCE(( \
+                SELECT group_concat(media_id) FROM ( \
+                    SELECT media_id FROM scheduled_post_media \
+                    WHERE scheduled_post_id = schedule_posts.id ORDER BY position \
+                ) \
+             ), '')"
+                .to_string(),
+        ))
+        .await?;
+
+        manager
+            .drop_table(
+                Table::drop()
+                    .table(entity::scheduled_post_media::Entity)
+                    .to_owned(),
+            )
+            .await
+    }
+}
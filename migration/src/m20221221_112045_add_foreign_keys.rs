@@ -0,0 +1,518 @@
+use sea_orm_migration::prelude::*;
+
+use entity::{
+    author, author_username, follow, post, post_media, published_post, scheduled_post,
+    scheduled_post_media, telegram_user,
+};
+
+use crate::helpers::{disable_foreign_keys, enable_foreign_keys, execute, rebuild_table};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+/// Removes rows, that reference deleted rows (with `ON DELETE CASCADE` semantics), and
+/// clears optional references (`ON DELETE SET NULL`). Order matters: removed posts
+/// orphan their media
+const CLEANUP: &[&str] = &[
+    "DELETE FROM follows WHERE user_id NOT IN (SELECT id FROM telegram_user) \
+     OR author_id NOT IN (SELECT id FROM authors)",
+    "DELETE FROM author_usernames WHERE author_id NOT IN (SELECT id FROM authors)",
+    "DELETE FROM posts WHERE author_id NOT IN (SELECT id FROM authors)",
+    "UPDATE posts SET parent_post_id = NULL WHERE parent_post_id NOT IN (SELECT id FROM posts)",
+    "UPDATE posts SET quoted_post_id = NULL WHERE quoted_post_id NOT IN (SELECT id FROM posts)",
+    "DELETE FROM post_media WHERE post_id NOT IN (SELECT id FROM posts)",
+    "DELETE FROM schedule_posts WHERE user_id NOT IN (SELECT id FROM telegram_user)",
+    "UPDATE schedule_posts SET post_id = NULL WHERE post_id NOT IN (SELECT id FROM posts)",
+    "UPDATE schedule_posts SET reply_to_id = NULL \
+     WHERE reply_to_id NOT IN (SELECT id FROM schedule_posts)",
+    "DELETE FROM published_posts WHERE user_id NOT IN (SELECT id FROM telegram_user)",
+    "UPDATE published_posts SET post_id = NULL WHERE post_id NOT IN (SELECT id FROM posts)",
+    "UPDATE published_posts SET scheduled_post_id = NULL \
+     WHERE scheduled_post_id NOT IN (SELECT id FROM schedule_posts)",
+    "DELETE FROM scheduled_post_media \
+     WHERE scheduled_post_id NOT IN (SELECT id FROM schedule_posts) \
+     OR media_id NOT IN (SELECT id FROM post_media)",
+];
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        disable_foreign_keys(manager).await?;
+
+        for sql in CLEANUP {
+            execute(manager, sql).await?;
+        }
+
+        for (name, table) in tables(true) {
+            rebuild_table(manager, name, table).await?;
+        }
+        for index in indexes() {
+            manager.create_index(index).await?;
+        }
+
+        enable_foreign_keys(manager).await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        disable_foreign_keys(manager).await?;
+
+        // other indexes are dropped with rebuilt tables
+        for name in [
+            "idx_authors_next_poll_at",
+            "idx_scheduled_post_media_media_id",
+        ] {
+            manager
+                .drop_index(Index::drop().name(name).to_owned())
+                .await?;
+        }
+        for (name, table) in tables(false) {
+            rebuild_table(manager, name, table).await?;
+        }
+
+        enable_foreign_keys(manager).await
+    }
+}
+
+/// Tables, that reference other tables, with or without foreign keys. Names are
+/// returned separately, since statements are built without them
+fn tables(foreign_keys: bool) -> Vec<(&'static str, TableCreateStatement)> {
+    let fk = |statement: &mut TableCreateStatement, foreign_key: &mut ForeignKeyCreateStatement| {
+        if foreign_keys {
+            statement.foreign_key(foreign_key);
+        }
+    };
+
+    let mut posts = Table::create()
+        .col(
+            ColumnDef::new(post::Column::Id)
+                .integer()
+                .not_null()
+                .auto_increment()
+                .primary_key(),
+        )
+        .col(
+            ColumnDef::new(post::Column::PlatformId)
+                .integer()
+                .unique_key()
+                .not_null(),
+        )
+        .col(ColumnDef::new(post::Column::AuthorId).integer().not_null())
+        .col(ColumnDef::new(post::Column::Text).string().not_null())
+        .col(ColumnDef::new(post::Column::SourceUrl).string().not_null())
+        .col(ColumnDef::new(post::Column::SourceText).string().not_null())
+        .col(
+            ColumnDef::new(post::Column::Entities)
+                .text()
+                .not_null()
+                .default("[]"),
+        )
+        .col(
+            ColumnDef::new(post::Column::Platform)
+                .string()
+                .not_null()
+                .default("twitter"),
+        )
+        .col(ColumnDef::new(post::Column::ParentPostId).integer().null())
+        .col(ColumnDef::new(post::Column::QuotedPostId).integer().null())
+        .col(
+            ColumnDef::new(post::Column::Deleted)
+                .boolean()
+                .not_null()
+                .default(false),
+        )
+        .col(ColumnDef::new(post::Column::VersionId).integer().null())
+        .col(
+            ColumnDef::new(post::Column::EditHistory)
+                .text()
+                .not_null()
+                .default("[]"),
+        )
+        .col(ColumnDef::new(post::Column::CheckedAt).integer().null())
+        .to_owned();
+    fk(
+        &mut posts,
+        ForeignKey::create()
+            .from(post::Entity, post::Column::AuthorId)
+            .to(author::Entity, author::Column::Id)
+            .on_delete(ForeignKeyAction::Cascade),
+    );
+    fk(
+        &mut posts,
+        ForeignKey::create()
+            .from(post::Entity, post::Column::ParentPostId)
+            .to(post::Entity, post::Column::Id)
+            .on_delete(ForeignKeyAction::SetNull),
+    );
+    fk(
+        &mut posts,
+        ForeignKey::create()
+            .from(post::Entity, post::Column::QuotedPostId)
+            .to(post::Entity, post::Column::Id)
+            .on_delete(ForeignKeyAction::SetNull),
+    );
+
+    let mut media = Table::create()
+        .col(
+            ColumnDef::new(post_media::Column::Id)
+                .integer()
+                .not_null()
+                .auto_increment()
+                .primary_key(),
+        )
+        .col(
+            // moved to `post_media_links` table later
+            ColumnDef::new(Alias::new("post_id")).integer().not_null(),
+        )
+        .col(
+            ColumnDef::new(post_media::Column::MediaType)
+                .string()
+                .not_null(),
+        )
+        .col(
+            ColumnDef::new(post_media::Column::MediaUrl)
+                .string()
+                .not_null(),
+        )
+        .col(ColumnDef::new(post_media::Column::AltText).string().null())
+        .col(ColumnDef::new(post_media::Column::Width).integer().null())
+        .col(ColumnDef::new(post_media::Column::Height).integer().null())
+        .col(
+            ColumnDef::new(post_media::Column::DurationMs)
+                .integer()
+                .null(),
+        )
+        .to_owned();
+    fk(
+        &mut media,
+        ForeignKey::create()
+            .from(post_media::Entity, Alias::new("post_id"))
+            .to(post::Entity, post::Column::Id)
+            .on_delete(ForeignKeyAction::Cascade),
+    );
+
+    let mut follows = Table::create()
+        .col(
+            ColumnDef::new(follow::Column::AuthorId)
+                .integer()
+                .not_null(),
+        )
+        .col(ColumnDef::new(follow::Column::UserId).integer().not_null())
+        .primary_key(
+            Index::create()
+                .col(follow::Column::AuthorId)
+                .col(follow::Column::UserId),
+        )
+        .to_owned();
+    fk(
+        &mut follows,
+        ForeignKey::create()
+            .from(follow::Entity, follow::Column::AuthorId)
+            .to(author::Entity, author::Column::Id)
+            .on_delete(ForeignKeyAction::Cascade),
+    );
+    fk(
+        &mut follows,
+        ForeignKey::create()
+            .from(follow::Entity, follow::Column::UserId)
+            .to(telegram_user::Entity, telegram_user::Column::Id)
+            .on_delete(ForeignKeyAction::Cascade),
+    );
+
+    let mut usernames = Table::create()
+        .col(
+            ColumnDef::new(author_username::Column::Id)
+                .integer()
+                .not_null()
+                .auto_increment()
+                .primary_key(),
+        )
+        .col(
+            ColumnDef::new(author_username::Column::AuthorId)
+                .integer()
+                .not_null(),
+        )
+        .col(
+            ColumnDef::new(author_username::Column::Username)
+                .string()
+                .not_null(),
+        )
+        .col(
+            ColumnDef::new(author_username::Column::ReplacedAt)
+                .integer()
+                .not_null(),
+        )
+        .to_owned();
+    fk(
+        &mut usernames,
+        ForeignKey::create()
+            .from(author_username::Entity, author_username::Column::AuthorId)
+            .to(author::Entity, author::Column::Id)
+            .on_delete(ForeignKeyAction::Cascade),
+    );
+
+    let mut scheduled = Table::create()
+        .col(
+            ColumnDef::new(scheduled_post::Column::Id)
+                .integer()
+                .not_null()
+                .auto_increment()
+                .primary_key(),
+        )
+        .col(
+            ColumnDef::new(scheduled_post::Column::UserId)
+                .integer()
+                .not_null(),
+        )
+        .col(
+            ColumnDef::new(scheduled_post::Column::PostText)
+                .string()
+                .not_null(),
+        )
+        .col(
+            ColumnDef::new(scheduled_post::Column::PostSource)
+                .string()
+                .not_null(),
+        )
+        .col(
+            ColumnDef::new(scheduled_post::Column::PostSourceUrl)
+                .string()
+                .not_null(),
+        )
+        .col(
+            ColumnDef::new(scheduled_post::Column::PublishAt)
+                .integer()
+                .null(),
+        )
+        .col(
+            ColumnDef::new(scheduled_post::Column::Status)
+                .string()
+                .not_null()
+                .default("pending"),
+        )
+        .col(
+            ColumnDef::new(scheduled_post::Column::Attempts)
+                .integer()
+                .not_null()
+                .default(0),
+        )
+        .col(
+            ColumnDef::new(scheduled_post::Column::LastError)
+                .string()
+                .null(),
+        )
+        .col(
+            ColumnDef::new(scheduled_post::Column::NextAttemptAt)
+                .integer()
+                .null(),
+        )
+        .col(
+            ColumnDef::new(scheduled_post::Column::PostId)
+                .integer()
+                .null(),
+        )
+        .col(
+            ColumnDef::new(scheduled_post::Column::PostEntities)
+                .text()
+                .not_null()
+                .default("[]"),
+        )
+        .col(
+            ColumnDef::new(scheduled_post::Column::ReplyToId)
+                .integer()
+                .null(),
+        )
+        .to_owned();
+    fk(
+        &mut scheduled,
+        ForeignKey::create()
+            .from(scheduled_post::Entity, scheduled_post::Column::UserId)
+            .to(telegram_user::Entity, telegram_user::Column::Id)
+            .on_delete(ForeignKeyAction::Cascade),
+    );
+    fk(
+        &mut scheduled,
+        ForeignKey::create()
+            .from(scheduled_post::Entity, scheduled_post::Column::PostId)
+            .to(post::Entity, post::Column::Id)
+            .on_delete(ForeignKeyAction::SetNull),
+    );
+    fk(
+        &mut scheduled,
+        ForeignKey::create()
+            .from(scheduled_post::Entity, scheduled_post::Column::ReplyToId)
+            .to(scheduled_post::Entity, scheduled_post::Column::Id)
+            .on_delete(ForeignKeyAction::SetNull),
+    );
+
+    let mut published = Table::create()
+        .col(
+            ColumnDef::new(published_post::Column::Id)
+                .integer()
+                .not_null()
+                .auto_increment()
+                .primary_key(),
+        )
+        .col(
+            ColumnDef::new(published_post::Column::UserId)
+                .integer()
+                .not_null(),
+        )
+        .col(
+            ColumnDef::new(published_post::Column::PostId)
+                .integer()
+                .null(),
+        )
+        .col(
+            ColumnDef::new(published_post::Column::ChatId)
+                .integer()
+                .not_null(),
+        )
+        .col(
+            ColumnDef::new(published_post::Column::MessageIds)
+                .string()
+                .not_null(),
+        )
+        .col(
+            ColumnDef::new(published_post::Column::TextMessageId)
+                .integer()
+                .not_null(),
+        )
+        .col(
+            ColumnDef::new(published_post::Column::TextInCaption)
+                .boolean()
+                .not_null(),
+        )
+        .col(
+            ColumnDef::new(published_post::Column::PostText)
+                .string()
+                .not_null(),
+        )
+        .col(
+            ColumnDef::new(published_post::Column::PostSource)
+                .string()
+                .not_null(),
+        )
+        .col(
+            ColumnDef::new(published_post::Column::PostSourceUrl)
+                .string()
+                .not_null(),
+        )
+        .col(
+            ColumnDef::new(published_post::Column::PublishedAt)
+                .integer()
+                .not_null(),
+        )
+        .col(
+            ColumnDef::new(published_post::Column::ScheduledPostId)
+                .integer()
+                .null(),
+        )
+        .to_owned();
+    fk(
+        &mut published,
+        ForeignKey::create()
+            .from(published_post::Entity, published_post::Column::UserId)
+            .to(telegram_user::Entity, telegram_user::Column::Id)
+            .on_delete(ForeignKeyAction::Cascade),
+    );
+    fk(
+        &mut published,
+        ForeignKey::create()
+            .from(published_post::Entity, published_post::Column::PostId)
+            .to(post::Entity, post::Column::Id)
+            .on_delete(ForeignKeyAction::SetNull),
+    );
+    fk(
+        &mut published,
+        ForeignKey::create()
+            .from(
+                published_post::Entity,
+                published_post::Column::ScheduledPostId,
+            )
+            .to(scheduled_post::Entity, scheduled_post::Column::Id)
+            .on_delete(ForeignKeyAction::SetNull),
+    );
+
+    vec![
+        ("posts", posts),
+        ("post_media", media),
+        ("follows", follows),
+        ("author_usernames", usernames),
+        ("schedule_posts", scheduled),
+        ("published_posts", published),
+    ]
+}
+
+/// Indexes of columns, used by feed, collector and posting queries (and by foreign key
+/// cascades)
+fn indexes() -> Vec<IndexCreateStatement> {
+    vec![
+        Index::create()
+            .name("idx_posts_author_id")
+            .table(post::Entity)
+            .col(post::Column::AuthorId)
+            .col(post::Column::PlatformId)
+            .to_owned(),
+        Index::create()
+            .name("idx_posts_parent_post_id")
+            .table(post::Entity)
+            .col(post::Column::ParentPostId)
+            .to_owned(),
+        Index::create()
+            .name("idx_post_media_post_id")
+            .table(post_media::Entity)
+            .col(Alias::new("post_id"))
+            .to_owned(),
+        Index::create()
+            .name("idx_follows_user_id")
+            .table(follow::Entity)
+            .col(follow::Column::UserId)
+            .to_owned(),
+        Index::create()
+            .name("idx_author_usernames_author_id")
+            .table(author_username::Entity)
+            .col(author_username::Column::AuthorId)
+            .to_owned(),
+        Index::create()
+            .name("idx_author_usernames_username")
+            .table(author_username::Entity)
+            .col(author_username::Column::Username)
+            .to_owned(),
+        Index::create()
+            .name("idx_authors_next_poll_at")
+            .table(author::Entity)
+            .col(author::Column::NextPollAt)
+            .to_owned(),
+        Index::create()
+            .name("idx_schedule_posts_user_id")
+            .table(scheduled_post::Entity)
+            .col(scheduled_post::Column::UserId)
+            .to_owned(),
+        Index::create()
+            .name("idx_schedule_posts_post_id")
+            .table(scheduled_post::Entity)
+            .col(scheduled_post::Column::PostId)
+            .to_owned(),
+        Index::create()
+            .name("idx_schedule_posts_status")
+            .table(scheduled_post::Entity)
+            .col(scheduled_post::Column::Status)
+            .col(scheduled_post::Column::PublishAt)
+            .to_owned(),
+        Index::create()
+            .name("idx_published_posts_user_id")
+            .table(published_post::Entity)
+            .col(published_post::Column::UserId)
+            .to_owned(),
+        Index::create()
+            .name("idx_published_posts_scheduled_post_id")
+            .table(published_post::Entity)
+            .col(published_post::Column::ScheduledPostId)
+            .to_owned(),
+        Index::create()
+            .name("idx_scheduled_post_media_media_id")
+            .table(scheduled_post_media::Entity)
+            .col(scheduled_post_media::Column::MediaId)
+            .to_owned(),
+    ]
+}
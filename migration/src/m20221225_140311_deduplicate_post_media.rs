@@ -0,0 +1,303 @@
+use std::collections::{HashMap, HashSet};
+
+use sea_orm_migration::{
+    prelude::*,
+    sea_orm::{ConnectionTrait, Statement},
+};
+
+use entity::{post, post_media, post_media_link, scheduled_post};
+
+use crate::helpers::{disable_foreign_keys, enable_foreign_keys, execute};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+/// Columns of `post_media`, that are kept as is
+const MEDIA_COLUMNS: &str = "media_type, media_url, alt_text, width, height, duration_ms";
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // `post_media` is rebuilt
+        disable_foreign_keys(manager).await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(post_media_link::Entity)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(post_media_link::Column::PostId)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(post_media_link::Column::MediaId)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(post_media_link::Column::Position)
+                            .integer()
+                            .not_null(),
+                    )
+                    .primary_key(
+                        Index::create()
+                            .col(post_media_link::Column::PostId)
+                            .col(post_media_link::Column::MediaId),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .from(post_media_link::Entity, post_media_link::Column::PostId)
+                            .to(post::Entity, post::Column::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .from(post_media_link::Entity, post_media_link::Column::MediaId)
+                            .to(post_media::Entity, post_media::Column::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        let mut media = media_table();
+        media.col(
+            ColumnDef::new(Alias::new("content_hash"))
+                .string()
+                .unique_key()
+                .not_null(),
+        );
+        manager
+            .create_table(media.table(Alias::new("post_media_new")).to_owned())
+            .await?;
+
+        // first media with the same url key is kept, other ones are replaced by it in
+        // posts and scheduled posts. The column is renamed to `url_key` later
+        let db = manager.get_connection();
+        let backend = manager.get_database_backend();
+        let rows = db
+            .query_all(Statement::from_string(
+                backend,
+                "SELECT id, post_id, media_url FROM post_media ORDER BY post_id, id".to_string(),
+            ))
+            .await?;
+        let mut kept = HashMap::<String, i64>::new();
+        let mut positions = HashMap::new();
+        for row in rows {
+            let id: i64 = row.try_get("", "id")?;
+            let post_id: i64 = row.try_get("", "post_id")?;
+            let media_url: String = row.try_get("", "media_url")?;
+
+            let content_hash = post_media::url_key(&media_url);
+            let kept_id = match kept.get(&content_hash) {
+                Some(&kept_id) => {
+                    db.execute(Statement::from_sql_and_values(
+                        backend,
+                        "UPDATE OR IGNORE scheduled_post_media SET media_id = ? WHERE media_id = ?",
+                        [kept_id.into(), id.into()],
+                    ))
+                    .await?;
+                    // scheduled post already had kept media
+                    db.execute(Statement::from_sql_and_values(
+                        backend,
+                        "DELETE FROM scheduled_post_media WHERE media_id = ?",
+                        [id.into()],
+                    ))
+                    .await?;
+
+                    kept_id
+                }
+                None => {
+                    db.execute(Statement::from_sql_and_values(
+                        backend,
+                        &format!(
+                            "INSERT INTO post_media_new (id, content_hash, {MEDIA_COLUMNS}) \
+                             SELECT id, ?, {MEDIA_COLUMNS} FROM post_media WHERE id = ?"
+                        ),
+                        [content_hash.clone().into(), id.into()],
+                    ))
+                    .await?;
+                    kept.insert(content_hash, id);
+
+                    id
+                }
+            };
+
+            let position = positions.entry(post_id).or_insert(0);
+            let res = db
+                .execute(Statement::from_sql_and_values(
+                    backend,
+                    "INSERT OR IGNORE INTO post_media_links (post_id, media_id, position) \
+                     VALUES (?, ?, ?)",
+                    [post_id.into(), kept_id.into(), (*position).into()],
+                ))
+                .await?;
+            *position += res.rows_affected() as i32;
+        }
+
+        replace_media_table(manager).await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_post_media_links_media_id")
+                    .table(post_media_link::Entity)
+                    .col(post_media_link::Column::MediaId)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(scheduled_post::Entity)
+                    .add_column(
+                        ColumnDef::new(scheduled_post::Column::Duplicate)
+                            .boolean()
+                            .not_null()
+                            .default(false),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        enable_foreign_keys(manager).await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        disable_foreign_keys(manager).await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(scheduled_post::Entity)
+                    .drop_column(scheduled_post::Column::Duplicate)
+                    .to_owned(),
+            )
+            .await?;
+
+        let mut media = media_table();
+        media
+            .col(ColumnDef::new(Alias::new("post_id")).integer().not_null())
+            .foreign_key(
+                ForeignKey::create()
+                    .from(Alias::new("post_media_new"), Alias::new("post_id"))
+                    .to(post::Entity, post::Column::Id)
+                    .on_delete(ForeignKeyAction::Cascade),
+            );
+        manager
+            .create_table(media.table(Alias::new("post_media_new")).to_owned())
+            .await?;
+
+        // every post gets own copy of shared media. First copy keeps id, so scheduled
+        // posts still reference it. Media without posts is dropped
+        let db = manager.get_connection();
+        let backend = manager.get_database_backend();
+        let rows = db
+            .query_all(Statement::from_string(
+                backend,
+                "SELECT post_id, media_id FROM post_media_links ORDER BY media_id, post_id"
+                    .to_string(),
+            ))
+            .await?;
+        let mut copied = HashSet::new();
+        let mut extra = vec![];
+        for row in rows {
+            let post_id: i64 = row.try_get("", "post_id")?;
+            let media_id: i64 = row.try_get("", "media_id")?;
+
+            if !copied.insert(media_id) {
+                extra.push((post_id, media_id));
+                continue;
+            }
+            db.execute(Statement::from_sql_and_values(
+                backend,
+                &format!(
+                    "INSERT INTO post_media_new (id, post_id, {MEDIA_COLUMNS}) \
+                     SELECT id, ?, {MEDIA_COLUMNS} FROM post_media WHERE id = ?"
+                ),
+                [post_id.into(), media_id.into()],
+            ))
+            .await?;
+        }
+        // after all kept ids are taken
+        for (post_id, media_id) in extra {
+            db.execute(Statement::from_sql_and_values(
+                backend,
+                &format!(
+                    "INSERT INTO post_media_new (post_id, {MEDIA_COLUMNS}) \
+                     SELECT ?, {MEDIA_COLUMNS} FROM post_media WHERE id = ?"
+                ),
+                [post_id.into(), media_id.into()],
+            ))
+            .await?;
+        }
+
+        replace_media_table(manager).await?;
+        execute(
+            manager,
+            "DELETE FROM scheduled_post_media WHERE media_id NOT IN (SELECT id FROM post_media)",
+        )
+        .await?;
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_post_media_post_id")
+                    .table(post_media::Entity)
+                    .col(Alias::new("post_id"))
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .drop_table(Table::drop().table(post_media_link::Entity).to_owned())
+            .await?;
+
+        enable_foreign_keys(manager).await
+    }
+}
+
+/// Common columns of old and new `post_media` table (without table name)
+fn media_table() -> TableCreateStatement {
+    Table::create()
+        .col(
+            ColumnDef::new(post_media::Column::Id)
+                .integer()
+                .not_null()
+                .auto_increment()
+                .primary_key(),
+        )
+        .col(
+            ColumnDef::new(post_media::Column::MediaType)
+                .string()
+                .not_null(),
+        )
+        .col(
+            ColumnDef::new(post_media::Column::MediaUrl)
+                .string()
+                .not_null(),
+        )
+        .col(ColumnDef::new(post_media::Column::AltText).string().null())
+        .col(ColumnDef::new(post_media::Column::Width).integer().null())
+        .col(ColumnDef::new(post_media::Column::Height).integer().null())
+        .col(
+            ColumnDef::new(post_media::Column::DurationMs)
+                .integer()
+                .null(),
+        )
+        .to_owned()
+}
+
+/// Replaces `post_media` table with filled `post_media_new`
+async fn replace_media_table(manager: &SchemaManager<'_>) -> Result<(), DbErr> {
+    manager
+        .drop_table(Table::drop().table(post_media::Entity).to_owned())
+        .await?;
+    manager
+        .rename_table(
+            Table::rename()
+                .table(Alias::new("post_media_new"), post_media::Entity)
+                .to_owned(),
+        )
+        .await
+}
@@ -0,0 +1,57 @@
+use sea_orm_migration::prelude::*;
+
+use crate::helpers::execute;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        use entity::post;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(post::Entity)
+                    .add_column(ColumnDef::new(post::Column::PostedAt).big_integer().null())
+                    .to_owned(),
+            )
+            .await?;
+
+        // time of tweets is known from their ids (see `snowflake_time`), other posts are
+        // left without it
+        execute(
+            manager,
+            "UPDATE posts SET posted_at = ((platform_id >> 22) + 1288834974657) / 1000 \
+             WHERE platform = 'twitter'",
+        )
+        .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_posts_posted_at")
+                    .table(post::Entity)
+                    .col(post::Column::PostedAt)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        use entity::post;
+
+        manager
+            .drop_index(Index::drop().name("idx_posts_posted_at").to_owned())
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(post::Entity)
+                    .drop_column(post::Column::PostedAt)
+                    .to_owned(),
+            )
+            .await
+    }
+}
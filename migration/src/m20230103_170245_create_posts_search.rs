@@ -0,0 +1,47 @@
+use sea_orm_migration::prelude::*;
+
+use crate::helpers::execute_all;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+/// Full-text index of post texts. It doesn't store texts itself (reads them from `posts`),
+/// so triggers keep it in sync with `posts`
+const UP: &[&str] = &[
+    "CREATE VIRTUAL TABLE posts_search USING fts5( \
+        text, content = 'posts', content_rowid = 'id', \
+        tokenize = 'unicode61 remove_diacritics 2' \
+     )",
+    "CREATE TRIGGER posts_search_insert AFTER INSERT ON posts BEGIN \
+        INSERT INTO posts_search (rowid, text) VALUES (new.id, new.text); \
+     END",
+    "CREATE TRIGGER posts_search_delete AFTER DELETE ON posts BEGIN \
+        INSERT INTO posts_search (posts_search, rowid, text) \
+        VALUES ('delete', old.id, old.text); \
+     END",
+    "CREATE TRIGGER posts_search_update AFTER UPDATE OF text ON posts BEGIN \
+        INSERT INTO posts_search (posts_search, rowid, text) \
+        VALUES ('delete', old.id, old.text); \
+        INSERT INTO posts_search (rowid, text) VALUES (new.id, new.text); \
+     END",
+    // index already collected posts
+    "INSERT INTO posts_search (posts_search) VALUES ('rebuild')",
+];
+
+const DOWN: &[&str] = &[
+    "DROP TRIGGER posts_search_update",
+    "DROP TRIGGER posts_search_delete",
+    "DROP TRIGGER posts_search_insert",
+    "DROP TABLE posts_search",
+];
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        execute_all(manager, UP).await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        execute_all(manager, DOWN).await
+    }
+}
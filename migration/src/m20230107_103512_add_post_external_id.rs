@@ -0,0 +1,201 @@
+use sea_orm_migration::prelude::*;
+
+use entity::{author, post};
+
+use crate::helpers::{disable_foreign_keys, enable_foreign_keys, execute, rebuild_table};
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+/// Search triggers of `posts`, that are dropped with rebuilt table
+const TRIGGERS: &[&str] = &[
+    "CREATE TRIGGER posts_search_insert AFTER INSERT ON posts BEGIN \
+        INSERT INTO posts_search (rowid, text) VALUES (new.id, new.text); \
+     END",
+    "CREATE TRIGGER posts_search_delete AFTER DELETE ON posts BEGIN \
+        INSERT INTO posts_search (posts_search, rowid, text) \
+        VALUES ('delete', old.id, old.text); \
+     END",
+    "CREATE TRIGGER posts_search_update AFTER UPDATE OF text ON posts BEGIN \
+        INSERT INTO posts_search (posts_search, rowid, text) \
+        VALUES ('delete', old.id, old.text); \
+        INSERT INTO posts_search (rowid, text) VALUES (new.id, new.text); \
+     END",
+];
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // platform ids of posts were unique, so they are their identity. Posts of
+        // platforms with hashed ids get time-ordered platform ids from publication time
+        // (or insertion order, if time is unknown)
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(post::Entity)
+                    .add_column(
+                        ColumnDef::new(post::Column::ExternalId)
+                            .string()
+                            .not_null()
+                            .default(""),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        execute(manager, "UPDATE posts SET external_id = platform_id").await?;
+        execute(
+            manager,
+            "UPDATE posts SET platform_id = (COALESCE(posted_at, 0) << 20) | (id & 1048575) \
+             WHERE platform = 'rss'",
+        )
+        .await?;
+
+        // sqlite can't drop unique constraint of column, so table is rebuilt
+        disable_foreign_keys(manager).await?;
+        rebuild_table(manager, "posts", posts(true)).await?;
+        for index in indexes(true) {
+            manager.create_index(index).await?;
+        }
+        for sql in TRIGGERS {
+            execute(manager, sql).await?;
+        }
+        enable_foreign_keys(manager).await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // hashed ids are kept in `external_id`. Fails, if posts of different platforms
+        // share platform id
+        execute(
+            manager,
+            "UPDATE posts SET platform_id = CAST(external_id AS INTEGER) \
+             WHERE platform = 'rss'",
+        )
+        .await?;
+        disable_foreign_keys(manager).await?;
+        rebuild_table(manager, "posts", posts(false)).await?;
+        for index in indexes(false) {
+            manager.create_index(index).await?;
+        }
+        for sql in TRIGGERS {
+            execute(manager, sql).await?;
+        }
+        enable_foreign_keys(manager).await
+    }
+}
+
+/// `posts` with `external_id` and not unique `platform_id`, or as it was before
+fn posts(external_id: bool) -> TableCreateStatement {
+    let mut platform_id = ColumnDef::new(post::Column::PlatformId);
+    platform_id.integer().not_null();
+    if !external_id {
+        platform_id.unique_key();
+    }
+
+    let mut posts = Table::create()
+        .col(
+            ColumnDef::new(post::Column::Id)
+                .integer()
+                .not_null()
+                .auto_increment()
+                .primary_key(),
+        )
+        .col(&mut platform_id)
+        .col(ColumnDef::new(post::Column::AuthorId).integer().not_null())
+        .col(ColumnDef::new(post::Column::Text).string().not_null())
+        .col(ColumnDef::new(post::Column::SourceUrl).string().not_null())
+        .col(ColumnDef::new(post::Column::SourceText).string().not_null())
+        .col(
+            ColumnDef::new(post::Column::Entities)
+                .text()
+                .not_null()
+                .default("[]"),
+        )
+        .col(
+            ColumnDef::new(post::Column::Platform)
+                .string()
+                .not_null()
+                .default("twitter"),
+        )
+        .col(ColumnDef::new(post::Column::ParentPostId).integer().null())
+        .col(ColumnDef::new(post::Column::QuotedPostId).integer().null())
+        .col(
+            ColumnDef::new(post::Column::Deleted)
+                .boolean()
+                .not_null()
+                .default(false),
+        )
+        .col(ColumnDef::new(post::Column::VersionId).integer().null())
+        .col(
+            ColumnDef::new(post::Column::EditHistory)
+                .text()
+                .not_null()
+                .default("[]"),
+        )
+        .col(ColumnDef::new(post::Column::CheckedAt).integer().null())
+        .col(ColumnDef::new(post::Column::PostedAt).big_integer().null())
+        .foreign_key(
+            ForeignKey::create()
+                .from(post::Entity, post::Column::AuthorId)
+                .to(author::Entity, author::Column::Id)
+                .on_delete(ForeignKeyAction::Cascade),
+        )
+        .foreign_key(
+            ForeignKey::create()
+                .from(post::Entity, post::Column::ParentPostId)
+                .to(post::Entity, post::Column::Id)
+                .on_delete(ForeignKeyAction::SetNull),
+        )
+        .foreign_key(
+            ForeignKey::create()
+                .from(post::Entity, post::Column::QuotedPostId)
+                .to(post::Entity, post::Column::Id)
+                .on_delete(ForeignKeyAction::SetNull),
+        )
+        .to_owned();
+    if external_id {
+        posts.col(ColumnDef::new(post::Column::ExternalId).string().not_null());
+    }
+
+    posts
+}
+
+fn indexes(external_id: bool) -> Vec<IndexCreateStatement> {
+    let mut indexes = vec![
+        Index::create()
+            .name("idx_posts_author_id")
+            .table(post::Entity)
+            .col(post::Column::AuthorId)
+            .col(post::Column::PlatformId)
+            .to_owned(),
+        Index::create()
+            .name("idx_posts_parent_post_id")
+            .table(post::Entity)
+            .col(post::Column::ParentPostId)
+            .to_owned(),
+        Index::create()
+            .name("idx_posts_posted_at")
+            .table(post::Entity)
+            .col(post::Column::PostedAt)
+            .to_owned(),
+    ];
+    if external_id {
+        indexes.push(
+            Index::create()
+                .name("idx_posts_external_id")
+                .table(post::Entity)
+                .col(post::Column::Platform)
+                .col(post::Column::ExternalId)
+                .unique()
+                .to_owned(),
+        );
+        indexes.push(
+            Index::create()
+                .name("idx_posts_platform_id")
+                .table(post::Entity)
+                .col(post::Column::PlatformId)
+                .to_owned(),
+        );
+    }
+
+    indexes
+}
@@ -0,0 +1,31 @@
+use sea_orm_migration::prelude::*;
+
+use crate::helpers::execute;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // mastodon posts had hashed platform ids. Ids of statuses are not stored, but
+        // they are built the same way: milliseconds of creation and sequence
+        execute(
+            manager,
+            "UPDATE posts SET platform_id = \
+             ((COALESCE(posted_at, 0) * 1000) << 16) | (id & 65535) \
+             WHERE platform = 'mastodon'",
+        )
+        .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // hashed ids are kept in `external_id`
+        execute(
+            manager,
+            "UPDATE posts SET platform_id = CAST(external_id AS INTEGER) \
+             WHERE platform = 'mastodon'",
+        )
+        .await
+    }
+}
@@ -0,0 +1,31 @@
+use sea_orm_migration::prelude::*;
+
+use crate::helpers::execute;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // bluesky posts had hashed platform ids. Their TIDs are not stored, but they
+        // are built the same way: microseconds of creation and clock id
+        execute(
+            manager,
+            "UPDATE posts SET platform_id = \
+             ((COALESCE(posted_at, 0) * 1000000) << 10) | (id & 1023) \
+             WHERE platform = 'bluesky'",
+        )
+        .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // hashed ids are kept in `external_id`
+        execute(
+            manager,
+            "UPDATE posts SET platform_id = CAST(external_id AS INTEGER) \
+             WHERE platform = 'bluesky'",
+        )
+        .await
+    }
+}
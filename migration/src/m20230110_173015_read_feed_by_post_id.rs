@@ -0,0 +1,33 @@
+use sea_orm_migration::prelude::*;
+
+use crate::helpers::execute;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // feed was read by platform ids, that were twitter ids in practice. Users keep
+        // tweets they have read, posts of other platforms may show up once again
+        execute(
+            manager,
+            "UPDATE telegram_user SET last_feed_id = COALESCE(( \
+                SELECT MAX(id) FROM posts \
+                WHERE platform = 'twitter' AND platform_id <= last_feed_id \
+             ), 0)",
+        )
+        .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        execute(
+            manager,
+            "UPDATE telegram_user SET last_feed_id = COALESCE(( \
+                SELECT MAX(platform_id) FROM posts \
+                WHERE platform = 'twitter' AND id <= last_feed_id \
+             ), 0)",
+        )
+        .await
+    }
+}
@@ -0,0 +1,60 @@
+use sea_orm_migration::{
+    prelude::*,
+    sea_orm::{ConnectionTrait, Statement},
+};
+
+use entity::post_media;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(post_media::Entity)
+                    .rename_column(Alias::new("content_hash"), post_media::Column::UrlKey)
+                    .to_owned(),
+            )
+            .await?;
+
+        // keys of media on other hosts than twitter now keep query. Media, that was
+        // already shared by urls with different queries, stays shared
+        let db = manager.get_connection();
+        let backend = manager.get_database_backend();
+        let rows = db
+            .query_all(Statement::from_string(
+                backend,
+                "SELECT id, media_url FROM post_media".to_string(),
+            ))
+            .await?;
+        for row in rows {
+            let id: i64 = row.try_get("", "id")?;
+            let media_url: String = row.try_get("", "media_url")?;
+
+            db.execute(Statement::from_sql_and_values(
+                backend,
+                "UPDATE post_media SET url_key = ? WHERE id = ?",
+                [post_media::url_key(&media_url).into(), id.into()],
+            ))
+            .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // keys of twitter media are the same. Other media keeps its key, so the same url
+        // may be stored once again
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(post_media::Entity)
+                    .rename_column(post_media::Column::UrlKey, Alias::new("content_hash"))
+                    .to_owned(),
+            )
+            .await
+    }
+}
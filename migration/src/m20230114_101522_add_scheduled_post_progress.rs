@@ -0,0 +1,50 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        use entity::scheduled_post::{Column, Entity};
+
+        // SQLite allows only one column per ALTER TABLE
+        let columns = [
+            ColumnDef::new(Column::SentParts)
+                .integer()
+                .default(0)
+                .not_null()
+                .to_owned(),
+            ColumnDef::new(Column::SentMessageIds)
+                .string()
+                .default("")
+                .not_null()
+                .to_owned(),
+        ];
+
+        for mut column in columns {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(Entity)
+                        .add_column(&mut column)
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        use entity::scheduled_post::{Column, Entity};
+
+        for column in [Column::SentParts, Column::SentMessageIds] {
+            manager
+                .alter_table(Table::alter().table(Entity).drop_column(column).to_owned())
+                .await?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,72 @@
+use sea_orm_migration::prelude::*;
+
+use crate::helpers::execute;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+/// Whether tweet `posts` was posted by author `authors`, judging by its url
+const POSTED_BY: &str = "posts.source_url = \
+    'https://twitter.com/' || authors.username || '/status/' || posts.external_id \
+    COLLATE NOCASE";
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        use entity::post;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(post::Entity)
+                    .add_column(
+                        ColumnDef::new(post::Column::QuotedOnly)
+                            .boolean()
+                            .not_null()
+                            .default(false),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // quoted tweets were stored with author of quoting tweet
+        execute(
+            manager,
+            &format!(
+                "UPDATE posts SET quoted_only = 1 \
+                 WHERE platform = 'twitter' \
+                 AND id IN (SELECT quoted_post_id FROM posts) \
+                 AND NOT EXISTS ( \
+                    SELECT 1 FROM authors WHERE authors.id = posts.author_id AND {POSTED_BY} \
+                 )"
+            ),
+        )
+        .await?;
+        execute(
+            manager,
+            &format!(
+                "UPDATE posts SET quoted_only = 0, author_id = ( \
+                    SELECT authors.id FROM authors \
+                    WHERE authors.platform = 'twitter' AND {POSTED_BY} \
+                 ) \
+                 WHERE quoted_only = 1 AND EXISTS ( \
+                    SELECT 1 FROM authors WHERE authors.platform = 'twitter' AND {POSTED_BY} \
+                 )"
+            ),
+        )
+        .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        use entity::post;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(post::Entity)
+                    .drop_column(post::Column::QuotedOnly)
+                    .to_owned(),
+            )
+            .await
+    }
+}
@@ -0,0 +1,51 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        use entity::post_media;
+
+        // hashes of already stored media are filled by posting worker, when it needs them
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(post_media::Entity)
+                    .add_column(
+                        ColumnDef::new(post_media::Column::ContentHash)
+                            .string()
+                            .null(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_post_media_content_hash")
+                    .table(post_media::Entity)
+                    .col(post_media::Column::ContentHash)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        use entity::post_media;
+
+        manager
+            .drop_index(Index::drop().name("idx_post_media_content_hash").to_owned())
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(post_media::Entity)
+                    .drop_column(post_media::Column::ContentHash)
+                    .to_owned(),
+            )
+            .await
+    }
+}
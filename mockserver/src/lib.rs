@@ -0,0 +1,190 @@
+//! Minimal HTTP server serving canned responses, used in place of real APIs in tests.
+//! Responses of real APIs are kept in `fixtures`, see [`fixture`]
+
+use std::{
+    fs,
+    path::Path,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+};
+
+use telegrambot::teloxide::Bot;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpListener,
+};
+
+/// Returns content of fixture file, like `fixture("twitter/user.json")`. Panics if there
+/// is no such file
+pub fn fixture(name: &str) -> String {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("fixtures")
+        .join(name);
+
+    fs::read_to_string(&path)
+        .unwrap_or_else(|e| panic!("Failed to read fixture {}: {e}", path.display()))
+}
+
+/// Canned response for requests, which path is path of `path` and which query contains
+/// all parameters of query of `path` (in any order)
+pub struct MockRoute {
+    pub path: &'static str,
+    pub status: u16,
+    pub body: String,
+    pub headers: Vec<(&'static str, String)>,
+    /// Number of requests, that route responds to. Next requests go to next routes
+    pub times: Option<usize>,
+    hits: AtomicUsize,
+}
+
+pub struct MockServer {
+    /// Base url of server, like `http://127.0.0.1:1234`
+    pub url: String,
+    requests: Arc<Mutex<Vec<MockRequest>>>,
+}
+
+/// Request received by [`MockServer`]
+#[derive(Clone)]
+struct MockRequest {
+    /// Path with query
+    path: String,
+    body: String,
+}
+
+impl MockRoute {
+    pub fn new(path: &'static str, status: u16, body: impl Into<String>) -> Self {
+        Self {
+            path,
+            status,
+            body: body.into(),
+            headers: vec![],
+            times: None,
+            hits: AtomicUsize::new(0),
+        }
+    }
+
+    /// Responds only to first `times` matching requests
+    pub fn times(mut self, times: usize) -> Self {
+        self.times = Some(times);
+        self
+    }
+
+    /// Adds header to response
+    pub fn header(mut self, name: &'static str, value: impl ToString) -> Self {
+        self.headers.push((name, value.to_string()));
+        self
+    }
+
+    fn matches(&self, request: &str) -> bool {
+        let (path, query) = self.path.split_once('?').unwrap_or((self.path, ""));
+        let (request_path, request_query) = request.split_once('?').unwrap_or((request, ""));
+
+        request_path == path
+            && query
+                .split('&')
+                .filter(|f| !f.is_empty())
+                .all(|f| request_query.split('&').any(|r| r == f))
+    }
+}
+
+impl MockServer {
+    /// Starts server on random port. Requests without matching route get 404
+    pub async fn start(routes: Vec<MockRoute>) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let requests = Arc::new(Mutex::new(vec![]));
+
+        let recorded = requests.clone();
+        tokio::spawn(async move {
+            let routes = Arc::new(routes);
+
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let routes = routes.clone();
+                let recorded = recorded.clone();
+
+                tokio::spawn(async move {
+                    let mut buff = vec![];
+                    let mut chunk = [0u8; 1024];
+                    let head_len = loop {
+                        if let Some(i) = buff.windows(4).position(|f| f == b"\r\n\r\n") {
+                            break i + 4;
+                        }
+                        match stream.read(&mut chunk).await {
+                            Ok(0) | Err(_) => return,
+                            Ok(n) => buff.extend_from_slice(&chunk[..n]),
+                        }
+                    };
+
+                    let head = String::from_utf8_lossy(&buff[..head_len]).to_string();
+                    let path = head.split(' ').nth(1).unwrap_or_default().to_string();
+                    let body_len = head
+                        .lines()
+                        .filter_map(|f| f.split_once(':'))
+                        .find(|(name, _)| name.eq_ignore_ascii_case("content-length"))
+                        .and_then(|(_, value)| value.trim().parse::<usize>().ok())
+                        .unwrap_or(0);
+                    while buff.len() < head_len + body_len {
+                        match stream.read(&mut chunk).await {
+                            Ok(0) | Err(_) => return,
+                            Ok(n) => buff.extend_from_slice(&chunk[..n]),
+                        }
+                    }
+                    let request_body = String::from_utf8_lossy(&buff[head_len..]).to_string();
+
+                    let route = routes.iter().find(|f| {
+                        f.matches(&path)
+                            && match f.times {
+                                Some(times) => f.hits.fetch_add(1, Ordering::SeqCst) < times,
+                                None => true,
+                            }
+                    });
+                    let (status, body) = route.map_or((404, "{}"), |f| (f.status, f.body.as_str()));
+                    let headers = route
+                        .iter()
+                        .flat_map(|f| &f.headers)
+                        .map(|(name, value)| format!("{name}: {value}\r\n"))
+                        .collect::<String>();
+                    recorded.lock().unwrap().push(MockRequest {
+                        path,
+                        body: request_body,
+                    });
+
+                    let res = format!(
+                        "HTTP/1.1 {status} Mock\r\ncontent-type: application/json\r\n\
+                         content-length: {}\r\nconnection: close\r\n{headers}\r\n{body}",
+                        body.len()
+                    );
+                    let _ = stream.write_all(res.as_bytes()).await;
+                });
+            }
+        });
+
+        Self { url, requests }
+    }
+
+    /// Paths (with query) of all received requests
+    pub fn requests(&self) -> Vec<String> {
+        let requests = self.requests.lock().unwrap();
+
+        requests.iter().map(|f| f.path.clone()).collect()
+    }
+
+    /// Bodies of received requests to `path` (with any query)
+    pub fn bodies(&self, path: &str) -> Vec<String> {
+        let requests = self.requests.lock().unwrap();
+
+        requests
+            .iter()
+            .filter(|f| f.path.split('?').next() == Some(path))
+            .map(|f| f.body.clone())
+            .collect()
+    }
+
+    /// Telegram bot with token `token`, that sends requests to this server. Bot API
+    /// methods are at `/bottoken/<Method>`, like `/bottoken/SendMessage`
+    pub fn bot(&self) -> Bot {
+        Bot::new("token").set_api_url(self.url.parse().unwrap())
+    }
+}
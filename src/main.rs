@@ -7,12 +7,24 @@ use sea_orm::Database;
 use telegrambot::{start_bot, teloxide::Bot};
 
 use crate::{
-    models::twitterclient::TwitterClient,
-    workers::{posting::start_posting_worker, twitter::start_twitter_collector},
+    models::{
+        blueskyclient::BlueskyClient,
+        feedclient::FeedClient,
+        mastodonclient::MastodonClient,
+        twitterclient::{TwitterClient, API_URL, DEFAULT_BACKFILL, DEFAULT_MAX_PAGES},
+    },
+    sources::Sources,
+    workers::{
+        collector::start_collector,
+        posting::{start_posting_worker, PostingOptions},
+        profiles::start_profile_worker,
+        revalidation::start_revalidation_worker,
+    },
 };
 
 mod models;
 mod routes;
+mod sources;
 mod workers;
 
 #[derive(Parser)]
@@ -33,6 +45,26 @@ struct Args {
     #[clap(short, long)]
     /// Twitter bearer token
     twitter_token: Option<String>,
+
+    #[clap(long, default_value_t = DEFAULT_MAX_PAGES)]
+    /// Maximum number of twitter timeline pages fetched per author in one poll
+    twitter_max_pages: usize,
+
+    #[clap(long, default_value_t = API_URL.to_string())]
+    /// Base url of twitter API
+    twitter_api_url: String,
+
+    #[clap(long, default_value_t = DEFAULT_BACKFILL)]
+    /// Number of tweets fetched for newly added twitter author
+    twitter_backfill: usize,
+
+    #[clap(long)]
+    /// Download media and upload it to telegram instead of passing urls
+    upload_media: bool,
+
+    #[clap(long)]
+    /// Append alternative texts of media to posts
+    show_alt_text: bool,
 }
 
 #[rocket::main]
@@ -64,7 +96,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         })
     }));
 
-    let twitter = TwitterClient::new(args.twitter_token.unwrap_or_else(|| {
+    let mut twitter = TwitterClient::new(args.twitter_token.unwrap_or_else(|| {
         std::env::var("TWITTER_TOKEN").unwrap_or_else(|_| {
             eprintln!(
                 "Please set $TWITTER_TOKEN env var, or provide it using --twitter-token key."
@@ -74,10 +106,20 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         })
     }));
 
+    twitter.base_url = args.twitter_api_url;
+    twitter.max_pages = args.twitter_max_pages;
+    twitter.backfill = args.twitter_backfill;
+
     if args.migrate_only {
         exit(0);
     }
 
+    let mut sources = Sources::default();
+    sources.register(twitter.clone());
+    sources.register(FeedClient::new());
+    sources.register(MastodonClient::new());
+    sources.register(BlueskyClient::new());
+
     let rocket = rocket::build()
         .configure(rocket::Config {
             keep_alive: 0,
@@ -86,23 +128,37 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         })
         .manage(db.clone())
         .manage(bot.clone())
-        .manage(twitter.clone())
+        .manage(sources.clone())
         .register("/", routes::catchers::catchers())
         .mount("/user", routes::user::routes())
         .mount("/author", routes::author::routes())
         .mount("/feed", routes::feed::routes())
+        .mount("/search", routes::search::routes())
         .launch();
 
     let telegram_bot = start_bot(bot.clone(), db.clone());
 
-    let twitter_worker = start_twitter_collector(&db, &twitter);
+    let collector = start_collector(&db, &sources);
+
+    let profile_worker = start_profile_worker(&db, &sources);
+
+    let revalidation_worker = start_revalidation_worker(&db, &twitter);
 
-    let telegram_worker = start_posting_worker(&db, &bot);
+    let telegram_worker = start_posting_worker(
+        &db,
+        &bot,
+        PostingOptions {
+            upload_media: args.upload_media,
+            show_alt_text: args.show_alt_text,
+        },
+    );
 
     select! {
         res = rocket => { let _ = res.unwrap(); },
         () = telegram_bot => (),
-        () = twitter_worker => (),
+        () = collector => (),
+        () = profile_worker => (),
+        () = revalidation_worker => (),
         () = telegram_worker => (),
     };
 
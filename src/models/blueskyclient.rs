@@ -0,0 +1,196 @@
+use std::fmt::Display;
+
+use serde::de::DeserializeOwned;
+
+pub use json_structs::*;
+
+/// Public Bluesky AppView, doesn't require authorization
+const PUBLIC_API_URL: &str = "https://public.api.bsky.app";
+
+#[derive(Clone)]
+pub struct BlueskyClient {
+    /// Base url of AppView
+    pub base_url: String,
+}
+
+#[derive(Debug)]
+pub enum BlueskyError {
+    HttpError(reqwest::Error),
+    /// Actor does not exists
+    NotFound,
+    APIError,
+}
+
+mod json_structs {
+    use serde::Deserialize;
+
+    #[derive(Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct BlueskyProfile {
+        pub did: String,
+        pub handle: String,
+        pub display_name: Option<String>,
+        pub avatar: Option<String>,
+    }
+    #[derive(Deserialize)]
+    pub struct BlueskyAuthorFeed {
+        pub cursor: Option<String>,
+        pub feed: Vec<BlueskyFeedItem>,
+    }
+    #[derive(Deserialize)]
+    pub struct BlueskyFeedItem {
+        pub post: BlueskyPost,
+        /// Present if item is repost (or pinned post)
+        pub reason: Option<serde_json::Value>,
+    }
+    #[derive(Deserialize)]
+    pub struct BlueskyPost {
+        /// `at://{did}/app.bsky.feed.post/{rkey}`
+        pub uri: String,
+        pub author: BlueskyProfile,
+        pub record: BlueskyRecord,
+        pub embed: Option<BlueskyEmbed>,
+    }
+    #[derive(Deserialize)]
+    pub struct BlueskyRecord {
+        pub text: String,
+        #[serde(default)]
+        pub facets: Vec<BlueskyFacet>,
+        /// Present if post is reply
+        pub reply: Option<serde_json::Value>,
+        /// ISO 8601 time of creation, set by client
+        #[serde(rename = "createdAt")]
+        pub created_at: Option<String>,
+    }
+    #[derive(Deserialize)]
+    pub struct BlueskyFacet {
+        pub index: BlueskyByteSlice,
+        pub features: Vec<BlueskyFeature>,
+    }
+    #[derive(Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct BlueskyByteSlice {
+        pub byte_start: usize,
+        pub byte_end: usize,
+    }
+    #[derive(Deserialize)]
+    #[serde(tag = "$type")]
+    pub enum BlueskyFeature {
+        #[serde(rename = "app.bsky.richtext.facet#link")]
+        Link { uri: String },
+        #[serde(rename = "app.bsky.richtext.facet#mention")]
+        Mention { did: String },
+        #[serde(rename = "app.bsky.richtext.facet#tag")]
+        Tag { tag: String },
+        #[serde(other)]
+        Unknown,
+    }
+    #[derive(Deserialize)]
+    #[serde(tag = "$type")]
+    pub enum BlueskyEmbed {
+        #[serde(rename = "app.bsky.embed.images#view")]
+        Images { images: Vec<BlueskyImage> },
+        #[serde(rename = "app.bsky.embed.external#view")]
+        External { external: BlueskyExternal },
+        #[serde(rename = "app.bsky.embed.recordWithMedia#view")]
+        RecordWithMedia { media: Box<BlueskyEmbed> },
+        #[serde(other)]
+        Unknown,
+    }
+    #[derive(Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct BlueskyImage {
+        pub fullsize: String,
+        #[serde(default)]
+        pub alt: String,
+        pub aspect_ratio: Option<BlueskyAspectRatio>,
+    }
+    #[derive(Deserialize)]
+    pub struct BlueskyAspectRatio {
+        pub width: i32,
+        pub height: i32,
+    }
+    #[derive(Deserialize)]
+    pub struct BlueskyExternal {
+        pub uri: String,
+        pub title: String,
+        pub thumb: Option<String>,
+    }
+}
+
+impl Display for BlueskyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::HttpError(e) => e.fmt(f),
+            Self::NotFound => write!(f, "Not found"),
+            Self::APIError => write!(f, "API returned error"),
+        }
+    }
+}
+impl std::error::Error for BlueskyError {}
+
+impl Default for BlueskyClient {
+    fn default() -> Self {
+        Self {
+            base_url: PUBLIC_API_URL.to_string(),
+        }
+    }
+}
+
+impl BlueskyClient {
+    /// Create new instance of bluesky client, using public AppView
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    async fn get<T: DeserializeOwned>(
+        &self,
+        method: &str,
+        query: &[(&str, String)],
+    ) -> Result<T, BlueskyError> {
+        let client = reqwest::ClientBuilder::new()
+            .use_rustls_tls()
+            .build()
+            .unwrap();
+        let res = client
+            .get(format!("{}/xrpc/{method}", self.base_url))
+            .query(query)
+            .send()
+            .await
+            .map_err(BlueskyError::HttpError)?;
+
+        // unknown actors are reported as 400 with `InvalidRequest` error
+        if matches!(res.status().as_u16(), 400 | 404) {
+            return Err(BlueskyError::NotFound);
+        }
+
+        let text = res.text().await.map_err(BlueskyError::HttpError)?;
+        serde_json::from_str(&text).map_err(|_| BlueskyError::APIError)
+    }
+
+    /// Fetches profile by handle or DID
+    pub async fn fetch_profile(&self, actor: &str) -> Result<BlueskyProfile, BlueskyError> {
+        self.get("app.bsky.actor.getProfile", &[("actor", actor.to_string())])
+            .await
+    }
+
+    /// Fetches page of actor posts (without replies), newest first. `cursor` is cursor
+    /// of previous page
+    pub async fn fetch_author_feed(
+        &self,
+        actor: &str,
+        cursor: Option<&str>,
+        limit: usize,
+    ) -> Result<BlueskyAuthorFeed, BlueskyError> {
+        let mut query = vec![
+            ("actor", actor.to_string()),
+            ("filter", "posts_no_replies".to_string()),
+            ("limit", limit.to_string()),
+        ];
+        if let Some(cursor) = cursor {
+            query.push(("cursor", cursor.to_string()));
+        }
+
+        self.get("app.bsky.feed.getAuthorFeed", &query).await
+    }
+}
@@ -0,0 +1,48 @@
+use std::fmt::Display;
+
+use feed_rs::{model::Feed, parser::ParseFeedError};
+
+#[derive(Clone, Default)]
+pub struct FeedClient;
+
+#[derive(Debug)]
+pub enum FeedError {
+    HttpError(reqwest::Error),
+    /// Response is not a valid RSS/Atom/JSON feed
+    ParseError(ParseFeedError),
+}
+
+impl Display for FeedError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::HttpError(e) => e.fmt(f),
+            Self::ParseError(e) => write!(f, "Invalid feed: {e}"),
+        }
+    }
+}
+impl std::error::Error for FeedError {}
+
+impl FeedClient {
+    /// Create new instance of feed client
+    pub fn new() -> Self {
+        Self
+    }
+
+    /// Fetches and parses feed at `url`
+    pub async fn fetch_feed(&self, url: &str) -> Result<Feed, FeedError> {
+        let client = reqwest::ClientBuilder::new()
+            .use_rustls_tls()
+            .build()
+            .unwrap();
+        let res = client
+            .get(url)
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+            .map_err(FeedError::HttpError)?;
+
+        let bytes = res.bytes().await.map_err(FeedError::HttpError)?;
+
+        feed_rs::parser::parse(bytes.as_ref()).map_err(FeedError::ParseError)
+    }
+}
@@ -0,0 +1,262 @@
+use entity::post::{EntityKind, PostEntity};
+
+/// Tags, after which text continues from new paragraph
+const BLOCK_TAGS: &[&str] = &[
+    "p",
+    "div",
+    "li",
+    "ul",
+    "ol",
+    "blockquote",
+    "pre",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+];
+
+/// Converts HTML (like RSS descriptions or mastodon statuses) to plain text. Links are
+/// converted to [`PostEntity`], mastodon mentions and hashtags are recognized by their
+/// `class` attribute
+pub fn html_to_text(html: &str) -> (String, Vec<PostEntity>) {
+    let mut text = String::with_capacity(html.len());
+    let mut len = 0;
+    let mut entities = vec![];
+    // start, kind and url of currently open <a>
+    let mut link: Option<(usize, EntityKind, String)> = None;
+    // content of <script> and <style> is not a text
+    let mut skip_until: Option<&str> = None;
+
+    let mut rest = html;
+    while !rest.is_empty() {
+        if let Some(tag_rest) = rest.strip_prefix('<') {
+            let (tag, after) = match tag_rest.find('>') {
+                Some(i) => (&tag_rest[..i], &tag_rest[i + 1..]),
+                None => (tag_rest, ""),
+            };
+            rest = after;
+
+            let closing = tag.starts_with('/');
+            let tag = tag.trim_start_matches('/');
+            let name = tag
+                .split(|c: char| c.is_whitespace() || c == '/')
+                .next()
+                .unwrap_or_default()
+                .to_ascii_lowercase();
+
+            if let Some(until) = skip_until {
+                if closing && name == until {
+                    skip_until = None;
+                }
+                continue;
+            }
+
+            match (name.as_str(), closing) {
+                ("script", false) => skip_until = Some("script"),
+                ("style", false) => skip_until = Some("style"),
+                ("br", _) => {
+                    text.push('\n');
+                    len += 1;
+                }
+                ("a", false) => {
+                    link = attribute(tag, "href").map(|href| {
+                        let class = attribute(tag, "class").unwrap_or_default();
+                        let kind = if class.contains("hashtag") {
+                            EntityKind::Hashtag
+                        } else if class.contains("mention") {
+                            EntityKind::Mention
+                        } else {
+                            EntityKind::Url
+                        };
+
+                        (len, kind, href)
+                    });
+                }
+                ("a", true) => {
+                    if let Some((start, kind, url)) = link.take() {
+                        if start < len {
+                            entities.push(PostEntity {
+                                kind,
+                                start,
+                                end: len,
+                                url,
+                            });
+                        }
+                    }
+                }
+                (name, _) if BLOCK_TAGS.contains(&name) => {
+                    // trailing whitespace before paragraph break
+                    while text.ends_with(' ') {
+                        text.pop();
+                        len -= 1;
+                    }
+                    while !text.is_empty() && !text.ends_with("\n\n") {
+                        text.push('\n');
+                        len += 1;
+                    }
+                    if name == "li" && !closing {
+                        text.push_str("• ");
+                        len += 2;
+                    }
+                }
+                _ => {}
+            }
+
+            continue;
+        }
+
+        let end = rest.find('<').unwrap_or(rest.len());
+        let chunk = &rest[..end];
+        rest = &rest[end..];
+
+        if skip_until.is_some() {
+            continue;
+        }
+
+        for c in decode_entities(chunk).chars() {
+            // collapse whitespace like browsers do
+            if c.is_whitespace() && c != '\u{a0}' {
+                if text.is_empty() || text.ends_with(' ') || text.ends_with('\n') {
+                    continue;
+                }
+                text.push(' ');
+            } else {
+                text.push(c);
+            }
+            len += 1;
+        }
+    }
+
+    let text = text.trim_end().to_string();
+    let len = text.chars().count();
+    for entity in &mut entities {
+        entity.end = entity.end.min(len);
+    }
+    entities.retain(|f| f.start < f.end);
+
+    (text, entities)
+}
+
+/// Returns decoded value of tag attribute
+fn attribute(tag: &str, name: &str) -> Option<String> {
+    let mut rest = tag;
+
+    while let Some(i) = rest.find(name) {
+        let before = rest[..i].chars().next_back();
+        let after = rest[i + name.len()..].trim_start();
+        rest = &rest[i + name.len()..];
+
+        if !matches!(before, Some(c) if c.is_whitespace()) {
+            continue;
+        }
+        let Some(value) = after.strip_prefix('=') else {
+            continue;
+        };
+        let value = value.trim_start();
+
+        let value = match value.chars().next() {
+            Some(q @ ('"' | '\'')) => value[1..].split(q).next().unwrap_or_default(),
+            _ => value.split_whitespace().next().unwrap_or_default(),
+        };
+
+        return Some(decode_entities(value));
+    }
+
+    None
+}
+
+/// Decodes HTML character references
+fn decode_entities(text: &str) -> String {
+    let mut buff = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(i) = rest.find('&') {
+        buff.push_str(&rest[..i]);
+        rest = &rest[i..];
+
+        let decoded = rest[1..]
+            .find(';')
+            .filter(|&end| end <= 10)
+            .and_then(|end| {
+                let name = &rest[1..end + 1];
+                let c = match name {
+                    "amp" => Some('&'),
+                    "lt" => Some('<'),
+                    "gt" => Some('>'),
+                    "quot" => Some('"'),
+                    "apos" => Some('\''),
+                    "nbsp" => Some('\u{a0}'),
+                    _ => name
+                        .strip_prefix("#x")
+                        .or_else(|| name.strip_prefix("#X"))
+                        .and_then(|f| u32::from_str_radix(f, 16).ok())
+                        .or_else(|| name.strip_prefix('#').and_then(|f| f.parse().ok()))
+                        .and_then(char::from_u32),
+                };
+
+                c.map(|c| (c, end + 2))
+            });
+
+        match decoded {
+            Some((c, skip)) => {
+                buff.push(c);
+                rest = &rest[skip..];
+            }
+            None => {
+                buff.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+
+    buff.push_str(rest);
+
+    buff
+}
+
+#[cfg(test)]
+mod tests {
+    use entity::post::EntityKind;
+
+    use super::html_to_text;
+
+    #[test]
+    /// Check [`html_to_text`] with paragraphs, line breaks and character references
+    fn test_html_to_text() {
+        let (text, entities) = html_to_text(
+            "<p>Hello,\n   <b>world</b> &amp; friends</p><p>second<br/>line &#x1F327;</p>\
+             <script>alert(1)</script>",
+        );
+
+        assert_eq!(text, "Hello, world & friends\n\nsecond\nline 🌧");
+        assert!(entities.is_empty());
+    }
+
+    #[test]
+    /// Check [`html_to_text`] converts links, including mastodon mentions and hashtags
+    fn test_html_to_text_links() {
+        let (text, entities) = html_to_text(
+            r#"<p>hi <span class="h-card"><a href="https://mastodon.social/@Gargron" class="u-url mention">@<span>Gargron</span></a></span> look <a href="https://example.com/?a=1&amp;b=2" rel="nofollow">example</a> <a href="https://mastodon.social/tags/rust" class="mention hashtag" rel="tag">#<span>rust</span></a></p>"#,
+        );
+
+        assert_eq!(text, "hi @Gargron look example #rust");
+
+        let chars = text.chars().collect::<Vec<_>>();
+        let shown = |i: usize| {
+            chars[entities[i].start..entities[i].end]
+                .iter()
+                .collect::<String>()
+        };
+
+        assert_eq!(entities.len(), 3);
+        assert_eq!(entities[0].kind, EntityKind::Mention);
+        assert_eq!(shown(0), "@Gargron");
+        assert_eq!(entities[1].kind, EntityKind::Url);
+        assert_eq!(entities[1].url, "https://example.com/?a=1&b=2");
+        assert_eq!(shown(1), "example");
+        assert_eq!(entities[2].kind, EntityKind::Hashtag);
+        assert_eq!(shown(2), "#rust");
+    }
+}
@@ -0,0 +1,165 @@
+use std::fmt::Display;
+
+use serde::de::DeserializeOwned;
+
+pub use json_structs::{MastodonAccount, MastodonStatus};
+
+#[derive(Clone, Default)]
+pub struct MastodonClient {
+    /// Base url used instead of `https://{instance}` for every instance, for tests
+    pub base_url: Option<String>,
+}
+
+#[derive(Debug)]
+pub enum MastodonError {
+    HttpError(reqwest::Error),
+    /// Account or instance does not exists
+    NotFound,
+    APIError,
+}
+
+mod json_structs {
+    use serde::Deserialize;
+
+    #[derive(Deserialize)]
+    pub struct MastodonAccount {
+        pub id: String,
+        pub username: String,
+        pub display_name: String,
+        pub avatar: Option<String>,
+    }
+    #[derive(Deserialize)]
+    pub struct MastodonStatus {
+        pub id: String,
+        /// Globally unique ActivityPub id of status
+        pub uri: String,
+        pub url: Option<String>,
+        /// HTML content
+        pub content: String,
+        /// ISO 8601 time of creation
+        pub created_at: Option<String>,
+        #[serde(default)]
+        pub spoiler_text: String,
+        #[serde(default)]
+        pub media_attachments: Vec<MastodonAttachment>,
+        pub account: MastodonAccount,
+    }
+    #[derive(Deserialize)]
+    pub struct MastodonAttachment {
+        /// `image`, `gifv`, `video`, `audio` or `unknown`
+        pub r#type: String,
+        pub url: String,
+        /// Alternative text
+        pub description: Option<String>,
+        #[serde(default)]
+        pub meta: MastodonAttachmentMeta,
+    }
+    #[derive(Deserialize, Default)]
+    pub struct MastodonAttachmentMeta {
+        pub original: Option<MastodonAttachmentSize>,
+    }
+    #[derive(Deserialize)]
+    pub struct MastodonAttachmentSize {
+        pub width: Option<i32>,
+        pub height: Option<i32>,
+        /// Duration in seconds
+        pub duration: Option<f64>,
+    }
+}
+
+impl Display for MastodonError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::HttpError(e) => e.fmt(f),
+            Self::NotFound => write!(f, "Not found"),
+            Self::APIError => write!(f, "API returned error"),
+        }
+    }
+}
+impl std::error::Error for MastodonError {}
+
+impl MastodonClient {
+    /// Create new instance of mastodon client
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn instance_url(&self, instance: &str) -> String {
+        match &self.base_url {
+            Some(url) => url.clone(),
+            None => format!("https://{instance}"),
+        }
+    }
+
+    async fn get<T: DeserializeOwned>(
+        &self,
+        instance: &str,
+        path: &str,
+        query: &[(&str, String)],
+    ) -> Result<T, MastodonError> {
+        let client = reqwest::ClientBuilder::new()
+            .use_rustls_tls()
+            .build()
+            .unwrap();
+        let res = client
+            .get(format!("{}{path}", self.instance_url(instance)))
+            .query(query)
+            .send()
+            .await
+            .map_err(MastodonError::HttpError)?;
+
+        if res.status() == reqwest::StatusCode::NOT_FOUND {
+            return Err(MastodonError::NotFound);
+        }
+
+        let text = res.text().await.map_err(MastodonError::HttpError)?;
+        serde_json::from_str(&text).map_err(|_| MastodonError::APIError)
+    }
+
+    /// Fetches account `id` on `instance`
+    pub async fn fetch_account(
+        &self,
+        instance: &str,
+        id: &str,
+    ) -> Result<MastodonAccount, MastodonError> {
+        self.get(instance, &format!("/api/v1/accounts/{id}"), &[])
+            .await
+    }
+
+    /// Fetches account by its local username on `instance`
+    pub async fn lookup_account(
+        &self,
+        instance: &str,
+        username: &str,
+    ) -> Result<MastodonAccount, MastodonError> {
+        self.get(
+            instance,
+            "/api/v1/accounts/lookup",
+            &[("acct", username.to_string())],
+        )
+        .await
+    }
+
+    /// Fetches public statuses of account `id`, newest first. With `min_id` fetches
+    /// `limit` statuses right after it, otherwise latest ones. Replies and reblogs are
+    /// excluded
+    pub async fn fetch_statuses(
+        &self,
+        instance: &str,
+        id: &str,
+        min_id: Option<&str>,
+        limit: usize,
+    ) -> Result<Vec<MastodonStatus>, MastodonError> {
+        let mut query = vec![
+            ("exclude_replies", "true".to_string()),
+            ("exclude_reblogs", "true".to_string()),
+            ("limit", limit.to_string()),
+        ];
+        if let Some(min_id) = min_id {
+            query.push(("min_id", min_id.to_string()));
+        }
+
+        self.get(instance, &format!("/api/v1/accounts/{id}/statuses"), &query)
+            .await
+    }
+}
@@ -1,3 +1,10 @@
+pub mod blueskyclient;
+pub mod feedclient;
+pub mod html;
+pub mod mastodonclient;
+pub mod postfilter;
+pub mod postformat;
 pub mod response;
 pub mod telegramauth;
+pub mod time;
 pub mod twitterclient;
@@ -0,0 +1,505 @@
+use std::{collections::HashMap, fmt, str::FromStr};
+
+use entity::post_media::MediaType;
+use migration::{Expr, Order, Query, SelectStatement};
+use rocket::{
+    form::FromForm,
+    http::Status,
+    request::{FromRequest, Outcome},
+    FromFormField, Request,
+};
+use sea_orm::{
+    ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter, QueryOrder, QuerySelect, Select,
+};
+
+/// Number of posts in page, if `limit` is not passed
+pub const DEFAULT_PAGE_SIZE: u64 = 50;
+/// Maximum number of posts in page
+pub const MAX_PAGE_SIZE: u64 = 100;
+
+/// Sort key of posts in listings, that is publication time (0 if unknown) and internal
+/// id for posts published at the same second
+const SORT_KEY: &str = "(COALESCE(posts.posted_at, 0), posts.id)";
+
+#[derive(Default)]
+/// Cursor and filters of post listings, passed in query
+pub struct PostFilter {
+    /// Only posts before this position
+    pub before: Option<PostCursor>,
+    /// Only posts after this position
+    pub after: Option<PostCursor>,
+    /// Maximum number of posts, [`DEFAULT_PAGE_SIZE`] by default
+    pub limit: Option<u64>,
+    /// Only posts with (`true`) or without (`false`) media
+    pub has_media: Option<bool>,
+    /// Only posts with media of this type
+    pub media_type: Option<MediaKind>,
+    /// Only posts published at or after this unix time
+    pub since: Option<i64>,
+    /// Only posts published before this unix time
+    pub until: Option<i64>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+/// Position of post in listings, ordered by publication time. Passed as opaque string
+pub struct PostCursor {
+    /// Publication time, 0 if it is unknown
+    pub posted_at: i64,
+    /// Internal id of post
+    pub id: i64,
+}
+
+impl PostCursor {
+    pub fn of(post: &entity::post::Model) -> Self {
+        Self {
+            posted_at: post.posted_at.unwrap_or(0),
+            id: post.id,
+        }
+    }
+}
+
+impl fmt::Display for PostCursor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}_{}", self.posted_at, self.id)
+    }
+}
+
+impl FromStr for PostCursor {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (posted_at, id) = s.split_once('_').ok_or(())?;
+
+        Ok(Self {
+            posted_at: posted_at.parse().map_err(|_| ())?,
+            id: id.parse().map_err(|_| ())?,
+        })
+    }
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for PostFilter {
+    type Error = String;
+
+    async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let filter = (|| {
+            let cursor = |name| {
+                query_value::<&str>(req, name)?
+                    .map(|f| f.parse().map_err(|_| format!("Invalid `{name}`")))
+                    .transpose()
+            };
+
+            Ok(PostFilter {
+                before: cursor("before")?,
+                after: cursor("after")?,
+                limit: query_value(req, "limit")?,
+                has_media: query_value(req, "has_media")?,
+                media_type: query_value(req, "media_type")?,
+                since: query_value(req, "since")?,
+                until: query_value(req, "until")?,
+            })
+        })();
+
+        match filter {
+            Ok(f) => Outcome::Success(f),
+            Err(e) => Outcome::Failure((Status::UnprocessableEntity, e)),
+        }
+    }
+}
+
+/// Parses optional query parameter `name`
+pub fn query_value<'r, T: FromForm<'r>>(
+    req: &'r Request<'_>,
+    name: &str,
+) -> Result<Option<T>, String> {
+    req.query_value(name)
+        .transpose()
+        .map_err(|_| format!("Invalid `{name}`"))
+}
+
+#[derive(FromFormField, Clone, Copy)]
+/// [`MediaType`] in query
+pub enum MediaKind {
+    Photo,
+    Video,
+    Animation,
+}
+
+impl From<MediaKind> for MediaType {
+    fn from(kind: MediaKind) -> Self {
+        match kind {
+            MediaKind::Photo => MediaType::Photo,
+            MediaKind::Video => MediaType::Video,
+            MediaKind::Animation => MediaType::Animation,
+        }
+    }
+}
+
+/// Returns page of `posts`, that match `filter`, with their media in order. Page goes
+/// forward from `after` if it is passed, otherwise it contains the latest posts (before
+/// `before`). Posts are ordered by publication time (see [`PostCursor`]), since platform
+/// ids are not comparable across platforms and posts may be collected not in order they
+/// were published. Fails if `limit` is out of bounds
+pub async fn find_posts(
+    db: &DatabaseConnection,
+    mut posts: Select<entity::post::Entity>,
+    filter: &PostFilter,
+) -> Result<Vec<(entity::post::Model, Vec<entity::post_media::Model>)>, &'static str> {
+    let limit = filter.limit.unwrap_or(DEFAULT_PAGE_SIZE);
+    if limit == 0 || limit > MAX_PAGE_SIZE {
+        return Err("limit must be from 1 to 100");
+    }
+
+    // quoted posts of authors, that are not tracked, are shown only with quoting posts
+    posts = posts.filter(entity::post::Column::QuotedOnly.eq(false));
+    if let Some(after) = filter.after {
+        posts = posts.filter(Expr::cust_with_values(
+            &format!("{SORT_KEY} > (?, ?)"),
+            [after.posted_at, after.id],
+        ));
+    }
+    if let Some(before) = filter.before {
+        posts = posts.filter(Expr::cust_with_values(
+            &format!("{SORT_KEY} < (?, ?)"),
+            [before.posted_at, before.id],
+        ));
+    }
+    match filter.has_media {
+        Some(true) => posts = posts.filter(entity::post::Column::Id.in_subquery(media_posts())),
+        Some(false) => {
+            posts = posts.filter(entity::post::Column::Id.not_in_subquery(media_posts()))
+        }
+        None => {}
+    }
+    if let Some(kind) = filter.media_type {
+        let subquery = media_posts()
+            .inner_join(
+                entity::post_media::Entity,
+                Expr::tbl(
+                    entity::post_media_link::Entity,
+                    entity::post_media_link::Column::MediaId,
+                )
+                .equals(entity::post_media::Entity, entity::post_media::Column::Id),
+            )
+            .and_where(entity::post_media::Column::MediaType.eq(MediaType::from(kind)))
+            .to_owned();
+        posts = posts.filter(entity::post::Column::Id.in_subquery(subquery));
+    }
+    if let Some(since) = filter.since {
+        posts = posts.filter(entity::post::Column::PostedAt.gte(since));
+    }
+    if let Some(until) = filter.until {
+        posts = posts.filter(entity::post::Column::PostedAt.lt(until));
+    }
+
+    let order = if filter.after.is_some() {
+        Order::Asc
+    } else {
+        Order::Desc
+    };
+    let mut posts = posts
+        .order_by(Expr::cust("COALESCE(posts.posted_at, 0)"), order.clone())
+        .order_by(entity::post::Column::Id, order)
+        .limit(limit)
+        .all(db)
+        .await
+        .unwrap();
+    posts.sort_by_key(PostCursor::of);
+
+    Ok(with_media(db, posts).await)
+}
+
+/// Pairs `posts` with their media in order
+pub async fn with_media(
+    db: &DatabaseConnection,
+    posts: Vec<entity::post::Model>,
+) -> Vec<(entity::post::Model, Vec<entity::post_media::Model>)> {
+    let links = entity::post_media_link::Entity::find()
+        .filter(entity::post_media_link::Column::PostId.is_in(posts.iter().map(|f| f.id)))
+        .find_also_related(entity::post_media::Entity)
+        .order_by_asc(entity::post_media_link::Column::Position)
+        .all(db)
+        .await
+        .unwrap();
+    let mut media = HashMap::<i64, Vec<_>>::new();
+    for (link, model) in links {
+        media.entry(link.post_id).or_default().extend(model);
+    }
+
+    posts
+        .into_iter()
+        .map(|post| {
+            let media = media.remove(&post.id).unwrap_or_default();
+            (post, media)
+        })
+        .collect()
+}
+
+/// Ids of posts, that have media
+fn media_posts() -> SelectStatement {
+    Query::select()
+        .column(entity::post_media_link::Column::PostId)
+        .from(entity::post_media_link::Entity)
+        .to_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use entity::{author::Platform, post_media::MediaType};
+    use migration::{Migrator, MigratorTrait};
+    use sea_orm::{
+        ActiveModelTrait, ColumnTrait, Database, DatabaseConnection, EntityTrait, QueryFilter, Set,
+    };
+
+    use super::{find_posts, MediaKind, PostCursor, PostFilter};
+
+    /// Database with posts 1..=6 of one author, posted at `100 * id`. Posts 2 and 4 have
+    /// photo, post 4 has video too
+    async fn database() -> DatabaseConnection {
+        let db = Database::connect("sqlite::memory:").await.unwrap();
+        Migrator::up(&db, None).await.unwrap();
+
+        let author = entity::author::ActiveModel {
+            platform_id: Set(12),
+            platform: Set(Platform::Twitter),
+            name: Set("jack".to_string()),
+            username: Set("jack".to_string()),
+            ..Default::default()
+        }
+        .insert(&db)
+        .await
+        .unwrap();
+
+        for id in 1..=6 {
+            entity::post::ActiveModel {
+                id: Set(id),
+                platform_id: Set(id),
+                external_id: Set(id.to_string()),
+                author_id: Set(author.id),
+                text: Set(format!("post {id}")),
+                source_url: Set(format!("https://twitter.com/jack/status/{id}")),
+                source_text: Set("jack".to_string()),
+                posted_at: Set(Some(100 * id)),
+                ..Default::default()
+            }
+            .insert(&db)
+            .await
+            .unwrap();
+        }
+
+        for (post_id, position, media_type, url) in [
+            (2, 0, MediaType::Photo, "https://pbs.twimg.com/media/a.jpg"),
+            (4, 1, MediaType::Photo, "https://pbs.twimg.com/media/a.jpg"),
+            (4, 0, MediaType::Video, "https://video.twimg.com/b.mp4"),
+        ] {
+            let url_key = entity::post_media::url_key(url);
+            let media = entity::post_media::Entity::find()
+                .filter(entity::post_media::Column::UrlKey.eq(url_key.as_str()))
+                .one(&db)
+                .await
+                .unwrap();
+            let media = match media {
+                Some(m) => m,
+                None => entity::post_media::ActiveModel {
+                    url_key: Set(url_key),
+                    media_type: Set(media_type),
+                    media_url: Set(url.to_string()),
+                    ..Default::default()
+                }
+                .insert(&db)
+                .await
+                .unwrap(),
+            };
+
+            entity::post_media_link::ActiveModel {
+                post_id: Set(post_id),
+                media_id: Set(media.id),
+                position: Set(position),
+            }
+            .insert(&db)
+            .await
+            .unwrap();
+        }
+
+        db
+    }
+
+    async fn ids(db: &DatabaseConnection, filter: PostFilter) -> Vec<i64> {
+        find_posts(db, entity::post::Entity::find(), &filter)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|(post, _)| post.id)
+            .collect()
+    }
+
+    /// Cursor of post `id` of [`database`]
+    fn cursor(id: i64) -> Option<PostCursor> {
+        Some(PostCursor {
+            posted_at: 100 * id,
+            id,
+        })
+    }
+
+    #[test]
+    /// Check [`PostCursor`] is parsed from its string
+    fn test_post_cursor() {
+        let cursor = cursor(2).unwrap();
+
+        assert_eq!(cursor.to_string().parse(), Ok(cursor));
+        assert_eq!(
+            "0_7".parse(),
+            Ok(PostCursor {
+                posted_at: 0,
+                id: 7
+            })
+        );
+        assert!("7".parse::<PostCursor>().is_err());
+        assert!("a_7".parse::<PostCursor>().is_err());
+    }
+
+    #[rocket::async_test]
+    /// Check [`find_posts`] pages through posts by `before`, `after` and `limit`
+    async fn test_find_posts_pages() {
+        let db = database().await;
+
+        assert_eq!(ids(&db, PostFilter::default()).await, [1, 2, 3, 4, 5, 6]);
+
+        let page = |before, after| PostFilter {
+            before,
+            after,
+            limit: Some(2),
+            ..Default::default()
+        };
+        // latest posts
+        assert_eq!(ids(&db, page(None, None)).await, [5, 6]);
+        assert_eq!(ids(&db, page(cursor(5), None)).await, [3, 4]);
+        assert_eq!(ids(&db, page(None, cursor(2))).await, [3, 4]);
+        assert_eq!(ids(&db, page(cursor(4), cursor(2))).await, [3]);
+        assert_eq!(ids(&db, page(cursor(1), None)).await, Vec::<i64>::new());
+
+        let filter = PostFilter {
+            limit: Some(0),
+            ..Default::default()
+        };
+        assert!(find_posts(&db, entity::post::Entity::find(), &filter)
+            .await
+            .is_err());
+    }
+
+    #[rocket::async_test]
+    /// Check [`find_posts`] pages through posts of different platforms by publication
+    /// time, whatever their platform ids and order of collection are
+    async fn test_find_posts_platforms() {
+        let db = database().await;
+
+        let mut authors = vec![];
+        for (platform, platform_id) in [(Platform::Rss, 13), (Platform::Mastodon, 14)] {
+            let author = entity::author::ActiveModel {
+                platform_id: Set(platform_id),
+                platform: Set(platform),
+                external_id: Set(platform_id.to_string()),
+                name: Set(format!("{platform:?}")),
+                username: Set(format!("{platform:?}")),
+                ..Default::default()
+            }
+            .insert(&db)
+            .await
+            .unwrap();
+            authors.push(author);
+        }
+
+        // rss platform id is publication time with hash, mastodon one is status id. Post
+        // 9 is backfilled old post, post 10 has the same time as post 6
+        for (id, author, platform_id, posted_at) in [
+            (7, &authors[1], 109300000000000000, 700),
+            (8, &authors[0], 1751121920000002, 800),
+            (9, &authors[1], 109400000000000000, 150),
+            (10, &authors[0], 42, 600),
+        ] {
+            entity::post::ActiveModel {
+                id: Set(id),
+                platform_id: Set(platform_id),
+                platform: Set(author.platform),
+                external_id: Set(id.to_string()),
+                author_id: Set(author.id),
+                text: Set(format!("post {id}")),
+                source_url: Set(format!("https://example.com/{id}")),
+                source_text: Set(author.name.clone()),
+                posted_at: Set(Some(posted_at)),
+                ..Default::default()
+            }
+            .insert(&db)
+            .await
+            .unwrap();
+        }
+
+        let page = |before, after| PostFilter {
+            before,
+            after,
+            limit: Some(3),
+            ..Default::default()
+        };
+        assert_eq!(ids(&db, page(None, None)).await, [10, 7, 8]);
+        assert_eq!(ids(&db, page(cursor(6), None)).await, [3, 4, 5]);
+        assert_eq!(ids(&db, page(None, cursor(1))).await, [9, 2, 3]);
+        assert_eq!(ids(&db, page(None, cursor(6))).await, [10, 7, 8]);
+        let cursor = Some(PostCursor {
+            posted_at: 600,
+            id: 10,
+        });
+        assert_eq!(ids(&db, page(cursor, None)).await, [4, 5, 6]);
+        assert_eq!(ids(&db, page(None, cursor)).await, [7, 8]);
+    }
+
+    #[rocket::async_test]
+    /// Check [`find_posts`] filters posts by media and publication time and returns media
+    /// in order
+    async fn test_find_posts_filters() {
+        let db = database().await;
+
+        let filter = PostFilter {
+            has_media: Some(true),
+            ..Default::default()
+        };
+        assert_eq!(ids(&db, filter).await, [2, 4]);
+
+        let filter = PostFilter {
+            has_media: Some(false),
+            ..Default::default()
+        };
+        assert_eq!(ids(&db, filter).await, [1, 3, 5, 6]);
+
+        let filter = PostFilter {
+            media_type: Some(MediaKind::Video),
+            ..Default::default()
+        };
+        assert_eq!(ids(&db, filter).await, [4]);
+
+        let filter = PostFilter {
+            since: Some(200),
+            until: Some(500),
+            ..Default::default()
+        };
+        assert_eq!(ids(&db, filter).await, [2, 3, 4]);
+
+        let filter = PostFilter {
+            media_type: Some(MediaKind::Photo),
+            ..Default::default()
+        };
+        let posts = find_posts(&db, entity::post::Entity::find(), &filter)
+            .await
+            .unwrap();
+        let media = posts
+            .iter()
+            .map(|(_, media)| media.iter().map(|f| f.media_type.clone()).collect())
+            .collect::<Vec<Vec<_>>>();
+        assert_eq!(
+            media,
+            [
+                vec![MediaType::Photo],
+                vec![MediaType::Video, MediaType::Photo]
+            ]
+        );
+    }
+}
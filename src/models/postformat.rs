@@ -0,0 +1,285 @@
+use std::fmt::Write;
+
+use entity::post::PostEntity;
+
+/// Maximum number of media in one telegram media group
+pub const MAX_MEDIA_GROUP_SIZE: usize = 10;
+/// Maximum length of media caption (after entities parsing)
+pub const MAX_CAPTION_LENGTH: usize = 1024;
+/// Maximum length of text message (after entities parsing)
+pub const MAX_TEXT_LENGTH: usize = 4096;
+
+/// Escapes `<`, `>` and `&` for telegram HTML parse mode
+pub fn escape_html(text: &str) -> String {
+    let mut buff = String::with_capacity(text.len());
+
+    for c in text.chars() {
+        match c {
+            '<' => buff.push_str("&lt;"),
+            '>' => buff.push_str("&gt;"),
+            '&' => buff.push_str("&amp;"),
+            _ => buff.push(c),
+        }
+    }
+
+    buff
+}
+
+/// Escapes text for use in HTML attribute value
+fn escape_attr(text: &str) -> String {
+    escape_html(text).replace('"', "&quot;")
+}
+
+/// Renders text with links from `entities`, using telegram HTML parse mode.
+/// Invalid and overlapping entities are ignored
+pub fn render_html(text: &str, entities: &[PostEntity]) -> String {
+    let chars = text.chars().collect::<Vec<_>>();
+    let slice =
+        |start: usize, end: usize| escape_html(&chars[start..end].iter().collect::<String>());
+
+    let mut entities = entities.iter().collect::<Vec<_>>();
+    entities.sort_by_key(|f| f.start);
+
+    let mut buff = String::with_capacity(text.len());
+    let mut cursor = 0;
+
+    for entity in entities {
+        if entity.start < cursor || entity.start > entity.end || entity.end > chars.len() {
+            continue;
+        }
+
+        buff.push_str(&slice(cursor, entity.start));
+        write!(
+            &mut buff,
+            "<a href=\"{}\">{}</a>",
+            escape_attr(&entity.url),
+            slice(entity.start, entity.end)
+        )
+        .unwrap();
+
+        cursor = entity.end;
+    }
+
+    buff.push_str(&slice(cursor, chars.len()));
+
+    buff
+}
+
+/// Length of text as telegram counts it (in UTF-16 code units)
+pub fn text_length(text: &str) -> usize {
+    text.encode_utf16().count()
+}
+
+/// Length of [`format_post`] result as telegram counts it
+pub fn post_length(text: &str, source: &str) -> usize {
+    text_length(text) + 2 + text_length(source)
+}
+
+/// Bold source link, using telegram HTML parse mode
+fn source_link(source: &str, source_url: &str) -> String {
+    format!(
+        "<b><a href=\"{}\">{}</a></b>",
+        escape_attr(source_url),
+        escape_html(source)
+    )
+}
+
+/// Formats post text with bold source link at the end, using telegram HTML parse mode
+pub fn format_post(text: &str, entities: &[PostEntity], source: &str, source_url: &str) -> String {
+    format!(
+        "{}\n\n{}",
+        render_html(text, entities),
+        source_link(source, source_url)
+    )
+}
+
+/// Splits text into parts not longer than `max_len` (see [`text_length`]).
+/// Prefers to split at line breaks, then at spaces.
+pub fn split_text(text: &str, max_len: usize) -> Vec<&str> {
+    let mut parts = vec![];
+    let mut rest = text;
+
+    while text_length(rest) > max_len {
+        // byte index where `max_len` code units end
+        let mut end = 0;
+        let mut units = 0;
+        for (i, c) in rest.char_indices() {
+            if units + c.len_utf16() > max_len {
+                break;
+            }
+            units += c.len_utf16();
+            end = i + c.len_utf8();
+        }
+
+        let cut = rest[..end]
+            .rfind('\n')
+            .or_else(|| rest[..end].rfind(' '))
+            .filter(|&i| i > 0)
+            .unwrap_or(end);
+
+        parts.push(rest[..cut].trim_end());
+        rest = rest[cut..].trim_start();
+    }
+
+    if !rest.is_empty() || parts.is_empty() {
+        parts.push(rest);
+    }
+
+    parts
+}
+
+/// Returns entities inside `part` of `text` with offsets relative to `part`.
+/// `part` must be a subslice of `text`
+fn part_entities(text: &str, part: &str, entities: &[PostEntity]) -> Vec<PostEntity> {
+    let byte_start = part.as_ptr() as usize - text.as_ptr() as usize;
+    let start = text[..byte_start].chars().count();
+    let end = start + part.chars().count();
+
+    entities
+        .iter()
+        .filter(|f| f.start >= start && f.end <= end)
+        .map(|f| PostEntity {
+            start: f.start - start,
+            end: f.end - start,
+            ..f.clone()
+        })
+        .collect()
+}
+
+/// Same as [`format_post`], but splits result into messages not longer than `max_len`.
+/// Entities on split boundaries are dropped
+pub fn split_post(
+    text: &str,
+    entities: &[PostEntity],
+    source: &str,
+    source_url: &str,
+    max_len: usize,
+) -> Vec<String> {
+    let footer_len = post_length("", source);
+    let parts = split_text(text, max_len);
+
+    let (last, parts) = parts
+        .split_last()
+        .expect("split_text returns at least one part");
+    let mut res = parts
+        .iter()
+        .map(|f| render_html(f, &part_entities(text, f, entities)))
+        .collect::<Vec<_>>();
+
+    let last_entities = part_entities(text, last, entities);
+    if text_length(last) + footer_len <= max_len {
+        res.push(format_post(last, &last_entities, source, source_url));
+    } else {
+        res.push(render_html(last, &last_entities));
+        res.push(source_link(source, source_url));
+    }
+
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use entity::post::{EntityKind, PostEntity};
+
+    use super::{render_html, split_post, split_text, text_length};
+
+    #[test]
+    /// Check [`split_text`] with text shorter than limit
+    fn test_split_text_short() {
+        assert_eq!(split_text("hello world", 100), vec!["hello world"]);
+        assert_eq!(split_text("", 100), vec![""]);
+    }
+
+    #[test]
+    /// Check [`split_text`] prefers line breaks and spaces
+    fn test_split_text_boundaries() {
+        assert_eq!(
+            split_text("first line\nsecond line", 15),
+            vec!["first line", "second line"]
+        );
+        assert_eq!(
+            split_text("lorem ipsum dolor sit amet", 12),
+            vec!["lorem ipsum", "dolor sit", "amet"]
+        );
+        assert_eq!(split_text("abcdefgh", 3), vec!["abc", "def", "gh"]);
+    }
+
+    #[test]
+    /// Check [`split_text`] counts UTF-16 code units, like telegram does
+    fn test_split_text_utf16() {
+        // each emoji is 2 code units
+        let parts = split_text("🌧🌧🌧", 4);
+
+        assert_eq!(parts, vec!["🌧🌧", "🌧"]);
+        assert!(parts.iter().all(|f| text_length(f) <= 4));
+    }
+
+    #[test]
+    /// Check [`split_post`] escapes html and moves source link to separate message
+    /// when it doesn't fit
+    fn test_split_post() {
+        let parts = split_post("a < b", &[], "src", "https://example.com", 100);
+        assert_eq!(
+            parts,
+            vec!["a &lt; b\n\n<b><a href=\"https://example.com\">src</a></b>"]
+        );
+
+        let parts = split_post("aaaa bbbb", &[], "src", "https://example.com", 5);
+        assert_eq!(
+            parts,
+            vec![
+                "aaaa",
+                "bbbb",
+                "<b><a href=\"https://example.com\">src</a></b>"
+            ]
+        );
+    }
+
+    fn link(start: usize, end: usize, url: &str) -> PostEntity {
+        PostEntity {
+            kind: EntityKind::Url,
+            start,
+            end,
+            url: url.to_string(),
+        }
+    }
+
+    #[test]
+    /// Check [`render_html`] wraps entities and escapes text around them
+    fn test_render_html() {
+        let text = "🌧 <see> example & #tag";
+        let entities = [
+            link(8, 15, "https://example.com/?a=1&b=\"2\""),
+            link(18, 22, "https://twitter.com/hashtag/tag"),
+            // overlaps previous one
+            link(19, 20, "https://example.com"),
+        ];
+
+        assert_eq!(
+            render_html(text, &entities),
+            "🌧 &lt;see&gt; <a href=\"https://example.com/?a=1&amp;b=&quot;2&quot;\">example</a> &amp; \
+             <a href=\"https://twitter.com/hashtag/tag\">#tag</a>"
+        );
+    }
+
+    #[test]
+    /// Check [`split_post`] moves entities to the part they belong to
+    fn test_split_post_entities() {
+        let parts = split_post(
+            "first #a\nsecond #b",
+            &[link(6, 8, "https://a"), link(16, 18, "https://b")],
+            "src",
+            "https://example.com",
+            10,
+        );
+
+        assert_eq!(
+            parts,
+            vec![
+                "first <a href=\"https://a\">#a</a>",
+                "second <a href=\"https://b\">#b</a>",
+                "<b><a href=\"https://example.com\">src</a></b>"
+            ]
+        );
+    }
+}
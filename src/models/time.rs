@@ -0,0 +1,18 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use chrono::DateTime;
+
+/// Current unix time in seconds
+pub fn unix_now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("Duration since UNIX_EPOCH")
+        .as_secs() as i64
+}
+
+/// Parses RFC 3339 date (like `2022-10-26T18:45:58.000Z`) to unix time in seconds
+pub fn parse_rfc3339(date: &str) -> Option<i64> {
+    DateTime::parse_from_rfc3339(date)
+        .ok()
+        .map(|f| f.timestamp())
+}
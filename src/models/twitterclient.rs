@@ -1,21 +1,75 @@
-use std::{collections::HashMap, fmt::Display};
+use std::{
+    collections::HashMap,
+    fmt::Display,
+    sync::{Arc, Mutex},
+};
 
+use entity::{
+    post::{EntityKind, PostEntity},
+    post_media::MediaType,
+};
+use itertools::Itertools;
 use json_structs::*;
-use lazy_static::lazy_static;
-use regex::Regex;
+
+use crate::models::time::unix_now;
 
 #[derive(Clone)]
 pub struct TwitterClient {
     /// Twitter bearer token
     pub token: String,
+    /// Base url of twitter API
+    pub base_url: String,
+    /// Maximum number of timeline pages fetched in one [`TwitterClient::fetch_timeline`]
+    pub max_pages: usize,
+    /// Number of tweets fetched on the first fetch of timeline
+    pub backfill: usize,
+    /// Unix time of rate limit reset of exhausted endpoints
+    rate_limits: Arc<Mutex<HashMap<&'static str, i64>>>,
 }
 
+/// Default value of [`TwitterClient::base_url`]
+pub const API_URL: &str = "https://api.twitter.com";
+/// Default value of [`TwitterClient::max_pages`]
+pub const DEFAULT_MAX_PAGES: usize = 5;
+/// Default value of [`TwitterClient::backfill`]
+pub const DEFAULT_BACKFILL: usize = 5;
+/// Bounds of `max_results` parameter of timeline endpoint
+const PAGE_SIZE: (usize, usize) = (5, 100);
+/// Maximum number of ids in one [`TwitterClient::lookup_tweets`] or
+/// [`TwitterClient::fetch_users`]
+pub const MAX_LOOKUP_IDS: usize = 100;
+/// Twitter snowflake epoch in unix milliseconds
+const SNOWFLAKE_EPOCH: i64 = 1288834974657;
+/// Rate limit window, used if 429 response has no `x-rate-limit-reset` header
+const RATE_LIMIT_WINDOW: i64 = 15 * 60;
+
 #[derive(Debug)]
 pub enum TwitterError {
     HttpError(reqwest::Error),
-    APIError,
+    /// API returned unexpected response
+    APIError {
+        status: u16,
+        errors: Vec<TwitterApiError>,
+    },
+    /// User (or tweet) does not exists
+    NotFound(Vec<TwitterApiError>),
+    /// User is suspended
+    Suspended(Vec<TwitterApiError>),
+    /// User is protected, so its tweets are not visible
+    Protected(Vec<TwitterApiError>),
+    /// Bearer token is invalid or has no access to endpoint
+    Unauthorized {
+        status: u16,
+        errors: Vec<TwitterApiError>,
+    },
+    /// Endpoint rate limit is exhausted until `reset_at` (unix time)
+    RateLimited {
+        reset_at: i64,
+    },
 }
 
+pub use json_structs::TwitterApiError;
+
 #[derive(Clone, Debug)]
 pub struct TwitterTweet {
     pub id: i64,
@@ -23,13 +77,42 @@ pub struct TwitterTweet {
     pub author_name: String,
     pub author_username: String,
     pub text: String,
+    pub entities: Vec<PostEntity>,
     pub media: Vec<TwitterMedia>,
+    /// ID of tweet, that this tweet replies to. Only self-replies are collected
+    pub reply_to: Option<i64>,
+    /// Tweet quoted by this tweet, without media
+    pub quoted: Option<Box<TwitterTweet>>,
+    /// IDs of all versions of tweet, oldest first. Empty if tweet was never edited
+    pub edit_history: Vec<i64>,
+}
+
+/// Result of [`TwitterClient::fetch_timeline`]
+pub struct TwitterTimeline {
+    /// New tweets
+    pub tweets: Vec<TwitterTweet>,
+    /// Token of the next page, if [`TwitterClient::max_pages`] pages were fetched
+    /// before reaching `last_id`. Older new tweets are fetched by passing it back
+    pub next_token: Option<String>,
+}
+
+/// Result of [`TwitterClient::lookup_tweets`]
+pub struct TwitterLookup {
+    /// Found tweets
+    pub tweets: Vec<TwitterTweet>,
+    /// IDs of deleted tweets
+    pub deleted: Vec<i64>,
 }
 
 #[derive(Clone, Debug)]
-pub enum TwitterMedia {
-    Photo(String),
-    Video(String),
+pub struct TwitterMedia {
+    pub media_type: MediaType,
+    /// URL of photo or best mp4 variant of video
+    pub url: String,
+    pub alt_text: Option<String>,
+    pub width: Option<i32>,
+    pub height: Option<i32>,
+    pub duration_ms: Option<i32>,
 }
 
 pub struct TwitterUser {
@@ -42,11 +125,36 @@ pub struct TwitterUser {
 mod json_structs {
     use serde::Deserialize;
 
+    #[derive(Clone, Debug, Deserialize)]
+    /// Element of `errors[]` array (or whole error response) of twitter API
+    pub struct TwitterApiError {
+        pub title: String,
+        #[serde(default)]
+        pub detail: String,
+        #[serde(rename = "type", default)]
+        pub kind: String,
+        /// ID of resource (like tweet), that error is about
+        #[serde(default)]
+        pub resource_id: String,
+    }
+    #[derive(Deserialize, Default)]
+    pub struct TwitterErrorResponse {
+        #[serde(default)]
+        pub errors: Vec<TwitterApiError>,
+        /// Present if response is a single problem (like 401)
+        #[serde(flatten)]
+        pub problem: Option<TwitterApiError>,
+    }
     #[derive(Deserialize)]
     pub struct TwitterUserResponse {
         pub data: TwitterUserResponseUser,
     }
     #[derive(Deserialize)]
+    pub struct TwitterUsersResponse {
+        #[serde(default)]
+        pub data: Vec<TwitterUserResponseUser>,
+    }
+    #[derive(Deserialize)]
     pub struct TwitterUserResponseUser {
         pub id: String,
         pub name: String,
@@ -59,12 +167,75 @@ mod json_structs {
         pub data: Vec<TwitterRawTweet>,
         #[serde(default)]
         pub includes: TwitterTimelineIncludes,
+        #[serde(default)]
+        pub meta: TwitterTimelineMeta,
+        #[serde(default)]
+        pub errors: Vec<TwitterApiError>,
+    }
+    #[derive(Deserialize)]
+    pub struct TwitterTweetsResponse {
+        #[serde(default)]
+        pub data: Vec<TwitterRawTweet>,
+        #[serde(default)]
+        pub includes: TwitterTimelineIncludes,
+        /// Errors of tweets, that can't be returned (like deleted ones)
+        #[serde(default)]
+        pub errors: Vec<TwitterApiError>,
+    }
+    #[derive(Deserialize, Default)]
+    pub struct TwitterTimelineMeta {
+        /// Token of next (older) page, absent on the last page
+        pub next_token: Option<String>,
     }
     #[derive(Deserialize)]
     pub struct TwitterRawTweet {
         pub id: String,
         pub text: String,
+        pub author_id: Option<String>,
         pub attachments: Option<TwitterRawTweetAttachments>,
+        #[serde(default)]
+        pub entities: TwitterRawTweetEntities,
+        #[serde(default)]
+        pub referenced_tweets: Vec<TwitterReferencedTweet>,
+        pub in_reply_to_user_id: Option<String>,
+        /// IDs of all versions of tweet, oldest first
+        #[serde(default)]
+        pub edit_history_tweet_ids: Vec<String>,
+    }
+    #[derive(Deserialize)]
+    pub struct TwitterReferencedTweet {
+        /// `replied_to`, `quoted` or `retweeted`
+        pub r#type: String,
+        pub id: String,
+    }
+    #[derive(Deserialize, Default)]
+    pub struct TwitterRawTweetEntities {
+        #[serde(default)]
+        pub urls: Vec<TwitterUrlEntity>,
+        #[serde(default)]
+        pub mentions: Vec<TwitterMentionEntity>,
+        #[serde(default)]
+        pub hashtags: Vec<TwitterHashtagEntity>,
+    }
+    #[derive(Deserialize)]
+    pub struct TwitterUrlEntity {
+        pub start: usize,
+        pub end: usize,
+        pub expanded_url: Option<String>,
+        /// Present if url links to tweet media
+        pub media_key: Option<String>,
+    }
+    #[derive(Deserialize)]
+    pub struct TwitterMentionEntity {
+        pub start: usize,
+        pub end: usize,
+        pub username: String,
+    }
+    #[derive(Deserialize)]
+    pub struct TwitterHashtagEntity {
+        pub start: usize,
+        pub end: usize,
+        pub tag: String,
     }
     #[derive(Deserialize)]
     pub struct TwitterRawTweetAttachments {
@@ -77,6 +248,9 @@ mod json_structs {
         pub media: Vec<TwitterTimelineMedia>,
         #[serde(default)]
         pub users: Vec<TwitterTimelineUser>,
+        /// Referenced (quoted and replied) tweets
+        #[serde(default)]
+        pub tweets: Vec<TwitterRawTweet>,
     }
     #[derive(Deserialize)]
     pub struct TwitterTimelineUser {
@@ -90,6 +264,10 @@ mod json_structs {
         pub r#type: String,
         pub url: Option<String>,
         pub variants: Option<Vec<TwitterTimelineMediaVariants>>,
+        pub alt_text: Option<String>,
+        pub width: Option<i32>,
+        pub height: Option<i32>,
+        pub duration_ms: Option<i32>,
     }
     #[derive(Deserialize)]
     pub struct TwitterTimelineMediaVariants {
@@ -104,36 +282,159 @@ impl Display for TwitterError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Self::HttpError(e) => e.fmt(f),
-            Self::APIError => write!(f, "API returned error"),
+            Self::APIError { status, errors } => {
+                write!(f, "API returned error ({status})")?;
+                fmt_api_errors(f, errors)
+            }
+            Self::NotFound(errors) => {
+                write!(f, "Not found")?;
+                fmt_api_errors(f, errors)
+            }
+            Self::Suspended(errors) => {
+                write!(f, "User is suspended")?;
+                fmt_api_errors(f, errors)
+            }
+            Self::Protected(errors) => {
+                write!(f, "User is protected")?;
+                fmt_api_errors(f, errors)
+            }
+            Self::Unauthorized { status, errors } => {
+                write!(f, "Unauthorized ({status})")?;
+                fmt_api_errors(f, errors)
+            }
+            Self::RateLimited { reset_at } => write!(f, "Rate limited until {reset_at}"),
         }
     }
 }
 impl std::error::Error for TwitterError {}
 
+/// Writes `: title (detail); ...` of api errors, if any
+fn fmt_api_errors(f: &mut std::fmt::Formatter<'_>, errors: &[TwitterApiError]) -> std::fmt::Result {
+    for (i, e) in errors.iter().enumerate() {
+        let sep = if i == 0 { ": " } else { "; " };
+        write!(f, "{sep}{}", e.title)?;
+        if !e.detail.is_empty() {
+            write!(f, " ({})", e.detail)?;
+        }
+    }
+
+    Ok(())
+}
+
+impl TwitterRawTweet {
+    /// ID of tweet referenced with `kind` (`replied_to` or `quoted`)
+    fn referenced(&self, kind: &str) -> Option<&str> {
+        self.referenced_tweets
+            .iter()
+            .find(|f| f.r#type == kind)
+            .map(|f| f.id.as_str())
+    }
+}
+
+impl TwitterError {
+    /// Classifies error response of twitter API. Note that twitter returns some errors
+    /// (like not found users) with 200 status
+    fn from_response(status: u16, text: &str) -> Self {
+        let res: TwitterErrorResponse = serde_json::from_str(text).unwrap_or_default();
+        let mut errors = res.errors;
+        if errors.is_empty() {
+            errors.extend(res.problem);
+        }
+
+        let has_type = |kind: &str| errors.iter().any(|f| f.kind.ends_with(kind));
+
+        if matches!(status, 401 | 403) {
+            Self::Unauthorized { status, errors }
+        } else if errors.iter().any(|f| f.detail.contains("suspended")) {
+            Self::Suspended(errors)
+        } else if has_type("/resource-not-found") || status == 404 {
+            Self::NotFound(errors)
+        } else if has_type("/not-authorized-for-resource") {
+            Self::Protected(errors)
+        } else {
+            Self::APIError { status, errors }
+        }
+    }
+}
+
 impl TwitterClient {
     /// Create new instance of twitter client
     pub fn new(token: String) -> Self {
-        Self { token }
+        Self {
+            token,
+            base_url: API_URL.to_string(),
+            max_pages: DEFAULT_MAX_PAGES,
+            backfill: DEFAULT_BACKFILL,
+            rate_limits: Default::default(),
+        }
+    }
+
+    /// Returns [`TwitterError::RateLimited`] if rate limit of `endpoint` is known to be
+    /// exhausted, so request is not sent at all
+    fn check_rate_limit(&self, endpoint: &'static str) -> Result<(), TwitterError> {
+        let mut limits = self.rate_limits.lock().unwrap();
+
+        match limits.get(endpoint) {
+            Some(&reset_at) if reset_at > unix_now() => Err(TwitterError::RateLimited { reset_at }),
+            Some(_) => {
+                limits.remove(endpoint);
+                Ok(())
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// Remembers rate limit state of `endpoint` from response headers. Returns
+    /// [`TwitterError::RateLimited`] on 429 response
+    fn update_rate_limit(
+        &self,
+        endpoint: &'static str,
+        res: &reqwest::Response,
+    ) -> Result<(), TwitterError> {
+        let header = |name: &str| -> Option<i64> {
+            res.headers()
+                .get(name)
+                .and_then(|f| f.to_str().ok())
+                .and_then(|f| f.parse().ok())
+        };
+        let reset_at = header("x-rate-limit-reset");
+
+        if res.status() == reqwest::StatusCode::TOO_MANY_REQUESTS {
+            let reset_at = reset_at.unwrap_or_else(|| unix_now() + RATE_LIMIT_WINDOW);
+            self.rate_limits.lock().unwrap().insert(endpoint, reset_at);
+
+            return Err(TwitterError::RateLimited { reset_at });
+        }
+
+        if let (Some(0), Some(reset_at)) = (header("x-rate-limit-remaining"), reset_at) {
+            self.rate_limits.lock().unwrap().insert(endpoint, reset_at);
+        }
+
+        Ok(())
     }
 
     /// Fetches user by id
     pub async fn fetch_user(&self, user_id: u64) -> Result<TwitterUser, TwitterError> {
+        self.check_rate_limit("users")?;
+
         let client = reqwest::ClientBuilder::new()
             .use_rustls_tls()
             .build()
             .unwrap();
         let res = client
-            .get(format!("https://api.twitter.com/2/users/{}", user_id))
+            .get(format!("{}/2/users/{}", self.base_url, user_id))
             .query(&[("user.fields", "name,username,profile_image_url")])
             .header("Authorization", format!("Bearer {}", self.token))
             .send()
             .await
             .map_err(TwitterError::HttpError)?;
+        self.update_rate_limit("users", &res)?;
 
+        let status = res.status();
         let text = res.text().await.map_err(TwitterError::HttpError)?;
         let res: TwitterUserResponse = match serde_json::from_str(&text) {
-            Ok(r) => r,
-            Err(_) => return Err(TwitterError::APIError),
+            Ok(r) if status.is_success() => r,
+            _ => return Err(TwitterError::from_response(status.as_u16(), &text)),
         };
         let user = TwitterUser {
             id: res.data.id.parse().unwrap(),
@@ -145,31 +446,76 @@ impl TwitterClient {
         Ok(user)
     }
 
+    /// Fetches users by ids (up to [`MAX_LOOKUP_IDS`]). Users, that are not found (or
+    /// suspended), are skipped
+    pub async fn fetch_users(&self, user_ids: &[u64]) -> Result<Vec<TwitterUser>, TwitterError> {
+        self.check_rate_limit("users/ids")?;
+
+        let client = reqwest::ClientBuilder::new()
+            .use_rustls_tls()
+            .build()
+            .unwrap();
+        let res = client
+            .get(format!("{}/2/users", self.base_url))
+            .query(&[
+                ("ids", user_ids.iter().join(",")),
+                ("user.fields", "name,username,profile_image_url".into()),
+            ])
+            .header("Authorization", format!("Bearer {}", self.token))
+            .send()
+            .await
+            .map_err(TwitterError::HttpError)?;
+        self.update_rate_limit("users/ids", &res)?;
+
+        let status = res.status();
+        let text = res.text().await.map_err(TwitterError::HttpError)?;
+        let res: TwitterUsersResponse = match serde_json::from_str(&text) {
+            Ok(r) if status.is_success() => r,
+            _ => return Err(TwitterError::from_response(status.as_u16(), &text)),
+        };
+
+        let users = res
+            .data
+            .into_iter()
+            .map(|f| TwitterUser {
+                id: f.id.parse().unwrap(),
+                name: f.name,
+                username: f.username,
+                profile_image_url: f.profile_image_url,
+            })
+            .collect();
+
+        Ok(users)
+    }
+
     /// Fetcher user by username
     pub async fn fetch_user_by_username(
         &self,
         username: &str,
     ) -> Result<TwitterUser, TwitterError> {
+        self.check_rate_limit("users/by/username")?;
+
         let client = reqwest::ClientBuilder::new()
             .use_rustls_tls()
             .build()
             .unwrap();
         let res = client
             .get(format!(
-                "https://api.twitter.com/2/users/by/username/{}",
-                username
+                "{}/2/users/by/username/{}",
+                self.base_url, username
             ))
             .query(&[("user.fields", "name,username,profile_image_url")])
             .header("Authorization", format!("Bearer {}", self.token))
             .send()
             .await
-            .map_err(TwitterError::HttpError)
-            .unwrap();
+            .map_err(TwitterError::HttpError)?;
+        self.update_rate_limit("users/by/username", &res)?;
 
+        let status = res.status();
         let text = res.text().await.map_err(TwitterError::HttpError)?;
         let res: TwitterUserResponse = match serde_json::from_str(&text) {
-            Ok(r) => r,
-            Err(_) => return Err(TwitterError::APIError),
+            Ok(r) if status.is_success() => r,
+            _ => return Err(TwitterError::from_response(status.as_u16(), &text)),
         };
         let user = TwitterUser {
             id: res.data.id.parse().unwrap(),
@@ -181,54 +527,203 @@ impl TwitterClient {
         Ok(user)
     }
 
-    /// Fetch `user_id` timeline and remove from it all tweets after `last_id` (if present)
+    /// Looks up current state of tweets by ids (up to [`MAX_LOOKUP_IDS`]). Quoted tweets
+    /// and replies are not resolved
+    pub async fn lookup_tweets(&self, ids: &[i64]) -> Result<TwitterLookup, TwitterError> {
+        self.check_rate_limit("tweets")?;
+
+        let client = reqwest::ClientBuilder::new()
+            .use_rustls_tls()
+            .build()
+            .unwrap();
+        let res = client
+            .get(format!("{}/2/tweets", self.base_url))
+            .query(&[
+                ("ids", ids.iter().join(",")),
+                (
+                    "tweet.fields",
+                    "attachments,author_id,entities,edit_history_tweet_ids".into(),
+                ),
+                ("expansions", "attachments.media_keys,author_id".into()),
+                (
+                    "media.fields",
+                    "type,url,variants,alt_text,width,height,duration_ms".into(),
+                ),
+                ("user.fields", "name,username".into()),
+            ])
+            .header("Authorization", format!("Bearer {}", self.token))
+            .send()
+            .await
+            .map_err(TwitterError::HttpError)?;
+        self.update_rate_limit("tweets", &res)?;
+
+        let status = res.status();
+        let text = res.text().await.map_err(TwitterError::HttpError)?;
+        let data = match serde_json::from_str::<TwitterTweetsResponse>(&text) {
+            Ok(r) if status.is_success() => r,
+            _ => return Err(TwitterError::from_response(status.as_u16(), &text)),
+        };
+
+        // tweets of suspended users are not found too, but they are not deleted
+        let deleted = data
+            .errors
+            .iter()
+            .filter(|f| f.kind.ends_with("/resource-not-found") && !f.detail.contains("suspended"))
+            .flat_map(|f| f.resource_id.parse())
+            .collect();
+
+        let tweets = data
+            .data
+            .iter()
+            .filter_map(|tweet| {
+                let author = data
+                    .includes
+                    .users
+                    .iter()
+                    .find(|f| Some(&f.id) == tweet.author_id.as_ref())?;
+
+                Some(convert_tweet(tweet, author, &data.includes))
+            })
+            .collect();
+
+        Ok(TwitterLookup { tweets, deleted })
+    }
+
+    /// Fetch `user_id` tweets newer than `last_id`, following pages (from
+    /// `pagination_token`, if passed) up to [`TwitterClient::max_pages`]. If `last_id` is
+    /// `None`, fetches [`TwitterClient::backfill`] latest tweets. If `threads` is set,
+    /// self-replies are fetched too
     pub async fn fetch_timeline(
         &self,
         user_id: &str,
         last_id: Option<i64>,
-    ) -> Result<Vec<TwitterTweet>, TwitterError> {
+        threads: bool,
+        pagination_token: Option<&str>,
+    ) -> Result<TwitterTimeline, TwitterError> {
+        let page_size = match last_id {
+            Some(_) => PAGE_SIZE.1,
+            None => self.backfill.clamp(PAGE_SIZE.0, PAGE_SIZE.1),
+        };
+
+        let mut res = vec![];
+        let mut token = pagination_token.map(str::to_string);
+        for page in 0..self.max_pages.max(1) {
+            let (tweets, next_token) = self
+                .fetch_timeline_page(user_id, last_id, threads, page_size, token.as_deref())
+                .await?;
+            res.extend(tweets);
+            token = next_token;
+
+            if last_id.is_none() && res.len() >= self.backfill {
+                break;
+            }
+            if token.is_none() {
+                break;
+            }
+
+            if last_id.is_some() && page + 1 >= self.max_pages {
+                eprintln!(
+                    "Warning: timeline of {user_id} has more than {} pages of new tweets, \
+                     older ones are fetched later",
+                    self.max_pages
+                );
+
+                return Ok(TwitterTimeline {
+                    tweets: res,
+                    next_token: token,
+                });
+            }
+        }
+
+        if last_id.is_none() {
+            res.sort_by_key(|f| f.id);
+            res.drain(..res.len().saturating_sub(self.backfill));
+        }
+
+        Ok(TwitterTimeline {
+            tweets: res,
+            next_token: None,
+        })
+    }
+
+    /// Fetch one page of `user_id` timeline and remove from it all tweets after `last_id`
+    /// (if present). Returns tweets and token of the next page
+    async fn fetch_timeline_page(
+        &self,
+        user_id: &str,
+        last_id: Option<i64>,
+        threads: bool,
+        max_results: usize,
+        pagination_token: Option<&str>,
+    ) -> Result<(Vec<TwitterTweet>, Option<String>), TwitterError> {
         let query = {
             let mut query = HashMap::from([
-                ("exclude", "replies,retweets".into()),
-                ("tweet.fields", "attachments,author_id".into()),
-                ("expansions", "attachments.media_keys,author_id".into()),
-                ("media.fields", "type,url,variants".into()),
-                ("max_results", "5".into()),
+                (
+                    "exclude",
+                    if threads {
+                        "retweets"
+                    } else {
+                        "replies,retweets"
+                    }
+                    .into(),
+                ),
+                (
+                    "tweet.fields",
+                    "attachments,author_id,entities,referenced_tweets,in_reply_to_user_id,\
+                     edit_history_tweet_ids"
+                        .into(),
+                ),
+                (
+                    "expansions",
+                    "attachments.media_keys,author_id,referenced_tweets.id,\
+                     referenced_tweets.id.author_id"
+                        .into(),
+                ),
+                (
+                    "media.fields",
+                    "type,url,variants,alt_text,width,height,duration_ms".into(),
+                ),
+                ("max_results", max_results.to_string()),
             ]);
 
             if let Some(last_id) = last_id {
                 query.insert("since_id", last_id.to_string());
             }
+            if let Some(token) = pagination_token {
+                query.insert("pagination_token", token.to_string());
+            }
 
             query
         };
 
+        self.check_rate_limit("users/tweets")?;
+
         let client = reqwest::ClientBuilder::new()
             .use_rustls_tls()
             .build()
             .unwrap();
         let res = client
-            .get(format!(
-                "https://api.twitter.com/2/users/{}/tweets",
-                user_id
-            ))
+            .get(format!("{}/2/users/{}/tweets", self.base_url, user_id))
             .query(&query)
             .header("Authorization", format!("Bearer {}", self.token))
             .send()
             .await
             .map_err(TwitterError::HttpError)?;
+        self.update_rate_limit("users/tweets", &res)?;
 
+        let status = res.status();
         let text = res.text().await.map_err(TwitterError::HttpError)?;
-        let data: TwitterUserTimeline = match serde_json::from_str(&text) {
-            Ok(r) => r,
-            Err(_) => return Err(TwitterError::APIError),
+        let data = match serde_json::from_str::<TwitterUserTimeline>(&text) {
+            Ok(r) if status.is_success() && (r.errors.is_empty() || !r.data.is_empty()) => r,
+            _ => return Err(TwitterError::from_response(status.as_u16(), &text)),
         };
 
         if data.data.is_empty() {
-            return Ok(vec![]);
+            return Ok((vec![], None));
         }
 
         let last_id = last_id.unwrap_or_default();
+        let next_token = data.meta.next_token;
 
         let mut res = vec![];
         let author = data
@@ -238,76 +733,319 @@ impl TwitterClient {
             .find(|f| f.id == user_id)
             .expect("api doesn't returned author object");
 
-        for tweet in data.data {
-            let id = tweet.id.parse().expect("tweet id is not a number");
+        for tweet in &data.data {
+            let id: i64 = tweet.id.parse().expect("tweet id is not a number");
 
             if id <= last_id {
                 continue;
             }
 
-            let media: Vec<TwitterMedia> = tweet
-                .attachments
-                .iter()
-                .flat_map(|attachments| &attachments.media_keys)
-                .map(|f| data.includes.media.iter().position(|r| &r.media_key == f))
-                .map(|idx| &data.includes.media[idx.expect("api returned invalid media_key")])
-                .map(|m| {
-                    if m.r#type == "photo" {
-                        TwitterMedia::Photo(m.url.clone().unwrap())
-                    } else {
-                        TwitterMedia::Video(
-                            m.variants
-                                .as_ref()
-                                .unwrap()
-                                .iter()
-                                .filter(|v| v.content_type == "video/mp4")
-                                .max_by_key(|v| v.bitrate)
-                                .expect("api doesn't returned normal video")
-                                .url
-                                .clone(),
-                        )
-                    }
-                })
-                .collect();
-
-            lazy_static! {
-                static ref RE: Regex = Regex::new("https://t\\.co/[^ ]+$").unwrap();
+            let reply_to = tweet.referenced("replied_to");
+            // only self-replies (threads) are collected
+            if reply_to.is_some() && tweet.in_reply_to_user_id.as_deref() != Some(user_id) {
+                continue;
             }
 
-            let text = if media.is_empty() {
-                tweet.text
-            } else {
-                RE.replace(&tweet.text, "").to_string()
-            };
+            let quoted = tweet
+                .referenced("quoted")
+                .and_then(|id| data.includes.tweets.iter().find(|f| f.id == id))
+                .and_then(|quoted| {
+                    let author = data
+                        .includes
+                        .users
+                        .iter()
+                        .find(|f| Some(&f.id) == quoted.author_id.as_ref())?;
+
+                    Some(Box::new(convert_tweet(quoted, author, &data.includes)))
+                });
 
             res.push(TwitterTweet {
-                id,
-                author_id: user_id.parse().unwrap(),
-                author_name: author.name.clone(),
-                author_username: author.username.clone(),
-                text,
-                media,
+                reply_to: reply_to.and_then(|f| f.parse().ok()),
+                quoted,
+                ..convert_tweet(tweet, author, &data.includes)
             });
         }
 
-        Ok(res)
+        Ok((res, next_token))
     }
 }
 
-impl TwitterMedia {
-    /// Get type of media
-    pub fn media_type(&self) -> entity::post_media::MediaType {
-        match self {
-            Self::Photo(_) => entity::post_media::MediaType::Photo,
-            Self::Video(_) => entity::post_media::MediaType::Video,
-        }
+/// Converts tweet of `author` with its media, without references to other tweets. Media
+/// missing in `includes` (like media of quoted tweets) is skipped
+fn convert_tweet(
+    tweet: &TwitterRawTweet,
+    author: &TwitterTimelineUser,
+    includes: &TwitterTimelineIncludes,
+) -> TwitterTweet {
+    let media = tweet
+        .attachments
+        .iter()
+        .flat_map(|attachments| &attachments.media_keys)
+        .filter_map(|f| includes.media.iter().find(|r| &r.media_key == f))
+        .map(|m| {
+            let (media_type, url) = match m.r#type.as_str() {
+                "photo" => (MediaType::Photo, m.url.clone().unwrap()),
+                kind => (
+                    if kind == "animated_gif" {
+                        MediaType::Animation
+                    } else {
+                        MediaType::Video
+                    },
+                    m.variants
+                        .as_ref()
+                        .unwrap()
+                        .iter()
+                        .filter(|v| v.content_type == "video/mp4")
+                        .max_by_key(|v| v.bitrate)
+                        .expect("api doesn't returned normal video")
+                        .url
+                        .clone(),
+                ),
+            };
+
+            TwitterMedia {
+                media_type,
+                url,
+                alt_text: m.alt_text.clone().filter(|f| !f.is_empty()),
+                width: m.width,
+                height: m.height,
+                duration_ms: m.duration_ms,
+            }
+        })
+        .collect();
+
+    let (text, entities) = expand_entities(&tweet.text, &tweet.entities);
+
+    // tweets, that were never edited, have only their own id in edit history
+    let edit_history = match tweet.edit_history_tweet_ids.len() {
+        0 | 1 => vec![],
+        _ => tweet
+            .edit_history_tweet_ids
+            .iter()
+            .flat_map(|f| f.parse())
+            .collect(),
+    };
+
+    TwitterTweet {
+        id: tweet.id.parse().expect("tweet id is not a number"),
+        author_id: author.id.parse().expect("user id is not a number"),
+        author_name: author.name.clone(),
+        author_username: author.username.clone(),
+        text,
+        entities,
+        media,
+        reply_to: None,
+        quoted: None,
+        edit_history,
     }
+}
 
-    /// Get url to media
-    pub fn media_url(self) -> String {
-        match self {
-            Self::Photo(s) => s,
-            Self::Video(s) => s,
+/// Smallest tweet id, that could be created at `time` (unix seconds)
+pub fn snowflake_at(time: i64) -> i64 {
+    (time * 1000 - SNOWFLAKE_EPOCH).max(0) << 22
+}
+
+/// Unix time (seconds) when tweet with `id` was created
+pub fn snowflake_time(id: i64) -> i64 {
+    ((id >> 22) + SNOWFLAKE_EPOCH) / 1000
+}
+
+/// Replaces t.co links in tweet text with expanded urls, removes links to tweet media and
+/// converts twitter entities to [`PostEntity`] (with offsets in resulting text)
+fn expand_entities(text: &str, raw: &TwitterRawTweetEntities) -> (String, Vec<PostEntity>) {
+    enum Raw<'a> {
+        Url(&'a TwitterUrlEntity),
+        Mention(&'a TwitterMentionEntity),
+        Hashtag(&'a TwitterHashtagEntity),
+    }
+
+    let mut raw_entities = raw
+        .urls
+        .iter()
+        .map(|f| (f.start, f.end, Raw::Url(f)))
+        .chain(
+            raw.mentions
+                .iter()
+                .map(|f| (f.start, f.end, Raw::Mention(f))),
+        )
+        .chain(
+            raw.hashtags
+                .iter()
+                .map(|f| (f.start, f.end, Raw::Hashtag(f))),
+        )
+        .collect::<Vec<_>>();
+    raw_entities.sort_by_key(|f| f.0);
+
+    // twitter offsets are in code points
+    let chars = text.chars().collect::<Vec<_>>();
+
+    let mut res = String::with_capacity(text.len());
+    let mut res_len = 0;
+    let mut entities = vec![];
+    let mut cursor = 0;
+
+    for (start, end, entity) in raw_entities {
+        if start < cursor || start > end || end > chars.len() {
+            continue;
         }
+
+        let before = &chars[cursor..start];
+        res.extend(before);
+        res_len += before.len();
+        cursor = end;
+
+        let (kind, shown, url) = match entity {
+            Raw::Url(TwitterUrlEntity {
+                media_key: Some(_), ..
+            }) => continue,
+            Raw::Url(u) => {
+                let url = match &u.expanded_url {
+                    Some(u) => u.clone(),
+                    None => chars[start..end].iter().collect(),
+                };
+
+                (EntityKind::Url, url.clone(), url)
+            }
+            Raw::Mention(m) => (
+                EntityKind::Mention,
+                chars[start..end].iter().collect(),
+                format!("https://twitter.com/{}", m.username),
+            ),
+            Raw::Hashtag(h) => (
+                EntityKind::Hashtag,
+                chars[start..end].iter().collect(),
+                format!("https://twitter.com/hashtag/{}", h.tag),
+            ),
+        };
+
+        let shown_len = shown.chars().count();
+        res.push_str(&shown);
+        entities.push(PostEntity {
+            kind,
+            start: res_len,
+            end: res_len + shown_len,
+            url,
+        });
+        res_len += shown_len;
+    }
+
+    res.extend(&chars[cursor..]);
+    // media links are usually at the end of text
+    let res = res.trim_end().to_string();
+
+    (res, entities)
+}
+
+#[cfg(test)]
+mod tests {
+    use entity::post::EntityKind;
+    use mockserver::{fixture, MockRoute, MockServer};
+
+    use super::{
+        expand_entities, json_structs::TwitterRawTweetEntities, snowflake_at, snowflake_time,
+        TwitterClient, TwitterError, SNOWFLAKE_EPOCH,
+    };
+
+    #[test]
+    /// Check [`expand_entities`] expands links, keeps mentions and removes media links
+    fn test_expand_entities() {
+        let text = "hi @jack 🌧 look https://t.co/abc #rust https://t.co/media";
+        let raw: TwitterRawTweetEntities = serde_json::from_str(
+            r#"{
+                "urls": [
+                    {"start": 16, "end": 32, "expanded_url": "https://example.com/page"},
+                    {"start": 39, "end": 57, "expanded_url": "https://twitter.com/i/photo/1", "media_key": "3_1"}
+                ],
+                "mentions": [{"start": 3, "end": 8, "username": "jack"}],
+                "hashtags": [{"start": 33, "end": 38, "tag": "rust"}]
+            }"#,
+        )
+        .unwrap();
+
+        let (res, entities) = expand_entities(text, &raw);
+
+        assert_eq!(res, "hi @jack 🌧 look https://example.com/page #rust");
+        assert_eq!(entities.len(), 3);
+
+        let chars = res.chars().collect::<Vec<_>>();
+        let shown = |i: usize| {
+            chars[entities[i].start..entities[i].end]
+                .iter()
+                .collect::<String>()
+        };
+
+        assert_eq!(entities[0].kind, EntityKind::Mention);
+        assert_eq!(shown(0), "@jack");
+        assert_eq!(entities[0].url, "https://twitter.com/jack");
+        assert_eq!(entities[1].kind, EntityKind::Url);
+        assert_eq!(shown(1), "https://example.com/page");
+        assert_eq!(entities[2].kind, EntityKind::Hashtag);
+        assert_eq!(shown(2), "#rust");
+    }
+
+    #[test]
+    /// Check [`snowflake_at`] bounds ids by their creation time and [`snowflake_time`]
+    /// returns it
+    fn test_snowflake_at() {
+        // created at 2022-10-26 18:45:58.034 UTC
+        let id: i64 = 1585341984679469056;
+        let created_at = 1666809958;
+
+        assert_eq!((id >> 22) + SNOWFLAKE_EPOCH, created_at * 1000 + 34);
+        assert!(snowflake_at(created_at) <= id);
+        assert!(snowflake_at(created_at + 1) > id);
+        assert_eq!(snowflake_at(0), 0);
+        assert_eq!(snowflake_time(id), created_at);
+    }
+
+    #[test]
+    /// Check [`TwitterError::from_response`] recognizes twitter error responses
+    fn test_error_from_response() {
+        let not_found = r#"{"errors":[{"value":"nobody","detail":"Could not find user with username: [nobody].","title":"Not Found Error","resource_type":"user","parameter":"username","resource_id":"nobody","type":"https://api.twitter.com/2/problems/resource-not-found"}]}"#;
+        let suspended = r#"{"errors":[{"parameter":"username","resource_id":"spam","value":"spam","detail":"User has been suspended: [spam].","title":"Forbidden","resource_type":"user","type":"https://api.twitter.com/2/problems/resource-not-found"}]}"#;
+        let protected = r#"{"errors":[{"title":"Authorization Error","type":"https://api.twitter.com/2/problems/not-authorized-for-resource","detail":"Sorry, you are not authorized to see the user with id: [1]."}]}"#;
+        let unauthorized =
+            r#"{"title":"Unauthorized","type":"about:blank","status":401,"detail":"Unauthorized"}"#;
+
+        assert!(matches!(
+            TwitterError::from_response(200, not_found),
+            TwitterError::NotFound(e) if e[0].title == "Not Found Error"
+        ));
+        assert!(matches!(
+            TwitterError::from_response(200, suspended),
+            TwitterError::Suspended(_)
+        ));
+        assert!(matches!(
+            TwitterError::from_response(200, protected),
+            TwitterError::Protected(_)
+        ));
+        assert!(matches!(
+            TwitterError::from_response(401, unauthorized),
+            TwitterError::Unauthorized { status: 401, errors } if errors[0].detail == "Unauthorized"
+        ));
+        assert!(matches!(
+            TwitterError::from_response(500, "<html>"),
+            TwitterError::APIError { status: 500, errors } if errors.is_empty()
+        ));
+    }
+
+    #[rocket::async_test]
+    /// Check [`TwitterClient::lookup_tweets`] returns edit history and reports deleted tweets
+    async fn test_lookup_tweets() {
+        let server = MockServer::start(vec![MockRoute::new(
+            "/2/tweets?ids=1001%2C1003",
+            200,
+            fixture("twitter/tweets.json"),
+        )])
+        .await;
+        let mut client = TwitterClient::new("token".to_string());
+        client.base_url = server.url.clone();
+
+        let lookup = client.lookup_tweets(&[1001, 1003]).await.unwrap();
+
+        assert_eq!(lookup.tweets.len(), 1);
+        assert_eq!(lookup.tweets[0].id, 1001);
+        assert_eq!(lookup.tweets[0].edit_history, [1001, 1006]);
+        assert_eq!(lookup.deleted, [1003]);
     }
 }
@@ -1,16 +1,21 @@
-use entity::telegram_user::POWER_MOD;
-use itertools::Itertools;
+use entity::{author::Platform, telegram_user::POWER_MOD};
 use migration::OnConflict;
-use rocket::{get, put, routes, Route, State};
+use rocket::{get, patch, put, routes, serde::json::Json, Route, State};
 use sea_orm::{
-    ActiveModelBehavior, ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter, Set,
-    TryIntoModel,
+    ActiveModelBehavior, ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait,
+    QueryFilter, Set,
 };
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use std::ops::Deref;
 
-use crate::models::{
-    response::APIResponse, telegramauth::TelegramUser, twitterclient::TwitterClient,
+use crate::{
+    models::{
+        postfilter::{find_posts, PostCursor, PostFilter},
+        response::APIResponse,
+        telegramauth::TelegramUser,
+    },
+    sources::{SourceError, SourceUser, Sources},
+    workers::profiles::{find_by_username, record_rename},
 };
 
 pub fn routes() -> Vec<Route> {
@@ -20,6 +25,8 @@ pub fn routes() -> Vec<Route> {
         get_by_username,
         put_by_platform_id,
         put_by_username,
+        patch_by_platform_id,
+        patch_by_username,
         get_posts_by_platform_id,
         get_posts_by_username
     ]
@@ -29,6 +36,14 @@ pub fn routes() -> Vec<Route> {
 struct PostData {
     post: entity::post::Model,
     media: Vec<entity::post_media::Model>,
+    /// Position of post for `before` and `after`
+    cursor: String,
+}
+
+#[derive(Deserialize)]
+struct AuthorOptions {
+    collect_threads: Option<bool>,
+    collect_quotes: Option<bool>,
 }
 
 #[get("/")]
@@ -59,11 +74,7 @@ async fn get_by_platform_id(id: i64, db: &State<DatabaseConnection>) -> APIRespo
 
 #[get("/<id>", rank = 2)]
 async fn get_by_username(id: &str, db: &State<DatabaseConnection>) -> APIResponse {
-    let author = entity::author::Entity::find()
-        .filter(entity::author::Column::Username.eq(id))
-        .one(db.deref())
-        .await
-        .unwrap();
+    let author = find_by_username(db.deref(), id).await;
 
     match author {
         Some(a) => APIResponse::new(a),
@@ -71,11 +82,74 @@ async fn get_by_username(id: &str, db: &State<DatabaseConnection>) -> APIRespons
     }
 }
 
+/// Creates (or updates) author from source user
+async fn upsert_author(
+    db: &DatabaseConnection,
+    platform: Platform,
+    author: SourceUser,
+) -> entity::author::Model {
+    let known = entity::author::Entity::find()
+        .filter(entity::author::Column::PlatformId.eq(author.platform_id))
+        .one(db)
+        .await
+        .unwrap();
+    if let Some(known) = known {
+        record_rename(db, &known, &author.username).await;
+    }
+
+    let mut active = entity::author::ActiveModel::new();
+    active.platform_id = Set(author.platform_id);
+    active.platform = Set(platform);
+    active.external_id = Set(author.external_id);
+    active.name = Set(author.name);
+    active.username = Set(author.username);
+    active.avatar_url = Set(author.avatar_url);
+
+    entity::author::Entity::insert(active)
+        .on_conflict(
+            OnConflict::column(entity::author::Column::PlatformId)
+                .update_columns([
+                    entity::author::Column::Name,
+                    entity::author::Column::Username,
+                    entity::author::Column::AvatarUrl,
+                ])
+                .to_owned(),
+        )
+        .exec_with_returning(db)
+        .await
+        .unwrap()
+}
+
+/// Converts error of source to API error
+fn source_error(platform: Platform, err: SourceError) -> APIResponse {
+    match err {
+        SourceError::NotFound => APIResponse::error(404, format!("{platform:?} user not found")),
+        SourceError::Suspended => {
+            APIResponse::error(410, format!("{platform:?} user is suspended"))
+        }
+        SourceError::Protected => {
+            APIResponse::error(403, format!("{platform:?} user is protected"))
+        }
+        SourceError::Unauthorized => {
+            APIResponse::error(502, format!("{platform:?} rejected credentials of the bot"))
+        }
+        SourceError::RateLimited { reset_at } => APIResponse::error(
+            429,
+            format!("{platform:?} rate limit exceeded, try again after {reset_at}"),
+        ),
+        SourceError::Other(e) => {
+            eprintln!("Warning: {platform:?} source failed: {e}");
+
+            APIResponse::error(502, format!("{platform:?} returned error"))
+        }
+    }
+}
+
 #[put("/<id>")]
 async fn put_by_platform_id(
     id: i64,
     db: &State<DatabaseConnection>,
-    twitter_client: &State<TwitterClient>,
+    sources: &State<Sources>,
     telegram_user: TelegramUser,
 ) -> APIResponse {
     if id <= 0 {
@@ -92,42 +166,24 @@ async fn put_by_platform_id(
         return APIResponse::error(403, format!("You need power level {POWER_MOD} or higher"));
     }
 
-    let author = match twitter_client.fetch_user(id as u64).await {
-        Ok(a) => a,
-        _ => return APIResponse::error(404, "Twitter user not found"),
+    // platform ids are twitter ids
+    let Some(source) = sources.get(Platform::Twitter) else {
+        return APIResponse::error(503, "Twitter source is not available");
     };
 
-    let mut active = entity::author::ActiveModel::new();
-    active.platform_id = Set(author.id);
-    active.name = Set(author.name);
-    active.username = Set(author.username);
-    active.avatar_url = Set(author.profile_image_url);
-
-    let id = entity::author::Entity::insert(active.clone())
-        .on_conflict(
-            OnConflict::column(entity::author::Column::PlatformId)
-                .update_columns([
-                    entity::author::Column::Name,
-                    entity::author::Column::Username,
-                    entity::author::Column::AvatarUrl,
-                ])
-                .to_owned(),
-        )
-        .exec(db.deref())
-        .await
-        .unwrap()
-        .last_insert_id;
-
-    active.id = Set(id);
+    let author = match source.fetch_user(&id.to_string()).await {
+        Ok(a) => a,
+        Err(e) => return source_error(source.platform(), e),
+    };
 
-    APIResponse::new(active.try_into_model().unwrap())
+    APIResponse::new(upsert_author(db.deref(), source.platform(), author).await)
 }
 
 #[put("/<id>", rank = 2)]
 async fn put_by_username(
     id: &str,
     db: &State<DatabaseConnection>,
-    twitter_client: &State<TwitterClient>,
+    sources: &State<Sources>,
     telegram_user: TelegramUser,
 ) -> APIResponse {
     let user_is_mod = entity::telegram_user::Entity::find_by_id(telegram_user.id)
@@ -140,40 +196,103 @@ async fn put_by_username(
         return APIResponse::error(403, format!("You need power level {POWER_MOD} or higher"));
     }
 
-    let author = match twitter_client.fetch_user_by_username(id).await {
+    let Some(source) = sources.for_username(id) else {
+        return APIResponse::error(422, "Unknown username format");
+    };
+
+    let author = match source.resolve_username(id).await {
         Ok(a) => a,
-        _ => return APIResponse::error(404, "Twitter user not found"),
+        Err(e) => return source_error(source.platform(), e),
     };
 
-    let mut active = entity::author::ActiveModel::new();
-    active.platform_id = Set(author.id);
-    active.name = Set(author.name);
-    active.username = Set(author.username);
-    active.avatar_url = Set(author.profile_image_url);
+    APIResponse::new(upsert_author(db.deref(), source.platform(), author).await)
+}
 
-    let id = entity::author::Entity::insert(active.clone())
-        .on_conflict(
-            OnConflict::column(entity::author::Column::PlatformId)
-                .update_columns([
-                    entity::author::Column::Name,
-                    entity::author::Column::Username,
-                    entity::author::Column::AvatarUrl,
-                ])
-                .to_owned(),
-        )
-        .exec(db.deref())
+/// Updates collecting options of `author`, if user is moderator
+async fn patch_author(
+    db: &DatabaseConnection,
+    telegram_user: TelegramUser,
+    author: Option<entity::author::Model>,
+    data: AuthorOptions,
+) -> APIResponse {
+    let user_is_mod = entity::telegram_user::Entity::find_by_id(telegram_user.id)
+        .one(db)
         .await
         .unwrap()
-        .last_insert_id;
+        .is_some_and(|v| v.power_level >= POWER_MOD);
+
+    if !user_is_mod {
+        return APIResponse::error(403, format!("You need power level {POWER_MOD} or higher"));
+    }
+
+    let Some(author) = author else {
+        return APIResponse::error(404, "Author does not exists");
+    };
+
+    let mut active: entity::author::ActiveModel = author.into();
+    if let Some(collect_threads) = data.collect_threads {
+        active.collect_threads = Set(collect_threads);
+    }
+    if let Some(collect_quotes) = data.collect_quotes {
+        active.collect_quotes = Set(collect_quotes);
+    }
+
+    APIResponse::new(active.update(db).await.unwrap())
+}
+
+#[patch("/<id>", data = "<data>")]
+async fn patch_by_platform_id(
+    id: i64,
+    db: &State<DatabaseConnection>,
+    telegram_user: TelegramUser,
+    data: Json<AuthorOptions>,
+) -> APIResponse {
+    let expr = if id >= 0 {
+        entity::author::Entity::find().filter(entity::author::Column::PlatformId.eq(id))
+    } else {
+        entity::author::Entity::find_by_id(-id)
+    };
+
+    let author = expr.one(db.deref()).await.unwrap();
+
+    patch_author(db.deref(), telegram_user, author, data.0).await
+}
+
+#[patch("/<id>", rank = 2, data = "<data>")]
+async fn patch_by_username(
+    id: &str,
+    db: &State<DatabaseConnection>,
+    telegram_user: TelegramUser,
+    data: Json<AuthorOptions>,
+) -> APIResponse {
+    let author = find_by_username(db.deref(), id).await;
 
-    active.id = Set(id);
+    patch_author(db.deref(), telegram_user, author, data.0).await
+}
 
-    APIResponse::new(active.try_into_model().unwrap())
+/// Returns page of posts of author with internal id `author_id`
+async fn author_posts(db: &DatabaseConnection, author_id: i64, filter: &PostFilter) -> APIResponse {
+    let posts = entity::post::Entity::find().filter(entity::post::Column::AuthorId.eq(author_id));
+
+    match find_posts(db, posts, filter).await {
+        Ok(posts) => APIResponse::new(
+            posts
+                .into_iter()
+                .map(|(post, media)| PostData {
+                    cursor: PostCursor::of(&post).to_string(),
+                    post,
+                    media,
+                })
+                .collect::<Vec<_>>(),
+        ),
+        Err(e) => APIResponse::error(422, e),
+    }
 }
 
 #[get("/<id>/posts")]
 async fn get_posts_by_platform_id(
     id: i64,
+    filter: PostFilter,
     db: &State<DatabaseConnection>,
     _telegram_user: TelegramUser,
 ) -> APIResponse {
@@ -192,47 +311,22 @@ async fn get_posts_by_platform_id(
         -id
     };
 
-    let posts = entity::post::Entity::find()
-        .filter(entity::post::Column::AuthorId.eq(author_id))
-        .find_with_related(entity::post_media::Entity)
-        .all(db.deref())
-        .await
-        .unwrap()
-        .into_iter()
-        .map(|(post, media)| PostData { post, media })
-        .sorted_by_key(|f| f.post.platform_id)
-        .collect::<Vec<PostData>>();
-
-    APIResponse::new(posts)
+    author_posts(db.deref(), author_id, &filter).await
 }
 
 #[get("/<id>/posts", rank = 2)]
 async fn get_posts_by_username(
     id: &str,
+    filter: PostFilter,
     db: &State<DatabaseConnection>,
     _telegram_user: TelegramUser,
 ) -> APIResponse {
-    let author = entity::author::Entity::find()
-        .filter(entity::author::Column::Username.eq(id))
-        .one(db.deref())
-        .await
-        .unwrap();
+    let author = find_by_username(db.deref(), id).await;
 
     let author_id = match author {
         Some(a) => a.id,
         None => return APIResponse::error(404, "Author does not exists"),
     };
 
-    let posts = entity::post::Entity::find()
-        .filter(entity::post::Column::AuthorId.eq(author_id))
-        .find_with_related(entity::post_media::Entity)
-        .all(db.deref())
-        .await
-        .unwrap()
-        .into_iter()
-        .map(|(post, media)| PostData { post, media })
-        .sorted_by_key(|f| f.post.platform_id)
-        .collect::<Vec<PostData>>();
-
-    APIResponse::new(posts)
+    author_posts(db.deref(), author_id, &filter).await
 }
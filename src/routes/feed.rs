@@ -1,14 +1,38 @@
-use crate::models::{response::APIResponse, telegramauth::TelegramUser};
+use crate::{
+    models::{
+        postfilter::{find_posts, PostCursor, PostFilter},
+        postformat::{
+            format_post, post_length, MAX_CAPTION_LENGTH, MAX_MEDIA_GROUP_SIZE, MAX_TEXT_LENGTH,
+        },
+        response::APIResponse,
+        telegramauth::TelegramUser,
+        time::unix_now,
+    },
+    routes::user::get_or_create_user,
+    workers::{
+        posting::{is_duplicate, resume_thread},
+        profiles::find_by_username,
+    },
+};
 
-use itertools::Itertools;
-use migration::{Condition, OnConflict};
-use rocket::{delete, get, patch, put, routes, serde::json::Json, Route, State};
+use entity::{post::PostEntities, scheduled_post::PostStatus};
+use migration::{Condition, OnConflict, Query};
+use rocket::{delete, get, patch, post, put, routes, serde::json::Json, Route, State};
 use sea_orm::{
     ActiveModelBehavior, ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait,
-    ModelTrait, QueryFilter, QuerySelect, Set,
+    ModelTrait, QueryFilter, QueryOrder, Set,
 };
 use serde::{Deserialize, Serialize};
-use std::ops::Deref;
+use std::{
+    collections::{HashMap, HashSet},
+    ops::Deref,
+};
+use telegrambot::teloxide::{
+    payloads::{EditMessageCaptionSetters, EditMessageTextSetters},
+    prelude::*,
+    types::{MessageId, ParseMode},
+    ApiError, RequestError,
+};
 
 pub fn routes() -> Vec<Route> {
     routes![
@@ -17,15 +41,37 @@ pub fn routes() -> Vec<Route> {
         patch_feed,
         get_scheduled_feed,
         create_scheduled_post,
-        delete_scheduled_post
+        create_scheduled_thread,
+        delete_scheduled_post,
+        retry_scheduled_post,
+        get_published_feed,
+        edit_published_post,
+        delete_published_post
     ]
 }
 
 #[derive(Serialize)]
-struct FeedElement<'a> {
+pub(crate) struct FeedElement<'a> {
     post: entity::post::Model,
     media: Vec<entity::post_media::Model>,
     author: &'a entity::author::Model,
+    /// Position of post for `before` and `after`
+    cursor: String,
+}
+
+impl<'a> FeedElement<'a> {
+    pub(crate) fn new(
+        post: entity::post::Model,
+        media: Vec<entity::post_media::Model>,
+        author: &'a entity::author::Model,
+    ) -> Self {
+        Self {
+            cursor: PostCursor::of(&post).to_string(),
+            post,
+            media,
+            author,
+        }
+    }
 }
 
 #[derive(Serialize)]
@@ -34,6 +80,11 @@ struct ScheduledFeedElement {
     media: Vec<entity::post_media::Model>,
 }
 
+#[derive(Deserialize)]
+struct EditPublishedPost {
+    post_text: String,
+}
+
 #[derive(Deserialize)]
 #[serde(rename_all = "camelCase")]
 enum FeedUpdateData<'a> {
@@ -48,10 +99,168 @@ struct CreateScheduledPost {
     post_text: Option<String>,
     #[serde(default)]
     exclude_media: Vec<i64>,
+    /// Media ids in order of posting. Media, that is not listed, goes after listed one
+    /// in original order
+    #[serde(default)]
+    media_order: Vec<i64>,
+    /// Unix time when post should be published. If `None`, post takes next free slot
+    /// of channel posting schedule or published as soon as possible
+    publish_at: Option<i64>,
+}
+
+#[derive(Deserialize)]
+struct CreateScheduledThread {
+    /// Internal ID of any post of thread
+    post_id: i64,
+    /// Unix time when thread should be published, see [`CreateScheduledPost::publish_at`]
+    publish_at: Option<i64>,
+}
+
+/// Posts of self-thread, that contains `post`, oldest first
+async fn thread_posts(
+    db: &DatabaseConnection,
+    post: entity::post::Model,
+) -> Vec<entity::post::Model> {
+    let mut root = post;
+    while let Some(parent_id) = root.parent_post_id {
+        match entity::post::Entity::find_by_id(parent_id)
+            .one(db)
+            .await
+            .unwrap()
+        {
+            Some(parent) => root = parent,
+            None => break,
+        }
+    }
+
+    let mut posts = vec![root];
+    loop {
+        let last = posts.last().expect("thread has root").id;
+        let next = entity::post::Entity::find()
+            .filter(entity::post::Column::ParentPostId.eq(last))
+            .order_by_asc(entity::post::Column::PlatformId)
+            .one(db)
+            .await
+            .unwrap();
+
+        match next {
+            Some(next) => posts.push(next),
+            None => break,
+        }
+    }
+
+    posts
+}
+
+/// Orders `media_ids` by `order`, keeping original order of not listed media. Returns
+/// id from `order`, that is not in `media_ids` or listed twice, as error
+fn order_media(mut media_ids: Vec<i64>, order: &[i64]) -> Result<Vec<i64>, i64> {
+    let mut ordered = Vec::with_capacity(media_ids.len());
+    for &id in order {
+        let Some(i) = media_ids.iter().position(|&f| f == id) else {
+            return Err(id);
+        };
+        ordered.push(media_ids.remove(i));
+    }
+    ordered.append(&mut media_ids);
+
+    Ok(ordered)
 }
 
-#[get("/")]
-async fn get_feed(db: &State<DatabaseConnection>, telegram_user: TelegramUser) -> APIResponse {
+/// Attaches media to scheduled post, in order of `media_ids`
+async fn insert_scheduled_media(
+    db: &DatabaseConnection,
+    scheduled_post_id: i64,
+    media_ids: &[i64],
+) {
+    if media_ids.is_empty() {
+        return;
+    }
+
+    let media = media_ids.iter().enumerate().map(|(i, &media_id)| {
+        entity::scheduled_post_media::ActiveModel {
+            scheduled_post_id: Set(scheduled_post_id),
+            media_id: Set(media_id),
+            position: Set(i as i32),
+        }
+    });
+
+    entity::scheduled_post_media::Entity::insert_many(media)
+        .exec(db)
+        .await
+        .unwrap();
+}
+
+/// Checks that post text with source fits in caption (if post has media) or message.
+/// Returns description of exceeded limit as error
+fn check_length(text: &str, source: &str, has_media: bool) -> Result<(), String> {
+    let (max_length, kind) = if has_media {
+        (MAX_CAPTION_LENGTH, "Caption")
+    } else {
+        (MAX_TEXT_LENGTH, "Message")
+    };
+
+    let length = post_length(text, source);
+    if length > max_length {
+        return Err(format!(
+            "{kind} with source is {length} characters long, maximum is {max_length}"
+        ));
+    }
+
+    Ok(())
+}
+
+/// Returns the first slot in posting schedule of user channel after now, that is not
+/// taken by pending post, or `None` if channel has no schedule
+async fn next_free_slot(db: &DatabaseConnection, user_id: i64) -> Option<i64> {
+    let channel = entity::telegram_user::Entity::find_by_id(user_id)
+        .one(db)
+        .await
+        .unwrap()?
+        .channel?;
+
+    let schedule = entity::posting_schedule::Entity::find_by_id(channel)
+        .one(db)
+        .await
+        .unwrap()?;
+
+    let now = unix_now();
+    let taken = entity::scheduled_post::Entity::find()
+        .inner_join(entity::telegram_user::Entity)
+        .filter(entity::telegram_user::Column::Channel.eq(channel))
+        .filter(entity::scheduled_post::Column::Status.eq(PostStatus::Pending))
+        .filter(entity::scheduled_post::Column::PublishAt.gte(now))
+        .all(db)
+        .await
+        .unwrap()
+        .into_iter()
+        .filter_map(|f| f.publish_at)
+        .collect::<HashSet<_>>();
+
+    Some(first_free_slot(&schedule, now, &taken))
+}
+
+/// Returns the first slot of `schedule` at or after `after`, that is not in `taken`
+fn first_free_slot(
+    schedule: &entity::posting_schedule::Model,
+    after: i64,
+    taken: &HashSet<i64>,
+) -> i64 {
+    let mut slot = schedule.next_slot(after);
+    while taken.contains(&slot) {
+        slot = schedule.next_slot(slot + 1);
+    }
+
+    slot
+}
+
+#[get("/?<authors>")]
+async fn get_feed(
+    db: &State<DatabaseConnection>,
+    telegram_user: TelegramUser,
+    authors: Vec<i64>,
+    filter: PostFilter,
+) -> APIResponse {
     let user = entity::telegram_user::Entity::find_by_id(telegram_user.id)
         .one(db.deref())
         .await
@@ -63,40 +272,62 @@ async fn get_feed(db: &State<DatabaseConnection>, telegram_user: TelegramUser) -
         return APIResponse::new::<Vec<()>>(vec![]);
     };
 
-    let follows = entity::follow::Entity::find()
-        .filter(entity::follow::Column::UserId.eq(user.id))
-        .find_also_related(entity::author::Entity)
-        .all(db.deref())
-        .await
-        .unwrap();
-
-    let mut new_posts = vec![];
+    let follows = Query::select()
+        .column(entity::follow::Column::AuthorId)
+        .from(entity::follow::Entity)
+        .and_where(entity::follow::Column::UserId.eq(user.id))
+        .to_owned();
+    let mut posts =
+        entity::post::Entity::find().filter(entity::post::Column::AuthorId.in_subquery(follows));
+    if !authors.is_empty() {
+        posts = posts.filter(entity::post::Column::AuthorId.is_in(authors));
+    }
 
-    for author in follows.iter().flat_map(|v| &v.1) {
-        let posts = entity::post::Entity::find()
-            .filter(entity::post::Column::AuthorId.eq(author.id))
-            .filter(entity::post::Column::PlatformId.gt(user.last_feed_id))
-            .find_with_related(entity::post_media::Entity)
-            .limit(50)
-            .all(db.deref())
-            .await
-            .unwrap();
+    // unread posts, unless user pages through feed
+    let filter = match (filter.after, filter.before) {
+        (None, None) => {
+            let last_read = entity::post::Entity::find_by_id(user.last_feed_id)
+                .one(db.deref())
+                .await
+                .unwrap();
 
-        for (post, media) in posts
-            .into_iter()
-            .filter(|f| f.0.platform_id > user.last_feed_id)
-        {
-            new_posts.push(FeedElement {
-                post,
-                media,
-                author,
-            })
+            PostFilter {
+                after: Some(last_read.as_ref().map_or(
+                    PostCursor {
+                        posted_at: 0,
+                        id: 0,
+                    },
+                    PostCursor::of,
+                )),
+                ..filter
+            }
         }
-    }
+        _ => filter,
+    };
+    let posts = match find_posts(db.deref(), posts, &filter).await {
+        Ok(r) => r,
+        Err(e) => return APIResponse::error(422, e),
+    };
+
+    let authors = entity::author::Entity::find()
+        .filter(entity::author::Column::Id.is_in(posts.iter().map(|f| f.0.author_id)))
+        .all(db.deref())
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|f| (f.id, f))
+        .collect::<HashMap<_, _>>();
 
-    new_posts.sort_by_key(|f| f.post.platform_id);
+    let feed = posts
+        .into_iter()
+        .filter_map(|(post, media)| {
+            let author = authors.get(&post.author_id)?;
 
-    APIResponse::new(new_posts)
+            Some(FeedElement::new(post, media, author))
+        })
+        .collect::<Vec<_>>();
+
+    APIResponse::new(feed)
 }
 
 #[get("/<id>")]
@@ -113,6 +344,7 @@ async fn get_feed_post(
 
     let res = expr
         .find_with_related(entity::post_media::Entity)
+        .order_by_asc(entity::post_media_link::Column::Position)
         .all(db.deref())
         .await
         .unwrap();
@@ -131,11 +363,7 @@ async fn get_feed_post(
         return APIResponse::error(404, "Post does not exists");
     };
 
-    APIResponse::new(FeedElement {
-        post,
-        media,
-        author: &author,
-    })
+    APIResponse::new(FeedElement::new(post, media, &author))
 }
 
 #[patch("/", data = "<data>")]
@@ -167,20 +395,26 @@ async fn patch_feed(
         _ => unreachable!(),
     };
 
-    let author_stmt = match author_id.parse::<i64>() {
-        Ok(id) if id > 0 => {
-            entity::author::Entity::find().filter(entity::author::Column::PlatformId.eq(id))
-        }
-        Ok(id) if id < 0 => entity::author::Entity::find_by_id(-id),
-        _ => entity::author::Entity::find().filter(entity::author::Column::Username.eq(author_id)),
+    let author = match author_id.parse::<i64>() {
+        Ok(id) if id > 0 => entity::author::Entity::find()
+            .filter(entity::author::Column::PlatformId.eq(id))
+            .one(db.deref())
+            .await
+            .unwrap(),
+        Ok(id) if id < 0 => entity::author::Entity::find_by_id(-id)
+            .one(db.deref())
+            .await
+            .unwrap(),
+        _ => find_by_username(db.deref(), author_id).await,
     };
 
-    let author = match author_stmt.one(db.deref()).await {
-        Ok(Some(a)) => a,
-        Ok(None) => return APIResponse::error(404, "Author does not exists"),
-        Err(e) => panic!("{e}"),
+    let Some(author) = author else {
+        return APIResponse::error(404, "Author does not exists");
     };
 
+    // follows reference user
+    get_or_create_user(db.deref(), telegram_user.id).await;
+
     let active = entity::follow::ActiveModel {
         user_id: Set(telegram_user.id),
         author_id: Set(author.id),
@@ -205,43 +439,28 @@ async fn patch_feed(
     }
 }
 
-#[get("/scheduled")]
+#[get("/scheduled?<sent>")]
 async fn get_scheduled_feed(
+    sent: Option<bool>,
     db: &State<DatabaseConnection>,
     telegram_user: TelegramUser,
 ) -> APIResponse {
-    let raw_posts = entity::scheduled_post::Entity::find()
-        .filter(entity::scheduled_post::Column::UserId.eq(telegram_user.id))
-        .all(db.deref())
-        .await
-        .unwrap();
+    let mut expr = entity::scheduled_post::Entity::find()
+        .filter(entity::scheduled_post::Column::UserId.eq(telegram_user.id));
+
+    if !sent.unwrap_or(false) {
+        expr = expr.filter(entity::scheduled_post::Column::Status.ne(PostStatus::Sent));
+    }
+
+    let raw_posts = expr.all(db.deref()).await.unwrap();
 
     let mut posts = vec![];
 
     for post in raw_posts {
-        let media_ids = post
-            .media_ids
-            .split(',')
-            .flat_map(|f| f.parse::<i64>())
-            .collect::<Vec<_>>();
-
         // NOTE: this code performs too many requests to db. May use
         // twitter-way (posts: Vec<_>, media: Vec<_>) or combine it later?
         // TODO:
-        let media = if media_ids.is_empty() {
-            vec![]
-        } else {
-            let mut conditions = Condition::any();
-            for media in media_ids {
-                conditions = conditions.add(entity::post_media::Column::Id.eq(media));
-            }
-
-            entity::post_media::Entity::find()
-                .filter(conditions)
-                .all(db.deref())
-                .await
-                .unwrap()
-        };
+        let media = post.find_media().all(db.deref()).await.unwrap();
 
         posts.push(ScheduledFeedElement { post, media });
     }
@@ -258,12 +477,19 @@ async fn create_scheduled_post(
     if data.0.exclude_media.len() > 8 {
         return APIResponse::error(422, "Excluded media too long");
     }
+    if data.0.media_order.len() > MAX_MEDIA_GROUP_SIZE {
+        return APIResponse::error(422, "Media order too long");
+    }
+    if matches!(data.0.publish_at, Some(t) if t < 0) {
+        return APIResponse::error(422, "publish_at must be non-negative unix time");
+    }
 
     let post = entity::post::Entity::find_by_id(data.post_id)
         .one(db.deref())
         .await
         .unwrap();
     let post = match post {
+        Some(p) if p.deleted => return APIResponse::error(410, "Post was deleted on platform"),
         Some(p) => p,
         _ => return APIResponse::error(404, "Post does not exists"),
     };
@@ -276,30 +502,140 @@ async fn create_scheduled_post(
 
         post.find_related(entity::post_media::Entity)
             .filter(cond)
+            .order_by_asc(entity::post_media_link::Column::Position)
             .all(db.deref())
             .await
             .unwrap()
             .into_iter()
             .map(|f| f.id)
-            .join(",")
+            .collect()
+    };
+    let media_ids = match order_media(media_ids, &data.0.media_order) {
+        Ok(r) => r,
+        Err(id) => {
+            return APIResponse::error(
+                422,
+                format!("Media {id} is not media of post, excluded or repeated in media order"),
+            )
+        }
     };
 
-    let post_text = data.0.post_text.unwrap_or(post.text);
+    // entities are valid only for original text
+    let (post_text, post_entities) = match data.0.post_text {
+        Some(text) => (text, PostEntities::default()),
+        None => (post.text, post.entities),
+    };
+
+    // posting worker can split longer text, but user should see it before posting
+    if let Err(e) = check_length(&post_text, &post.source_text, !media_ids.is_empty()) {
+        return APIResponse::error(422, e);
+    }
+
+    // scheduled posts reference user
+    get_or_create_user(db.deref(), telegram_user.id).await;
+
+    let publish_at = match data.0.publish_at {
+        Some(t) => Some(t),
+        None => next_free_slot(db.deref(), telegram_user.id).await,
+    };
 
     let active = entity::scheduled_post::ActiveModel {
         user_id: Set(telegram_user.id),
-        media_ids: Set(media_ids),
+        post_id: Set(Some(post.id)),
         post_text: Set(post_text),
+        post_entities: Set(post_entities),
         post_source: Set(post.source_text),
         post_source_url: Set(post.source_url),
+        publish_at: Set(publish_at),
+        duplicate: Set(is_duplicate(db.deref(), telegram_user.id, &media_ids).await),
         ..Default::default()
     };
 
     let model = active.insert(db.deref()).await.unwrap();
+    insert_scheduled_media(db.deref(), model.id, &media_ids).await;
 
     APIResponse::new(model)
 }
 
+#[put("/scheduled/thread", data = "<data>")]
+async fn create_scheduled_thread(
+    db: &State<DatabaseConnection>,
+    telegram_user: TelegramUser,
+    data: Json<CreateScheduledThread>,
+) -> APIResponse {
+    if matches!(data.0.publish_at, Some(t) if t < 0) {
+        return APIResponse::error(422, "publish_at must be non-negative unix time");
+    }
+
+    let post = entity::post::Entity::find_by_id(data.post_id)
+        .one(db.deref())
+        .await
+        .unwrap();
+    let Some(post) = post else {
+        return APIResponse::error(404, "Post does not exists");
+    };
+
+    let posts = thread_posts(db.deref(), post).await;
+
+    let mut thread = Vec::with_capacity(posts.len());
+    for post in posts {
+        if post.deleted {
+            return APIResponse::error(
+                410,
+                format!("Post {} of thread was deleted on platform", post.id),
+            );
+        }
+
+        let media_ids = post
+            .find_related(entity::post_media::Entity)
+            .order_by_asc(entity::post_media_link::Column::Position)
+            .all(db.deref())
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|f| f.id)
+            .collect::<Vec<_>>();
+
+        if let Err(e) = check_length(&post.text, &post.source_text, !media_ids.is_empty()) {
+            return APIResponse::error(422, format!("Post {} of thread: {e}", post.id));
+        }
+
+        thread.push((post, media_ids));
+    }
+
+    get_or_create_user(db.deref(), telegram_user.id).await;
+
+    // whole thread takes one slot, next posts are sent right after previous ones
+    let publish_at = match data.0.publish_at {
+        Some(t) => Some(t),
+        None => next_free_slot(db.deref(), telegram_user.id).await,
+    };
+
+    let mut models = vec![];
+    let mut reply_to_id = None;
+    for (post, media_ids) in thread {
+        let active = entity::scheduled_post::ActiveModel {
+            user_id: Set(telegram_user.id),
+            post_id: Set(Some(post.id)),
+            reply_to_id: Set(reply_to_id),
+            post_text: Set(post.text),
+            post_entities: Set(post.entities),
+            post_source: Set(post.source_text),
+            post_source_url: Set(post.source_url),
+            publish_at: Set(publish_at),
+            duplicate: Set(is_duplicate(db.deref(), telegram_user.id, &media_ids).await),
+            ..Default::default()
+        };
+
+        let model = active.insert(db.deref()).await.unwrap();
+        insert_scheduled_media(db.deref(), model.id, &media_ids).await;
+        reply_to_id = Some(model.id);
+        models.push(model);
+    }
+
+    APIResponse::new(models)
+}
+
 #[delete("/scheduled/<id>")]
 async fn delete_scheduled_post(
     db: &State<DatabaseConnection>,
@@ -320,3 +656,385 @@ async fn delete_scheduled_post(
         APIResponse::error(404, "Post does not exists")
     }
 }
+
+#[post("/scheduled/<id>/retry")]
+async fn retry_scheduled_post(
+    db: &State<DatabaseConnection>,
+    telegram_user: TelegramUser,
+    id: u64,
+) -> APIResponse {
+    let post = entity::scheduled_post::Entity::find_by_id(id as i64)
+        .filter(entity::scheduled_post::Column::UserId.eq(telegram_user.id))
+        .one(db.deref())
+        .await
+        .unwrap();
+
+    let post = match post {
+        Some(p) if p.status == PostStatus::Sent => {
+            return APIResponse::error(409, "Post already sent")
+        }
+        Some(p) => p,
+        None => return APIResponse::error(404, "Post does not exists"),
+    };
+
+    let status = post.status.clone();
+    let mut active: entity::scheduled_post::ActiveModel = post.into();
+    active.status = Set(PostStatus::Pending);
+    active.attempts = Set(0);
+    active.last_error = Set(None);
+    active.next_attempt_at = Set(None);
+
+    let model = active.update(db.deref()).await.unwrap();
+
+    // next posts of thread were stopped with this one
+    if status != PostStatus::Pending {
+        resume_thread(db.deref(), model.id, status).await;
+    }
+
+    APIResponse::new(model)
+}
+
+#[get("/published")]
+async fn get_published_feed(
+    db: &State<DatabaseConnection>,
+    telegram_user: TelegramUser,
+) -> APIResponse {
+    let posts = entity::published_post::Entity::find()
+        .filter(entity::published_post::Column::UserId.eq(telegram_user.id))
+        .order_by_desc(entity::published_post::Column::PublishedAt)
+        .all(db.deref())
+        .await
+        .unwrap();
+
+    APIResponse::new(posts)
+}
+
+#[patch("/published/<id>", data = "<data>")]
+async fn edit_published_post(
+    db: &State<DatabaseConnection>,
+    bot: &State<Bot>,
+    telegram_user: TelegramUser,
+    id: u64,
+    data: Json<EditPublishedPost>,
+) -> APIResponse {
+    let post = entity::published_post::Entity::find_by_id(id as i64)
+        .filter(entity::published_post::Column::UserId.eq(telegram_user.id))
+        .one(db.deref())
+        .await
+        .unwrap();
+    let Some(post) = post else {
+        return APIResponse::error(404, "Post does not exists");
+    };
+
+    if let Err(e) = check_length(&data.post_text, &post.post_source, post.text_in_caption) {
+        return APIResponse::error(422, e);
+    }
+
+    let text = format_post(
+        &data.post_text,
+        &[],
+        &post.post_source,
+        &post.post_source_url,
+    );
+    let chat_id = ChatId(post.chat_id);
+    let message_id = MessageId(post.text_message_id);
+
+    let res = if post.text_in_caption {
+        bot.edit_message_caption(chat_id, message_id)
+            .caption(text)
+            .parse_mode(ParseMode::Html)
+            .await
+    } else {
+        bot.edit_message_text(chat_id, message_id, text)
+            .parse_mode(ParseMode::Html)
+            .await
+    };
+
+    if let Err(e) = res {
+        return APIResponse::error(502, format!("Unable to edit message: {e}"));
+    }
+
+    let mut active: entity::published_post::ActiveModel = post.into();
+    active.post_text = Set(data.0.post_text);
+
+    let model = active.update(db.deref()).await.unwrap();
+
+    APIResponse::new(model)
+}
+
+#[delete("/published/<id>")]
+async fn delete_published_post(
+    db: &State<DatabaseConnection>,
+    bot: &State<Bot>,
+    telegram_user: TelegramUser,
+    id: u64,
+) -> APIResponse {
+    let post = entity::published_post::Entity::find_by_id(id as i64)
+        .filter(entity::published_post::Column::UserId.eq(telegram_user.id))
+        .one(db.deref())
+        .await
+        .unwrap();
+    let Some(post) = post else {
+        return APIResponse::error(404, "Post does not exists");
+    };
+
+    for message_id in post.message_ids() {
+        let res = bot
+            .delete_message(ChatId(post.chat_id), MessageId(message_id))
+            .await;
+
+        match res {
+            // already deleted by channel admins
+            Ok(_) | Err(RequestError::Api(ApiError::MessageToDeleteNotFound)) => {}
+            Err(e) => return APIResponse::error(502, format!("Unable to delete message: {e}")),
+        }
+    }
+
+    post.delete(db.deref()).await.unwrap();
+
+    APIResponse::NoContent
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use migration::{Migrator, MigratorTrait};
+    use mockserver::{fixture, MockRoute, MockServer};
+    use rocket::serde::json::Json;
+    use sea_orm::{ActiveModelTrait, Database, DatabaseConnection, EntityTrait, Set};
+
+    use super::{
+        check_length, delete_published_post, edit_published_post, first_free_slot, order_media,
+        EditPublishedPost,
+    };
+    use crate::models::{response::APIResponse, telegramauth::TelegramUser};
+
+    #[test]
+    /// Check [`order_media`] puts listed media first and rejects unknown or repeated ids
+    fn test_order_media() {
+        assert_eq!(order_media(vec![1, 2, 3], &[]), Ok(vec![1, 2, 3]));
+        assert_eq!(order_media(vec![1, 2, 3], &[3, 1]), Ok(vec![3, 1, 2]));
+        assert_eq!(order_media(vec![1, 2, 3], &[3, 2, 1]), Ok(vec![3, 2, 1]));
+        assert_eq!(order_media(vec![1, 2, 3], &[4]), Err(4));
+        assert_eq!(order_media(vec![1, 2, 3], &[2, 2]), Err(2));
+    }
+
+    #[test]
+    /// Check [`check_length`] applies caption limit to posts with media and names it
+    fn test_check_length() {
+        // with "\n\n" and source
+        let text = "x".repeat(1100 - 6);
+
+        assert_eq!(check_length(&text, "jack", false), Ok(()));
+        assert_eq!(
+            check_length(&text, "jack", true),
+            Err("Caption with source is 1100 characters long, maximum is 1024".to_string())
+        );
+        assert_eq!(
+            check_length(&"x".repeat(5000), "jack", false),
+            Err("Message with source is 5006 characters long, maximum is 4096".to_string())
+        );
+        // telegram counts UTF-16 code units
+        assert!(check_length(&"🌧".repeat(512), "", true).is_err());
+        assert!(check_length(&"🌧".repeat(511), "", true).is_ok());
+    }
+
+    #[test]
+    /// Check [`first_free_slot`] skips taken slots, but not free slots before them
+    fn test_first_free_slot() {
+        // every hour between 09:00 and 12:00 UTC
+        let schedule = entity::posting_schedule::Model {
+            channel: -100,
+            interval: 60,
+            day_start: 9 * 60,
+            day_end: 12 * 60,
+            utc_offset: 0,
+        };
+        const H: i64 = 60 * 60;
+
+        assert_eq!(first_free_slot(&schedule, 0, &HashSet::new()), 9 * H);
+        // post at far-future time does not move slots before it
+        let taken = HashSet::from([9 * H, 30 * 24 * H]);
+        assert_eq!(first_free_slot(&schedule, 0, &taken), 10 * H);
+        let taken = HashSet::from([9 * H, 10 * H, 11 * H, 12 * H]);
+        assert_eq!(first_free_slot(&schedule, 0, &taken), 33 * H);
+        // slot of post with explicit time between slots is free
+        let taken = HashSet::from([9 * H + 1]);
+        assert_eq!(first_free_slot(&schedule, 0, &taken), 9 * H);
+    }
+
+    fn telegram_user(id: i64) -> TelegramUser {
+        TelegramUser {
+            id,
+            first_name: "jack".to_string(),
+            last_name: None,
+            username: None,
+            language_code: None,
+            is_premium: None,
+            photo_url: None,
+        }
+    }
+
+    fn status(res: &APIResponse) -> u16 {
+        match res {
+            APIResponse::Ok { .. } => 200,
+            APIResponse::NoContent => 204,
+            APIResponse::Error { error_code, .. } => *error_code,
+        }
+    }
+
+    /// Database with user 1, that published post 1 (as caption of messages 20 and 21)
+    /// and post 2 (as text message 10) to channel -1001
+    async fn published_database() -> DatabaseConnection {
+        let db = Database::connect("sqlite::memory:").await.unwrap();
+        Migrator::up(&db, None).await.unwrap();
+
+        entity::telegram_user::ActiveModel {
+            id: Set(1),
+            channel: Set(Some(-1001)),
+            ..Default::default()
+        }
+        .insert(&db)
+        .await
+        .unwrap();
+
+        for (id, message_ids, text_in_caption) in [(1, "20,21", true), (2, "10", false)] {
+            entity::published_post::ActiveModel {
+                id: Set(id),
+                user_id: Set(1),
+                chat_id: Set(-1001),
+                message_ids: Set(message_ids.to_string()),
+                text_message_id: Set(message_ids[..2].parse().unwrap()),
+                text_in_caption: Set(text_in_caption),
+                post_text: Set("hello".to_string()),
+                post_source: Set("jack".to_string()),
+                post_source_url: Set("https://twitter.com/jack/status/1".to_string()),
+                published_at: Set(0),
+                ..Default::default()
+            }
+            .insert(&db)
+            .await
+            .unwrap();
+        }
+
+        db
+    }
+
+    #[rocket::async_test]
+    /// Check [`edit_published_post`] edits caption or text message and checks its limit
+    async fn test_edit_published_post() {
+        let db = published_database().await;
+        let server = MockServer::start(vec![
+            MockRoute::new(
+                "/bottoken/EditMessageCaption",
+                200,
+                fixture("telegram/message.json"),
+            ),
+            MockRoute::new(
+                "/bottoken/EditMessageText",
+                200,
+                fixture("telegram/message.json"),
+            ),
+        ])
+        .await;
+        let bot = server.bot();
+
+        let edit = |id: u64, text: String| {
+            edit_published_post(
+                (&db).into(),
+                (&bot).into(),
+                telegram_user(1),
+                id,
+                Json(EditPublishedPost { post_text: text }),
+            )
+        };
+
+        assert_eq!(status(&edit(1, "a < b".to_string()).await), 200);
+        let bodies = server.bodies("/bottoken/EditMessageCaption");
+        assert_eq!(bodies.len(), 1);
+        assert!(bodies[0].contains(r#""message_id":20"#));
+        assert!(bodies[0].contains(r#""caption":"a &lt; b\n\n<b><a href="#));
+
+        assert_eq!(status(&edit(2, "x".repeat(2000)).await), 200);
+        let bodies = server.bodies("/bottoken/EditMessageText");
+        assert_eq!(bodies.len(), 1);
+        assert!(bodies[0].contains(r#""message_id":10"#));
+
+        // caption limit is shorter than message one
+        assert_eq!(status(&edit(1, "x".repeat(2000)).await), 422);
+        assert_eq!(server.requests().len(), 2);
+
+        let posts = entity::published_post::Entity::find()
+            .all(&db)
+            .await
+            .unwrap();
+        assert_eq!(posts[0].post_text, "a < b");
+        assert_eq!(posts[1].post_text, "x".repeat(2000));
+
+        // post of another user
+        let res = edit_published_post(
+            (&db).into(),
+            (&bot).into(),
+            telegram_user(2),
+            1,
+            Json(EditPublishedPost {
+                post_text: "mine".to_string(),
+            }),
+        )
+        .await;
+        assert_eq!(status(&res), 404);
+    }
+
+    #[rocket::async_test]
+    /// Check [`delete_published_post`] deletes all messages of post, even if some of them
+    /// are already deleted, and keeps post on other errors
+    async fn test_delete_published_post() {
+        let db = published_database().await;
+        let server = MockServer::start(vec![MockRoute::new(
+            "/bottoken/DeleteMessage",
+            200,
+            fixture("telegram/true.json"),
+        )])
+        .await;
+        let deleted = MockServer::start(vec![MockRoute::new(
+            "/bottoken/DeleteMessage",
+            400,
+            fixture("telegram/message_to_delete_not_found.json"),
+        )])
+        .await;
+        let failing = MockServer::start(vec![MockRoute::new(
+            "/bottoken/DeleteMessage",
+            400,
+            fixture("telegram/chat_not_found.json"),
+        )])
+        .await;
+
+        let res =
+            delete_published_post((&db).into(), (&failing.bot()).into(), telegram_user(1), 1).await;
+        assert_eq!(status(&res), 502);
+        assert_eq!(failing.requests().len(), 1);
+
+        let res =
+            delete_published_post((&db).into(), (&server.bot()).into(), telegram_user(1), 1).await;
+        assert_eq!(status(&res), 204);
+        let bodies = server.bodies("/bottoken/DeleteMessage");
+        assert_eq!(bodies.len(), 2);
+        assert!(bodies[0].contains(r#""message_id":20"#));
+        assert!(bodies[1].contains(r#""message_id":21"#));
+
+        let res =
+            delete_published_post((&db).into(), (&deleted.bot()).into(), telegram_user(1), 2).await;
+        assert_eq!(status(&res), 204);
+
+        let posts = entity::published_post::Entity::find()
+            .all(&db)
+            .await
+            .unwrap();
+        assert!(posts.is_empty());
+
+        let res =
+            delete_published_post((&db).into(), (&server.bot()).into(), telegram_user(1), 2).await;
+        assert_eq!(status(&res), 404);
+    }
+}
@@ -1,4 +1,5 @@
 pub mod author;
 pub mod catchers;
 pub mod feed;
+pub mod search;
 pub mod user;
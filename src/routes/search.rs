@@ -0,0 +1,329 @@
+use std::{collections::HashMap, ops::Deref};
+
+use rocket::{
+    get,
+    http::Status,
+    request::{FromRequest, Outcome},
+    routes, Request, Route, State,
+};
+use sea_orm::{
+    ColumnTrait, DatabaseConnection, DbBackend, EntityTrait, FromQueryResult, QueryFilter,
+    Statement, Value,
+};
+use serde::Serialize;
+
+use crate::{
+    models::{
+        postfilter::{query_value, with_media, DEFAULT_PAGE_SIZE, MAX_PAGE_SIZE},
+        postformat::escape_html,
+        response::APIResponse,
+        telegramauth::TelegramUser,
+    },
+    routes::feed::FeedElement,
+};
+
+/// Marks around matched words in snippets from sqlite, replaced by tags after escaping
+const MATCH_START: &str = "\u{2}";
+const MATCH_END: &str = "\u{3}";
+/// Maximum number of words in snippet
+const SNIPPET_WORDS: i32 = 16;
+
+pub fn routes() -> Vec<Route> {
+    routes![search]
+}
+
+/// Search query and filters, passed in query
+struct SearchQuery {
+    q: String,
+    /// Internal ids of authors
+    authors: Vec<i64>,
+    since: Option<i64>,
+    until: Option<i64>,
+    limit: Option<u64>,
+    offset: Option<u64>,
+}
+
+#[derive(Serialize)]
+struct SearchElement<'a> {
+    #[serde(flatten)]
+    element: FeedElement<'a>,
+    /// Escaped fragment of post text with matched words in `<b>` tags
+    snippet: String,
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for SearchQuery {
+    type Error = String;
+
+    async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let query = (|| {
+            Ok(SearchQuery {
+                q: query_value(req, "q")?.unwrap_or_default(),
+                authors: query_value(req, "authors")?.unwrap_or_default(),
+                since: query_value(req, "since")?,
+                until: query_value(req, "until")?,
+                limit: query_value(req, "limit")?,
+                offset: query_value(req, "offset")?,
+            })
+        })();
+
+        match query {
+            Ok(f) => Outcome::Success(f),
+            Err(e) => Outcome::Failure((Status::UnprocessableEntity, e)),
+        }
+    }
+}
+
+#[derive(FromQueryResult)]
+struct SearchHit {
+    id: i64,
+    snippet: String,
+}
+
+#[get("/")]
+async fn search(
+    db: &State<DatabaseConnection>,
+    _telegram_user: TelegramUser,
+    query: SearchQuery,
+) -> APIResponse {
+    let Some(terms) = match_query(&query.q) else {
+        return APIResponse::error(422, "Search query is empty");
+    };
+    let limit = query.limit.unwrap_or(DEFAULT_PAGE_SIZE);
+    if limit == 0 || limit > MAX_PAGE_SIZE {
+        return APIResponse::error(422, "limit must be from 1 to 100");
+    }
+
+    let hits = find_hits(db.deref(), &terms, &query, limit).await;
+
+    let mut posts = entity::post::Entity::find()
+        .filter(entity::post::Column::Id.is_in(hits.iter().map(|f| f.id)))
+        .all(db.deref())
+        .await
+        .unwrap();
+    // most relevant first
+    posts.sort_by_key(|post| hits.iter().position(|f| f.id == post.id));
+    let posts = with_media(db.deref(), posts).await;
+
+    let authors = entity::author::Entity::find()
+        .filter(entity::author::Column::Id.is_in(posts.iter().map(|f| f.0.author_id)))
+        .all(db.deref())
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|f| (f.id, f))
+        .collect::<HashMap<_, _>>();
+
+    let mut snippets = hits
+        .into_iter()
+        .map(|f| (f.id, f.snippet))
+        .collect::<HashMap<_, _>>();
+    let results = posts
+        .into_iter()
+        .filter_map(|(post, media)| {
+            let author = authors.get(&post.author_id)?;
+            let snippet = snippets.remove(&post.id)?;
+
+            Some(SearchElement {
+                element: FeedElement::new(post, media, author),
+                snippet: highlight(&snippet),
+            })
+        })
+        .collect::<Vec<_>>();
+
+    APIResponse::new(results)
+}
+
+/// Converts search query of user to FTS5 query, that matches posts with all words of it.
+/// Words are quoted, so FTS5 syntax in query is searched as text. Returns `None` if
+/// query has no words
+fn match_query(query: &str) -> Option<String> {
+    let words = query
+        .split_whitespace()
+        .map(|f| format!("\"{}\"", f.replace('"', "\"\"")))
+        .collect::<Vec<_>>();
+
+    (!words.is_empty()).then(|| words.join(" "))
+}
+
+/// Escapes snippet and wraps matched words in `<b>` tags
+fn highlight(snippet: &str) -> String {
+    escape_html(snippet)
+        .replace(MATCH_START, "<b>")
+        .replace(MATCH_END, "</b>")
+}
+
+/// Returns ids of posts matching FTS5 query `terms` and filters of `query` with
+/// snippets, most relevant first
+async fn find_hits(
+    db: &DatabaseConnection,
+    terms: &str,
+    query: &SearchQuery,
+    limit: u64,
+) -> Vec<SearchHit> {
+    let mut sql = format!(
+        "SELECT posts.id AS id, \
+         snippet(posts_search, 0, ?, ?, '…', {SNIPPET_WORDS}) AS snippet \
+         FROM posts_search JOIN posts ON posts.id = posts_search.rowid \
+         WHERE posts_search MATCH ? AND posts.quoted_only = 0"
+    );
+    let mut values: Vec<Value> = vec![MATCH_START.into(), MATCH_END.into(), terms.into()];
+
+    if !query.authors.is_empty() {
+        let params = vec!["?"; query.authors.len()].join(", ");
+        sql.push_str(&format!(" AND posts.author_id IN ({params})"));
+        values.extend(query.authors.iter().map(|&f| Value::from(f)));
+    }
+    if let Some(since) = query.since {
+        sql.push_str(" AND posts.posted_at >= ?");
+        values.push(since.into());
+    }
+    if let Some(until) = query.until {
+        sql.push_str(" AND posts.posted_at < ?");
+        values.push(until.into());
+    }
+    sql.push_str(" ORDER BY rank LIMIT ? OFFSET ?");
+    values.push((limit as i64).into());
+    values.push((query.offset.unwrap_or(0) as i64).into());
+
+    SearchHit::find_by_statement(Statement::from_sql_and_values(
+        DbBackend::Sqlite,
+        &sql,
+        values,
+    ))
+    .all(db)
+    .await
+    .unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use migration::{Migrator, MigratorTrait};
+    use sea_orm::{ActiveModelTrait, Database, DatabaseConnection, Set};
+
+    use super::{find_hits, highlight, match_query, SearchQuery, MATCH_END, MATCH_START};
+
+    fn query(authors: Vec<i64>, since: Option<i64>) -> SearchQuery {
+        SearchQuery {
+            q: String::new(),
+            authors,
+            since,
+            until: None,
+            limit: None,
+            offset: None,
+        }
+    }
+
+    async fn ids(db: &DatabaseConnection, q: &str, query: &SearchQuery) -> Vec<i64> {
+        let terms = match_query(q).unwrap();
+
+        find_hits(db, &terms, query, 10)
+            .await
+            .into_iter()
+            .map(|f| f.id)
+            .collect()
+    }
+
+    #[test]
+    /// Check [`match_query`] quotes words and rejects empty queries
+    fn test_match_query() {
+        assert_eq!(
+            match_query("rust  lang").as_deref(),
+            Some("\"rust\" \"lang\"")
+        );
+        assert_eq!(
+            match_query("say \"hi\" OR").as_deref(),
+            Some("\"say\" \"\"\"hi\"\"\" \"OR\"")
+        );
+        assert_eq!(match_query(" \n"), None);
+    }
+
+    #[test]
+    /// Check [`highlight`] escapes snippet, but not its highlights
+    fn test_highlight() {
+        let snippet = format!("a <b> {MATCH_START}rust{MATCH_END} & c");
+
+        assert_eq!(highlight(&snippet), "a &lt;b&gt; <b>rust</b> &amp; c");
+    }
+
+    #[rocket::async_test]
+    /// Check [`find_hits`] finds posts by words, keeps index in sync with post texts and
+    /// applies filters
+    async fn test_find_hits() {
+        let db = Database::connect("sqlite::memory:").await.unwrap();
+        Migrator::up(&db, None).await.unwrap();
+
+        let mut authors = vec![];
+        for id in 1..=2 {
+            let author = entity::author::ActiveModel {
+                platform_id: Set(id),
+                name: Set(format!("author {id}")),
+                username: Set(format!("author{id}")),
+                ..Default::default()
+            }
+            .insert(&db)
+            .await
+            .unwrap();
+            authors.push(author.id);
+        }
+
+        let mut posts = vec![];
+        for (id, author_id, text) in [
+            (1, authors[0], "Rust 1.66 released"),
+            (2, authors[0], "Rust, rust and more rust"),
+            (3, authors[1], "Café with friends, no rust"),
+            (4, authors[1], "Nothing here"),
+        ] {
+            let post = entity::post::ActiveModel {
+                platform_id: Set(id),
+                external_id: Set(id.to_string()),
+                author_id: Set(author_id),
+                text: Set(text.to_string()),
+                source_url: Set(format!("https://twitter.com/a/status/{id}")),
+                source_text: Set("a".to_string()),
+                posted_at: Set(Some(100 * id)),
+                ..Default::default()
+            }
+            .insert(&db)
+            .await
+            .unwrap();
+            posts.push(post);
+        }
+        let id = |i: usize| posts[i].id;
+
+        // more frequent word and shorter text rank higher
+        assert_eq!(
+            ids(&db, "RUST", &query(vec![], None)).await,
+            [id(1), id(0), id(2)]
+        );
+        assert_eq!(ids(&db, "cafe", &query(vec![], None)).await, [id(2)]);
+        assert_eq!(
+            ids(&db, "rust released", &query(vec![], None)).await,
+            [id(0)]
+        );
+        assert_eq!(
+            ids(&db, "rust", &query(vec![authors[1]], None)).await,
+            [id(2)]
+        );
+        assert_eq!(
+            ids(&db, "rust", &query(vec![], Some(200))).await,
+            [id(1), id(2)]
+        );
+
+        let hits = find_hits(&db, "\"released\"", &query(vec![], None), 10).await;
+        assert_eq!(
+            hits[0].snippet,
+            format!("Rust 1.66 {MATCH_START}released{MATCH_END}")
+        );
+
+        // edited text replaces the old one
+        let mut active: entity::post::ActiveModel = posts[3].clone().into();
+        active.text = Set("Rust is here".to_string());
+        active.update(&db).await.unwrap();
+        assert_eq!(
+            ids(&db, "nothing", &query(vec![], None)).await,
+            Vec::<i64>::new()
+        );
+        assert_eq!(ids(&db, "here", &query(vec![], None)).await, [id(3)]);
+    }
+}
@@ -1,6 +1,7 @@
 use std::ops::Deref;
 
-use rocket::{delete, get, post, routes, serde::json::Json, Route, State};
+use migration::OnConflict;
+use rocket::{delete, get, post, put, routes, serde::json::Json, Route, State};
 use sea_orm::{
     ActiveModelBehavior, ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait,
     QueryFilter, Set,
@@ -11,7 +12,15 @@ use telegrambot::teloxide::prelude::*;
 use crate::models::{response::APIResponse, telegramauth::TelegramUser};
 
 pub fn routes() -> Vec<Route> {
-    routes![get_self, modify_channel, delete_self, get_following_authors]
+    routes![
+        get_self,
+        modify_channel,
+        delete_self,
+        get_following_authors,
+        get_schedule,
+        put_schedule,
+        delete_schedule
+    ]
 }
 
 #[derive(Deserialize)]
@@ -19,7 +28,23 @@ struct ChannelData {
     channel_id: String,
 }
 
-async fn get_or_create_user(db: &DatabaseConnection, user_id: i64) -> entity::telegram_user::Model {
+#[derive(Deserialize)]
+struct ScheduleData {
+    /// Minutes between two posts
+    interval: i32,
+    /// First slot of the day, in minutes after local midnight
+    day_start: i32,
+    /// No slots after this time, in minutes after local midnight
+    day_end: i32,
+    /// Offset of timezone from UTC, in minutes
+    #[serde(default)]
+    utc_offset: i32,
+}
+
+pub(super) async fn get_or_create_user(
+    db: &DatabaseConnection,
+    user_id: i64,
+) -> entity::telegram_user::Model {
     let user = entity::telegram_user::Entity::find_by_id(user_id)
         .one(db)
         .await
@@ -88,3 +113,192 @@ async fn get_following_authors(
 
     APIResponse::new(authors)
 }
+
+#[get("/schedule")]
+async fn get_schedule(db: &State<DatabaseConnection>, telegram_user: TelegramUser) -> APIResponse {
+    let user = get_or_create_user(db.deref(), telegram_user.id).await;
+    let Some(channel) = user.channel else {
+        return APIResponse::error(404, "You don't have linked channel");
+    };
+
+    let schedule = entity::posting_schedule::Entity::find_by_id(channel)
+        .one(db.deref())
+        .await
+        .unwrap();
+
+    match schedule {
+        Some(s) => APIResponse::new(s),
+        None => APIResponse::error(404, "Channel has no posting schedule"),
+    }
+}
+
+#[put("/schedule", data = "<data>")]
+async fn put_schedule(
+    db: &State<DatabaseConnection>,
+    telegram_user: TelegramUser,
+    data: Json<ScheduleData>,
+) -> APIResponse {
+    const MINUTES_IN_DAY: i32 = 24 * 60;
+
+    if data.interval <= 0 {
+        return APIResponse::error(422, "interval must be positive");
+    }
+    if !(0..MINUTES_IN_DAY).contains(&data.day_start)
+        || !(0..MINUTES_IN_DAY).contains(&data.day_end)
+    {
+        return APIResponse::error(422, "day_start and day_end must be in 0..1440");
+    }
+    if data.day_start > data.day_end {
+        return APIResponse::error(422, "day_start must not be after day_end");
+    }
+    if !(-12 * 60..=14 * 60).contains(&data.utc_offset) {
+        return APIResponse::error(422, "utc_offset must be in -720..=840");
+    }
+
+    let user = get_or_create_user(db.deref(), telegram_user.id).await;
+    let Some(channel) = user.channel else {
+        return APIResponse::error(404, "You don't have linked channel");
+    };
+
+    let active = entity::posting_schedule::ActiveModel {
+        channel: Set(channel),
+        interval: Set(data.interval),
+        day_start: Set(data.day_start),
+        day_end: Set(data.day_end),
+        utc_offset: Set(data.utc_offset),
+    };
+
+    let schedule = entity::posting_schedule::Entity::insert(active)
+        .on_conflict(
+            OnConflict::column(entity::posting_schedule::Column::Channel)
+                .update_columns([
+                    entity::posting_schedule::Column::Interval,
+                    entity::posting_schedule::Column::DayStart,
+                    entity::posting_schedule::Column::DayEnd,
+                    entity::posting_schedule::Column::UtcOffset,
+                ])
+                .to_owned(),
+        )
+        .exec_with_returning(db.deref())
+        .await
+        .unwrap();
+
+    APIResponse::new(schedule)
+}
+
+#[delete("/schedule")]
+async fn delete_schedule(
+    db: &State<DatabaseConnection>,
+    telegram_user: TelegramUser,
+) -> APIResponse {
+    let user = get_or_create_user(db.deref(), telegram_user.id).await;
+    let Some(channel) = user.channel else {
+        return APIResponse::error(404, "You don't have linked channel");
+    };
+
+    let result = entity::posting_schedule::Entity::delete_by_id(channel)
+        .exec(db.deref())
+        .await
+        .unwrap();
+
+    if result.rows_affected != 0 {
+        APIResponse::NoContent
+    } else {
+        APIResponse::error(404, "Channel has no posting schedule")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use entity::post::PostEntities;
+    use migration::{Migrator, MigratorTrait};
+    use sea_orm::{ActiveModelTrait, Database, DatabaseConnection, EntityTrait, Set};
+
+    async fn database() -> DatabaseConnection {
+        let db = Database::connect("sqlite::memory:").await.unwrap();
+        Migrator::up(&db, None).await.unwrap();
+
+        db
+    }
+
+    #[rocket::async_test]
+    /// Check deleting user removes their follows and scheduled and published posts
+    async fn test_delete_user_cascades() {
+        let db = database().await;
+
+        let user = entity::telegram_user::ActiveModel {
+            id: Set(1),
+            channel: Set(Some(-1001)),
+            ..Default::default()
+        }
+        .insert(&db)
+        .await
+        .unwrap();
+        let author = entity::author::ActiveModel {
+            platform_id: Set(12),
+            name: Set("jack".to_string()),
+            username: Set("jack".to_string()),
+            ..Default::default()
+        }
+        .insert(&db)
+        .await
+        .unwrap();
+        entity::follow::ActiveModel {
+            user_id: Set(user.id),
+            author_id: Set(author.id),
+        }
+        .insert(&db)
+        .await
+        .unwrap();
+
+        let scheduled = entity::scheduled_post::ActiveModel {
+            user_id: Set(user.id),
+            post_text: Set("hello".to_string()),
+            post_entities: Set(PostEntities::default()),
+            post_source: Set("jack".to_string()),
+            post_source_url: Set("https://twitter.com/jack/status/1".to_string()),
+            ..Default::default()
+        }
+        .insert(&db)
+        .await
+        .unwrap();
+        entity::published_post::ActiveModel {
+            user_id: Set(user.id),
+            scheduled_post_id: Set(Some(scheduled.id)),
+            chat_id: Set(-1001),
+            message_ids: Set("10".to_string()),
+            text_message_id: Set(10),
+            text_in_caption: Set(false),
+            post_text: Set("hello".to_string()),
+            post_source: Set("jack".to_string()),
+            post_source_url: Set("https://twitter.com/jack/status/1".to_string()),
+            published_at: Set(0),
+            ..Default::default()
+        }
+        .insert(&db)
+        .await
+        .unwrap();
+
+        entity::telegram_user::Entity::delete_by_id(user.id)
+            .exec(&db)
+            .await
+            .unwrap();
+
+        let follows = entity::follow::Entity::find().all(&db).await.unwrap();
+        assert!(follows.is_empty());
+        let scheduled = entity::scheduled_post::Entity::find()
+            .all(&db)
+            .await
+            .unwrap();
+        assert!(scheduled.is_empty());
+        let published = entity::published_post::Entity::find()
+            .all(&db)
+            .await
+            .unwrap();
+        assert!(published.is_empty());
+
+        // authors are shared between users
+        let authors = entity::author::Entity::find().all(&db).await.unwrap();
+        assert_eq!(authors.len(), 1);
+    }
+}
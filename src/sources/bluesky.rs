@@ -0,0 +1,471 @@
+use std::fmt::Display;
+
+use entity::{
+    author::Platform,
+    post::{EntityKind, PostEntity},
+    post_media::MediaType,
+};
+
+use super::{
+    hash_id, time_id, Source, SourceError, SourceMedia, SourcePost, SourceTimeline, SourceUser,
+    TimelineOptions,
+};
+use crate::models::{
+    blueskyclient::{
+        BlueskyClient, BlueskyEmbed, BlueskyError, BlueskyFacet, BlueskyFeature, BlueskyPost,
+        BlueskyProfile,
+    },
+    time::parse_rfc3339,
+};
+
+/// Number of posts fetched on the first fetch of author
+const INITIAL_POSTS: usize = 5;
+/// Page size of `getAuthorFeed` on next fetches
+const PAGE_SIZE: usize = 50;
+/// Maximum number of pages fetched in one `fetch_timeline`
+const MAX_PAGES: usize = 5;
+
+impl From<BlueskyError> for SourceError {
+    fn from(e: BlueskyError) -> Self {
+        match e {
+            BlueskyError::NotFound => SourceError::NotFound,
+            e => SourceError::Other(Box::new(e)),
+        }
+    }
+}
+
+impl From<BlueskyProfile> for SourceUser {
+    fn from(profile: BlueskyProfile) -> Self {
+        SourceUser {
+            platform_id: hash_id(Platform::Bluesky, &profile.did),
+            name: profile
+                .display_name
+                .filter(|f| !f.is_empty())
+                .unwrap_or_else(|| profile.handle.clone()),
+            external_id: profile.did,
+            username: profile.handle,
+            avatar_url: profile.avatar,
+        }
+    }
+}
+
+/// Record key of post, TIDs are sortable by creation time
+fn post_rkey(uri: &str) -> &str {
+    uri.rsplit('/').next().unwrap_or(uri)
+}
+
+/// Decodes TID (base32-sortable number of microseconds since epoch and clock id),
+/// returns `None` if `rkey` is not a TID
+fn tid_id(rkey: &str) -> Option<i64> {
+    const ALPHABET: &[u8] = b"234567abcdefghijklmnopqrstuvwxyz";
+
+    if rkey.len() != 13 {
+        return None;
+    }
+
+    rkey.bytes().try_fold(0i64, |id, c| {
+        let digit = ALPHABET.iter().position(|&f| f == c)? as i64;
+
+        id.checked_mul(32)?.checked_add(digit)
+    })
+}
+
+#[rocket::async_trait]
+impl Source for BlueskyClient {
+    fn platform(&self) -> Platform {
+        Platform::Bluesky
+    }
+
+    fn owns_username(&self, username: &str) -> bool {
+        username.starts_with("did:")
+            || username.contains('.')
+                && username
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '.' || c == '-')
+    }
+
+    async fn fetch_user(&self, id: &str) -> Result<SourceUser, SourceError> {
+        Ok(self.fetch_profile(id).await?.into())
+    }
+
+    async fn resolve_username(&self, username: &str) -> Result<SourceUser, SourceError> {
+        Ok(self.fetch_profile(username).await?.into())
+    }
+
+    /// Pages author feed until it reaches `cursor` (see [`FeedCursor`])
+    async fn fetch_timeline(
+        &self,
+        id: &str,
+        cursor: Option<&str>,
+        _options: TimelineOptions,
+    ) -> Result<SourceTimeline, SourceError> {
+        let cursor = cursor.map(FeedCursor::parse);
+        let last = cursor.as_ref().map(|f| f.last.as_str());
+        let limit = if cursor.is_some() {
+            PAGE_SIZE
+        } else {
+            INITIAL_POSTS
+        };
+
+        let mut posts = vec![];
+        let mut page_cursor = cursor.as_ref().and_then(|f| f.page.clone());
+        let mut reached = false;
+        for _ in 0..MAX_PAGES {
+            let page = self
+                .fetch_author_feed(id, page_cursor.as_deref(), limit)
+                .await?;
+
+            reached = page.feed.is_empty();
+            for item in page.feed {
+                let rkey = post_rkey(&item.post.uri);
+                if matches!(last, Some(c) if rkey <= c) {
+                    // pinned post may be older than cursor
+                    reached |= item.reason.is_none();
+                    continue;
+                }
+
+                // reposts and replies are skipped, like on twitter
+                if item.reason.is_some()
+                    || item.post.record.reply.is_some()
+                    || item.post.author.did != id
+                {
+                    continue;
+                }
+
+                posts.push(item.post);
+            }
+
+            page_cursor = page.cursor;
+            if reached || last.is_none() || page_cursor.is_none() {
+                break;
+            }
+        }
+
+        posts.sort_by(|a, b| post_rkey(&a.uri).cmp(post_rkey(&b.uri)));
+        posts.dedup_by(|a, b| a.uri == b.uri);
+
+        let newest = posts
+            .last()
+            .map(|f| post_rkey(&f.uri).to_string())
+            .max(cursor.as_ref().map(|f| f.newest.clone()));
+        let cursor = match (last, newest, page_cursor) {
+            (Some(last), Some(newest), Some(page)) if !reached => Some(
+                FeedCursor {
+                    last: last.to_string(),
+                    newest,
+                    page: Some(page),
+                }
+                .to_string(),
+            ),
+            (_, newest, _) => newest,
+        };
+        let posts = posts.into_iter().map(bluesky_post).collect();
+
+        Ok(SourceTimeline { posts, cursor })
+    }
+}
+
+/// Cursor of author feed: record key of the newest collected post. If feed was not paged
+/// to it in [`MAX_PAGES`] pages, it is `{last_rkey}:{newest_rkey}:{page_cursor}`, and
+/// older posts after `last_rkey` are fetched from `page_cursor` before new ones
+struct FeedCursor {
+    last: String,
+    newest: String,
+    page: Option<String>,
+}
+
+impl FeedCursor {
+    fn parse(cursor: &str) -> Self {
+        // record keys have no colons, page cursors may have them
+        let mut parts = cursor.splitn(3, ':');
+        let last = parts.next().unwrap_or_default().to_string();
+
+        Self {
+            newest: parts.next().map_or_else(|| last.clone(), str::to_string),
+            page: parts.next().map(str::to_string),
+            last,
+        }
+    }
+}
+
+impl Display for FeedCursor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.page {
+            Some(page) => write!(f, "{}:{}:{page}", self.last, self.newest),
+            None => write!(f, "{}", self.newest),
+        }
+    }
+}
+
+fn bluesky_post(post: BlueskyPost) -> SourcePost {
+    let mut text = post.record.text;
+    let mut entities = facet_entities(&text, &post.record.facets);
+    let mut media = vec![];
+
+    let embed = match post.embed {
+        Some(BlueskyEmbed::RecordWithMedia { media }) => Some(*media),
+        embed => embed,
+    };
+    match embed {
+        Some(BlueskyEmbed::Images { images }) => {
+            media.extend(images.into_iter().map(|f| SourceMedia {
+                media_type: MediaType::Photo,
+                url: f.fullsize,
+                alt_text: Some(f.alt).filter(|f| !f.is_empty()),
+                width: f.aspect_ratio.as_ref().map(|f| f.width),
+                height: f.aspect_ratio.as_ref().map(|f| f.height),
+                duration_ms: None,
+            }));
+        }
+        Some(BlueskyEmbed::External { external }) => {
+            // link card, text usually doesn't contain full link
+            if !entities.iter().any(|f| f.url == external.uri) {
+                let title = if external.title.is_empty() {
+                    external.uri.clone()
+                } else {
+                    external.title
+                };
+
+                if !text.is_empty() {
+                    text.push_str("\n\n");
+                }
+                let start = text.chars().count();
+                text.push_str(&title);
+                entities.push(PostEntity {
+                    kind: EntityKind::Url,
+                    start,
+                    end: start + title.chars().count(),
+                    url: external.uri,
+                });
+            }
+
+            media.extend(external.thumb.map(|url| SourceMedia {
+                media_type: MediaType::Photo,
+                url,
+                alt_text: None,
+                width: None,
+                height: None,
+                duration_ms: None,
+            }));
+        }
+        _ => {}
+    }
+
+    let posted_at = post.record.created_at.as_deref().and_then(parse_rfc3339);
+    let id = hash_id(Platform::Bluesky, &post.uri);
+
+    SourcePost {
+        platform_id: tid_id(post_rkey(&post.uri))
+            .unwrap_or_else(|| time_id(posted_at.unwrap_or(0), id)),
+        // record keys are unique only in repository of author
+        external_id: id.to_string(),
+        author_id: Some(post.author.did),
+        source_url: format!(
+            "https://bsky.app/profile/{}/post/{}",
+            post.author.handle,
+            post_rkey(&post.uri)
+        ),
+        text,
+        entities,
+        source_text: post
+            .author
+            .display_name
+            .filter(|f| !f.is_empty())
+            .unwrap_or(post.author.handle),
+        media,
+        posted_at,
+        reply_to: None,
+        quoted: None,
+        edit_of: None,
+    }
+}
+
+/// Converts facets (with offsets in UTF-8 bytes) to [`PostEntity`]
+fn facet_entities(text: &str, facets: &[BlueskyFacet]) -> Vec<PostEntity> {
+    let char_offset = |byte: usize| text.get(..byte).map(|f| f.chars().count());
+
+    facets
+        .iter()
+        .filter_map(|facet| {
+            let start = char_offset(facet.index.byte_start)?;
+            let end = char_offset(facet.index.byte_end)?;
+
+            let (kind, url) = facet.features.iter().find_map(|f| match f {
+                BlueskyFeature::Link { uri } => Some((EntityKind::Url, uri.clone())),
+                BlueskyFeature::Mention { did } => Some((
+                    EntityKind::Mention,
+                    format!("https://bsky.app/profile/{did}"),
+                )),
+                BlueskyFeature::Tag { tag } => Some((
+                    EntityKind::Hashtag,
+                    format!("https://bsky.app/hashtag/{}", urlencoding::encode(tag)),
+                )),
+                BlueskyFeature::Unknown => None,
+            })?;
+
+            Some(PostEntity {
+                kind,
+                start,
+                end,
+                url,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use entity::{post::EntityKind, post_media::MediaType};
+    use mockserver::{fixture, MockRoute, MockServer};
+
+    use super::{tid_id, MAX_PAGES};
+    use crate::{
+        models::blueskyclient::BlueskyClient,
+        sources::{Source, SourceError, TimelineOptions},
+    };
+
+    const DID: &str = "did:plc:z72i7hdynmk6r22z27h6tvur";
+
+    async fn client(routes: Vec<MockRoute>) -> (MockServer, BlueskyClient) {
+        let server = MockServer::start(routes).await;
+        let client = BlueskyClient {
+            base_url: server.url.clone(),
+        };
+
+        (server, client)
+    }
+
+    #[test]
+    /// Check [`BlueskyClient::owns_username`] accepts handles and DIDs only
+    fn test_owns_username() {
+        let client = BlueskyClient::new();
+
+        assert!(client.owns_username("bsky.app"));
+        assert!(client.owns_username("jay.bsky.team"));
+        assert!(client.owns_username(DID));
+        assert!(!client.owns_username("jack"));
+        assert!(!client.owns_username("@Gargron@mastodon.social"));
+        assert!(!client.owns_username("https://blog.example.com/rss.xml"));
+    }
+
+    #[test]
+    /// Check [`tid_id`] decodes TIDs in order of their time and rejects other keys
+    fn test_tid_id() {
+        assert_eq!(tid_id("2222222222222"), Some(0));
+        assert_eq!(tid_id("222222222223b"), Some(32 + 7));
+        // 2023-07-24T09:11:30.060288Z
+        assert_eq!(
+            tid_id("3k3b222222222").map(|f| f >> 10),
+            Some(1690189890060288)
+        );
+        assert!(tid_id("3k3b222222222") < tid_id("3k4c222222222"));
+        assert_eq!(tid_id("self"), None);
+        assert_eq!(tid_id("3k3b22222222A"), None);
+    }
+
+    #[rocket::async_test]
+    /// Check [`BlueskyClient::resolve_username`] and not found actors
+    async fn test_resolve_username() {
+        let (_server, client) = client(vec![MockRoute::new(
+            "/xrpc/app.bsky.actor.getProfile?actor=bsky.app",
+            200,
+            fixture("bluesky/profile.json"),
+        )])
+        .await;
+
+        let user = client.resolve_username("bsky.app").await.unwrap();
+        assert_eq!(user.external_id, DID);
+        assert_eq!(user.username, "bsky.app");
+        assert_eq!(user.name, "Bluesky");
+
+        let res = client.resolve_username("nobody.bsky.social").await;
+        assert!(matches!(res, Err(SourceError::NotFound)));
+    }
+
+    #[rocket::async_test]
+    /// Check [`BlueskyClient::fetch_timeline`] skips reposts, replies and known posts,
+    /// and maps facets and embeds
+    async fn test_fetch_timeline() {
+        let (server, client) = client(vec![MockRoute::new(
+            "/xrpc/app.bsky.feed.getAuthorFeed",
+            200,
+            fixture("bluesky/author_feed.json"),
+        )])
+        .await;
+
+        let timeline = client
+            .fetch_timeline(DID, Some("3k2a222222222"), TimelineOptions::default())
+            .await
+            .unwrap();
+
+        // known post reached, so the next page is not requested
+        assert_eq!(server.requests().len(), 1);
+        assert_eq!(timeline.cursor.as_deref(), Some("3k4c222222222"));
+        assert_eq!(timeline.posts.len(), 2);
+
+        let post = &timeline.posts[0];
+        assert_eq!(post.text, "Photos from 🌧 #rainy day");
+        assert_eq!(
+            post.source_url,
+            "https://bsky.app/profile/bsky.app/post/3k3b222222222"
+        );
+        assert_eq!(post.source_text, "Bluesky");
+        assert_eq!(post.posted_at, Some(1693612800));
+        assert_eq!(post.platform_id, tid_id("3k3b222222222").unwrap());
+        assert_eq!(post.entities.len(), 1);
+        assert_eq!(post.entities[0].kind, EntityKind::Hashtag);
+        assert_eq!(post.entities[0].start, 14);
+        assert_eq!(post.entities[0].end, 20);
+        assert_eq!(post.entities[0].url, "https://bsky.app/hashtag/rainy");
+        assert_eq!(post.media.len(), 2);
+        assert!(post.media.iter().all(|f| f.media_type == MediaType::Photo));
+        assert_eq!(post.media[0].alt_text, None);
+        assert_eq!(post.media[1].alt_text.as_deref(), Some("rain"));
+
+        let post = &timeline.posts[1];
+        assert_eq!(post.text, "Read our blog\n\nBluesky Blog");
+        assert_eq!(post.entities.len(), 1);
+        assert_eq!(post.entities[0].start, 15);
+        assert_eq!(post.entities[0].end, 27);
+        assert_eq!(post.entities[0].url, "https://bsky.social/about/blog");
+        assert_eq!(post.media.len(), 1);
+        assert_eq!(post.media[0].url, "https://cdn.bsky.app/img/thumb.jpg");
+    }
+
+    #[rocket::async_test]
+    /// Check [`BlueskyClient::fetch_timeline`] keeps cursor of unfinished pagination
+    /// and resumes from it on the next fetch
+    async fn test_fetch_timeline_max_pages() {
+        let feed = fixture("bluesky/author_feed.json").replace("2023-09-01T00:00:00.000Z", "page2");
+        let (server, client) = client(vec![
+            MockRoute::new("/xrpc/app.bsky.feed.getAuthorFeed", 200, feed).times(MAX_PAGES),
+            MockRoute::new(
+                "/xrpc/app.bsky.feed.getAuthorFeed?cursor=page2",
+                200,
+                r#"{"feed": []}"#,
+            ),
+        ])
+        .await;
+
+        let timeline = client
+            .fetch_timeline(DID, Some("3k1a222222222"), TimelineOptions::default())
+            .await
+            .unwrap();
+
+        assert_eq!(server.requests().len(), MAX_PAGES);
+        assert_eq!(timeline.posts.len(), 3);
+        assert_eq!(
+            timeline.cursor.as_deref(),
+            Some("3k1a222222222:3k4c222222222:page2")
+        );
+
+        let timeline = client
+            .fetch_timeline(DID, timeline.cursor.as_deref(), TimelineOptions::default())
+            .await
+            .unwrap();
+
+        assert!(server.requests()[MAX_PAGES].contains("cursor=page2"));
+        assert!(timeline.posts.is_empty());
+        assert_eq!(timeline.cursor.as_deref(), Some("3k4c222222222"));
+    }
+}
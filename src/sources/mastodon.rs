@@ -0,0 +1,376 @@
+use entity::{author::Platform, post::PostEntity, post_media::MediaType};
+
+use super::{
+    hash_id, time_id, Source, SourceError, SourceMedia, SourcePost, SourceTimeline, SourceUser,
+    TimelineOptions,
+};
+use crate::models::{
+    html::html_to_text,
+    mastodonclient::{MastodonAccount, MastodonClient, MastodonError, MastodonStatus},
+    time::parse_rfc3339,
+};
+
+/// Number of statuses fetched on the first fetch of account
+const INITIAL_STATUSES: usize = 5;
+/// Maximum page size of mastodon API
+const MAX_STATUSES: usize = 40;
+/// Maximum number of pages fetched in one poll. Remaining statuses are fetched on the
+/// next polls
+const MAX_PAGES: usize = 5;
+
+impl From<MastodonError> for SourceError {
+    fn from(e: MastodonError) -> Self {
+        match e {
+            MastodonError::NotFound => SourceError::NotFound,
+            e => SourceError::Other(Box::new(e)),
+        }
+    }
+}
+
+/// Splits `id@instance` (optionally prefixed with `@`)
+fn split_acct(acct: &str) -> Option<(&str, &str)> {
+    let (name, instance) = acct.strip_prefix('@').unwrap_or(acct).split_once('@')?;
+
+    (!name.is_empty() && !instance.is_empty() && !acct.contains('/') && !instance.contains('@'))
+        .then_some((name, instance))
+}
+
+/// Sort key of status id. Ids are numeric strings
+fn id_key(id: &str) -> (usize, &str) {
+    (id.len(), id)
+}
+
+fn account_user(instance: &str, account: MastodonAccount) -> SourceUser {
+    let external_id = format!("{}@{instance}", account.id);
+
+    SourceUser {
+        platform_id: hash_id(Platform::Mastodon, &external_id),
+        external_id,
+        name: if account.display_name.is_empty() {
+            account.username.clone()
+        } else {
+            account.display_name
+        },
+        username: format!("@{}@{instance}", account.username),
+        avatar_url: account.avatar,
+    }
+}
+
+#[rocket::async_trait]
+impl Source for MastodonClient {
+    fn platform(&self) -> Platform {
+        Platform::Mastodon
+    }
+
+    fn owns_username(&self, username: &str) -> bool {
+        username.starts_with('@') && split_acct(username).is_some()
+    }
+
+    async fn fetch_user(&self, id: &str) -> Result<SourceUser, SourceError> {
+        let (id, instance) = split_acct(id).ok_or(SourceError::NotFound)?;
+        let account = self.fetch_account(instance, id).await?;
+
+        Ok(account_user(instance, account))
+    }
+
+    async fn resolve_username(&self, username: &str) -> Result<SourceUser, SourceError> {
+        let (username, instance) = split_acct(username).ok_or(SourceError::NotFound)?;
+        let account = self.lookup_account(instance, username).await?;
+
+        Ok(account_user(instance, account))
+    }
+
+    async fn fetch_timeline(
+        &self,
+        id: &str,
+        cursor: Option<&str>,
+        _options: TimelineOptions,
+    ) -> Result<SourceTimeline, SourceError> {
+        let (account_id, instance) = split_acct(id).ok_or(SourceError::NotFound)?;
+
+        let mut statuses = match cursor {
+            None => {
+                self.fetch_statuses(instance, account_id, None, INITIAL_STATUSES)
+                    .await?
+            }
+            // pages go forward in time from cursor, so nothing between polls is skipped
+            Some(cursor) => {
+                let mut statuses = vec![];
+                let mut min_id = cursor.to_string();
+                for _ in 0..MAX_PAGES {
+                    let page = self
+                        .fetch_statuses(instance, account_id, Some(&min_id), MAX_STATUSES)
+                        .await?;
+                    let full = page.len() == MAX_STATUSES;
+                    if let Some(newest) = page.iter().map(|f| id_key(&f.id)).max() {
+                        min_id = newest.1.to_string();
+                    }
+                    statuses.extend(page);
+
+                    if !full {
+                        break;
+                    }
+                }
+
+                statuses
+            }
+        };
+        statuses.sort_by(|a, b| id_key(&a.id).cmp(&id_key(&b.id)));
+
+        let cursor = statuses.last().map(|f| f.id.clone());
+        let posts = statuses.into_iter().map(status_post).collect();
+
+        Ok(SourceTimeline { posts, cursor })
+    }
+}
+
+fn status_post(status: MastodonStatus) -> SourcePost {
+    let (content, content_entities) = html_to_text(&status.content);
+
+    // content warning goes before text
+    let (text, entities) = if status.spoiler_text.is_empty() {
+        (content, content_entities)
+    } else {
+        let offset = status.spoiler_text.chars().count() + 2;
+        let entities = content_entities
+            .into_iter()
+            .map(|f| PostEntity {
+                start: f.start + offset,
+                end: f.end + offset,
+                ..f
+            })
+            .collect();
+
+        (format!("{}\n\n{content}", status.spoiler_text), entities)
+    };
+
+    let media = status
+        .media_attachments
+        .into_iter()
+        .filter_map(|f| {
+            let media_type = match f.r#type.as_str() {
+                "image" => MediaType::Photo,
+                "video" => MediaType::Video,
+                // gifv is mp4 video without sound
+                "gifv" => MediaType::Animation,
+                _ => return None,
+            };
+            let size = f.meta.original;
+
+            Some(SourceMedia {
+                media_type,
+                url: f.url,
+                alt_text: f.description.filter(|f| !f.is_empty()),
+                width: size.as_ref().and_then(|f| f.width),
+                height: size.as_ref().and_then(|f| f.height),
+                duration_ms: size
+                    .as_ref()
+                    .and_then(|f| f.duration)
+                    .map(|f| (f * 1000.0) as i32),
+            })
+        })
+        .collect();
+
+    let posted_at = status.created_at.as_deref().and_then(parse_rfc3339);
+    let id = hash_id(Platform::Mastodon, &status.uri);
+
+    SourcePost {
+        // mastodon ids are time-ordered numbers, other servers may use any strings
+        platform_id: status
+            .id
+            .parse()
+            .unwrap_or_else(|_| time_id(posted_at.unwrap_or(0), id)),
+        // status ids are unique only on instance
+        external_id: id.to_string(),
+        author_id: None,
+        text,
+        entities,
+        source_text: if status.account.display_name.is_empty() {
+            status.account.username
+        } else {
+            status.account.display_name
+        },
+        source_url: status.url.unwrap_or(status.uri),
+        media,
+        posted_at,
+        reply_to: None,
+        quoted: None,
+        edit_of: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use entity::{post::EntityKind, post_media::MediaType};
+    use mockserver::{fixture, MockRoute, MockServer};
+
+    use super::split_acct;
+    use crate::{
+        models::mastodonclient::MastodonClient,
+        sources::{Source, SourceError, TimelineOptions},
+    };
+
+    async fn client(routes: Vec<MockRoute>) -> (MockServer, MastodonClient) {
+        let server = MockServer::start(routes).await;
+        let client = MastodonClient {
+            base_url: Some(server.url.clone()),
+        };
+
+        (server, client)
+    }
+
+    #[test]
+    /// Check [`split_acct`] with valid and invalid accounts
+    fn test_split_acct() {
+        assert_eq!(
+            split_acct("@Gargron@mastodon.social"),
+            Some(("Gargron", "mastodon.social"))
+        );
+        assert_eq!(
+            split_acct("1@mastodon.social"),
+            Some(("1", "mastodon.social"))
+        );
+        assert_eq!(split_acct("@Gargron"), None);
+        assert_eq!(split_acct("@Gargron@"), None);
+        assert_eq!(split_acct("https://example.com/@Gargron"), None);
+    }
+
+    #[rocket::async_test]
+    /// Check [`MastodonClient::resolve_username`] looks account up on its instance
+    async fn test_resolve_username() {
+        let (server, client) = client(vec![MockRoute::new(
+            "/api/v1/accounts/lookup",
+            200,
+            fixture("mastodon/account.json"),
+        )])
+        .await;
+
+        let user = client
+            .resolve_username("@Gargron@mastodon.social")
+            .await
+            .unwrap();
+
+        assert_eq!(user.external_id, "1@mastodon.social");
+        assert_eq!(user.username, "@Gargron@mastodon.social");
+        assert_eq!(user.name, "Eugen Rochko");
+        assert_eq!(
+            server.requests(),
+            vec!["/api/v1/accounts/lookup?acct=Gargron"]
+        );
+    }
+
+    #[rocket::async_test]
+    /// Check [`MastodonClient::fetch_timeline`] converts statuses and passes cursor
+    /// as `min_id`
+    async fn test_fetch_timeline() {
+        let (server, client) = client(vec![MockRoute::new(
+            "/api/v1/accounts/1/statuses",
+            200,
+            fixture("mastodon/statuses.json"),
+        )])
+        .await;
+
+        let timeline = client
+            .fetch_timeline(
+                "1@mastodon.social",
+                Some("109000000000000000"),
+                TimelineOptions::default(),
+            )
+            .await
+            .unwrap();
+
+        assert!(server.requests()[0].contains("min_id=109000000000000000"));
+        assert!(server.requests()[0].contains("exclude_reblogs=true"));
+        assert_eq!(timeline.cursor.as_deref(), Some("109400000000000000"));
+        assert_eq!(timeline.posts.len(), 2);
+
+        // oldest first
+        let post = &timeline.posts[0];
+        assert_eq!(post.text, "CW: spoilers\n\nThe end was great #movies");
+        assert_eq!(post.source_text, "Eugen Rochko");
+        assert_eq!(
+            post.source_url,
+            "https://mastodon.social/@Gargron/109300000000000000"
+        );
+        assert_eq!(post.posted_at, Some(1667845800));
+        assert_eq!(post.platform_id, 109300000000000000);
+        assert_eq!(post.entities.len(), 1);
+        assert_eq!(post.entities[0].kind, EntityKind::Hashtag);
+        assert_eq!(post.entities[0].start, 32);
+        assert_eq!(post.entities[0].end, 39);
+        assert!(post.media.is_empty());
+
+        let post = &timeline.posts[1];
+        assert_eq!(post.platform_id, 109400000000000000);
+        assert_eq!(post.text, "Look at this\n\nphotos and video");
+        assert_eq!(post.media.len(), 2);
+        assert_eq!(post.media[0].media_type, MediaType::Photo);
+        assert_eq!(
+            post.media[0].url,
+            "https://files.mastodon.social/media/original/photo.png"
+        );
+        assert_eq!(post.media[0].alt_text.as_deref(), Some("A cat"));
+        assert_eq!(post.media[0].width, Some(640));
+        assert_eq!(post.media[1].media_type, MediaType::Animation);
+        assert_eq!(post.media[1].duration_ms, Some(2500));
+    }
+
+    #[rocket::async_test]
+    /// Check [`MastodonClient::fetch_timeline`] fetches all pages of statuses after
+    /// cursor, oldest first
+    async fn test_fetch_timeline_pages() {
+        // full page of statuses right after cursor
+        let status: serde_json::Value =
+            serde_json::from_str(&fixture("mastodon/statuses.json")).unwrap();
+        let page = (1..=40)
+            .rev()
+            .map(|i| {
+                let id = format!("1090000000000000{i:02}");
+                let mut status = status[1].clone();
+                status["id"] = id.clone().into();
+                status["uri"] = format!("https://mastodon.social/statuses/{id}").into();
+                status
+            })
+            .collect::<Vec<_>>();
+
+        let (server, client) = client(vec![
+            MockRoute::new(
+                "/api/v1/accounts/1/statuses?min_id=109000000000000000",
+                200,
+                serde_json::to_string(&page).unwrap(),
+            ),
+            MockRoute::new(
+                "/api/v1/accounts/1/statuses?min_id=109000000000000040",
+                200,
+                fixture("mastodon/statuses.json"),
+            ),
+        ])
+        .await;
+
+        let timeline = client
+            .fetch_timeline(
+                "1@mastodon.social",
+                Some("109000000000000000"),
+                TimelineOptions::default(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(server.requests().len(), 2);
+        assert_eq!(timeline.posts.len(), 42);
+        assert_eq!(timeline.posts[0].platform_id, 109000000000000001);
+        assert_eq!(timeline.posts[39].platform_id, 109000000000000040);
+        assert_eq!(timeline.posts[41].platform_id, 109400000000000000);
+        assert_eq!(timeline.cursor.as_deref(), Some("109400000000000000"));
+    }
+
+    #[rocket::async_test]
+    /// Check [`MastodonClient::fetch_user`] returns [`SourceError::NotFound`] on 404
+    async fn test_fetch_user_not_found() {
+        let (_server, client) = client(vec![]).await;
+
+        let res = client.fetch_user("404@mastodon.social").await;
+
+        assert!(matches!(res, Err(SourceError::NotFound)));
+    }
+}
@@ -0,0 +1,185 @@
+use std::{fmt::Display, sync::Arc};
+
+use entity::{author::Platform, post::PostEntity, post_media::MediaType};
+use sha2::{Digest, Sha256};
+
+pub mod bluesky;
+pub mod mastodon;
+pub mod rss;
+pub mod twitter;
+
+/// Content source (platform), that can feed posts
+#[rocket::async_trait]
+pub trait Source: Send + Sync {
+    /// Platform of this source
+    fn platform(&self) -> Platform;
+
+    /// Whether `username` looks like username on this platform
+    fn owns_username(&self, username: &str) -> bool;
+
+    /// Fetches user by platform-native id (see `author.external_id`)
+    async fn fetch_user(&self, id: &str) -> Result<SourceUser, SourceError>;
+
+    /// Fetches users by platform-native ids. Users, that are not found (or not visible
+    /// anymore), are skipped. Sources without batch lookup fetch users one by one
+    async fn fetch_users(&self, ids: &[String]) -> Result<Vec<SourceUser>, SourceError> {
+        let mut users = vec![];
+        for id in ids {
+            match self.fetch_user(id).await {
+                Ok(user) => users.push(user),
+                Err(SourceError::NotFound | SourceError::Suspended | SourceError::Protected) => {}
+                Err(e) => return Err(e),
+            }
+        }
+
+        Ok(users)
+    }
+
+    /// Fetches user by username
+    async fn resolve_username(&self, username: &str) -> Result<SourceUser, SourceError>;
+
+    /// Fetches posts of user `id`, that are newer than `cursor`. If `cursor` is `None`,
+    /// fetches latest posts
+    async fn fetch_timeline(
+        &self,
+        id: &str,
+        cursor: Option<&str>,
+        options: TimelineOptions,
+    ) -> Result<SourceTimeline, SourceError>;
+}
+
+#[derive(Clone, Copy, Default)]
+/// What to collect besides plain posts of author. Sources, that don't support an
+/// option, ignore it
+pub struct TimelineOptions {
+    /// Collect self-replies (threads), see [`SourcePost::reply_to`]
+    pub threads: bool,
+    /// Collect quoted posts, see [`SourcePost::quoted`]
+    pub quotes: bool,
+}
+
+pub struct SourceUser {
+    /// Platform ID of user, see `author.platform_id`
+    pub platform_id: i64,
+    /// Platform-native ID of user
+    pub external_id: String,
+    pub name: String,
+    pub username: String,
+    pub avatar_url: Option<String>,
+}
+
+pub struct SourceTimeline {
+    /// New posts, oldest first
+    pub posts: Vec<SourcePost>,
+    /// Cursor to pass in next `fetch_timeline`, `None` if it doesn't change
+    pub cursor: Option<String>,
+}
+
+pub struct SourcePost {
+    /// Platform ID of post, that grows with its time, see `post.platform_id`
+    pub platform_id: i64,
+    /// Platform-native ID of post, see `post.external_id`
+    pub external_id: String,
+    /// Platform-native ID of author of post (see `author.external_id`), if it may differ
+    /// from author of timeline
+    pub author_id: Option<String>,
+    pub text: String,
+    pub entities: Vec<PostEntity>,
+    /// Default text of source link (usually author name)
+    pub source_text: String,
+    pub source_url: String,
+    pub media: Vec<SourceMedia>,
+    /// Unix time when post was published, if known
+    pub posted_at: Option<i64>,
+    /// Platform ID of post, that this post replies to. Set only for self-replies
+    pub reply_to: Option<i64>,
+    /// Post quoted by this post
+    pub quoted: Option<Box<SourcePost>>,
+    /// Platform ID of original version of post, if this post is its edited version
+    pub edit_of: Option<i64>,
+}
+
+pub struct SourceMedia {
+    pub media_type: MediaType,
+    pub url: String,
+    pub alt_text: Option<String>,
+    pub width: Option<i32>,
+    pub height: Option<i32>,
+    pub duration_ms: Option<i32>,
+}
+
+#[derive(Debug)]
+pub enum SourceError {
+    /// User (or post) does not exists
+    NotFound,
+    /// User is suspended (or banned) on platform
+    Suspended,
+    /// User is protected, so its posts are not visible
+    Protected,
+    /// Platform rejected credentials of source
+    Unauthorized,
+    /// Platform rate limit is exhausted until `reset_at` (unix time)
+    RateLimited { reset_at: i64 },
+    /// Any other error
+    Other(Box<dyn std::error::Error + Send + Sync>),
+}
+
+impl Display for SourceError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NotFound => write!(f, "Not found"),
+            Self::Suspended => write!(f, "User is suspended"),
+            Self::Protected => write!(f, "User is protected"),
+            Self::Unauthorized => write!(f, "Unauthorized"),
+            Self::RateLimited { reset_at } => write!(f, "Rate limited until {reset_at}"),
+            Self::Other(e) => e.fmt(f),
+        }
+    }
+}
+impl std::error::Error for SourceError {}
+
+#[derive(Clone, Default)]
+/// Registered sources
+pub struct Sources(Vec<Arc<dyn Source>>);
+
+impl Sources {
+    /// Registers source, replacing previous source of the same platform
+    pub fn register<S>(&mut self, source: S)
+    where
+        S: Source + 'static,
+    {
+        self.0.retain(|f| f.platform() != source.platform());
+        self.0.push(Arc::new(source));
+    }
+
+    /// Returns source of `platform`, if it registered
+    pub fn get(&self, platform: Platform) -> Option<&dyn Source> {
+        self.0
+            .iter()
+            .find(|f| f.platform() == platform)
+            .map(|f| f.as_ref())
+    }
+
+    /// Returns first source, that owns `username`
+    pub fn for_username(&self, username: &str) -> Option<&dyn Source> {
+        self.0
+            .iter()
+            .find(|f| f.owns_username(username))
+            .map(|f| f.as_ref())
+    }
+}
+
+/// Hash of platform-native `id` for platforms with non-numeric ids, always
+/// non-negative. Identifies users and posts, but has no order
+pub fn hash_id(platform: Platform, id: &str) -> i64 {
+    let hash = Sha256::digest(format!("{platform:?}:{id}"));
+    let bytes = hash[..8].try_into().expect("sha256 is longer than 8 bytes");
+
+    (u64::from_be_bytes(bytes) >> 1) as i64
+}
+
+/// Platform ID of post for platforms without time-ordered ids. Grows with unix `time`,
+/// posts of the same second are ordered by low bits of `hash`
+pub fn time_id(time: i64, hash: i64) -> i64 {
+    (time << 20) | (hash & 0xFFFFF)
+}
@@ -0,0 +1,363 @@
+use entity::{author::Platform, post::PostEntity, post_media::MediaType};
+use feed_rs::model::{Entry, Feed, MediaObject, Text};
+
+use super::{
+    hash_id, time_id, Source, SourceError, SourceMedia, SourcePost, SourceTimeline, SourceUser,
+    TimelineOptions,
+};
+use crate::models::{
+    feedclient::{FeedClient, FeedError},
+    html::html_to_text,
+};
+
+/// Number of latest entries taken on the first fetch of feed
+const INITIAL_ENTRIES: usize = 5;
+/// Maximum length of entry text in chars, longer texts are cut
+const MAX_ENTRY_TEXT: usize = 1000;
+
+impl From<FeedError> for SourceError {
+    fn from(e: FeedError) -> Self {
+        match e {
+            FeedError::HttpError(e) if e.status() == Some(reqwest::StatusCode::NOT_FOUND) => {
+                SourceError::NotFound
+            }
+            e => SourceError::Other(Box::new(e)),
+        }
+    }
+}
+
+#[rocket::async_trait]
+impl Source for FeedClient {
+    fn platform(&self) -> Platform {
+        Platform::Rss
+    }
+
+    fn owns_username(&self, username: &str) -> bool {
+        username.starts_with("https://") || username.starts_with("http://")
+    }
+
+    async fn fetch_user(&self, id: &str) -> Result<SourceUser, SourceError> {
+        let feed = self.fetch_feed(id).await?;
+
+        Ok(feed_user(id, &feed))
+    }
+
+    async fn resolve_username(&self, username: &str) -> Result<SourceUser, SourceError> {
+        // feeds are identified by url only
+        Source::fetch_user(self, username).await
+    }
+
+    async fn fetch_timeline(
+        &self,
+        id: &str,
+        cursor: Option<&str>,
+        _options: TimelineOptions,
+    ) -> Result<SourceTimeline, SourceError> {
+        let feed = self.fetch_feed(id).await?;
+
+        Ok(parse_timeline(id, feed, cursor))
+    }
+}
+
+/// Author of feed at `url`
+fn feed_user(url: &str, feed: &Feed) -> SourceUser {
+    SourceUser {
+        platform_id: hash_id(Platform::Rss, url),
+        external_id: url.to_string(),
+        name: feed_title(url, feed),
+        username: url.to_string(),
+        avatar_url: feed
+            .logo
+            .as_ref()
+            .or(feed.icon.as_ref())
+            .map(|f| f.uri.clone()),
+    }
+}
+
+fn feed_title(url: &str, feed: &Feed) -> String {
+    feed.title
+        .as_ref()
+        .map(|f| f.content.trim().to_string())
+        .filter(|f| !f.is_empty())
+        .unwrap_or_else(|| url.to_string())
+}
+
+/// Converts entries of feed at `url` newer than `cursor` to posts. Cursor is unix time of
+/// the latest entry, entries without date are treated as published at `0`, so they are
+/// only taken on the first fetch
+fn parse_timeline(url: &str, feed: Feed, cursor: Option<&str>) -> SourceTimeline {
+    let last = cursor.and_then(|f| f.parse::<i64>().ok());
+    let source_text = feed_title(url, &feed);
+
+    let mut entries = feed
+        .entries
+        .into_iter()
+        .map(|f| {
+            let date = f.published.or(f.updated).map_or(0, |d| d.timestamp());
+
+            (date, f)
+        })
+        .filter(|(date, _)| last.is_none_or(|last| *date > last))
+        .collect::<Vec<_>>();
+    entries.sort_by_key(|f| f.0);
+
+    if last.is_none() {
+        entries.drain(..entries.len().saturating_sub(INITIAL_ENTRIES));
+    }
+
+    let cursor = entries.last().map(|f| f.0.to_string());
+    let posts = entries
+        .into_iter()
+        .map(|(date, entry)| entry_post(url, &source_text, date, entry))
+        .collect();
+
+    SourceTimeline { posts, cursor }
+}
+
+fn entry_post(url: &str, source_text: &str, date: i64, entry: Entry) -> SourcePost {
+    let title = entry
+        .title
+        .as_ref()
+        .map(|f| text_content(f).0)
+        .unwrap_or_default();
+
+    let body = entry
+        .content
+        .as_ref()
+        .and_then(|f| f.body.as_deref())
+        .map(html_to_text)
+        .or_else(|| entry.summary.as_ref().map(text_content))
+        .or_else(|| {
+            entry
+                .media
+                .iter()
+                .find_map(|f| f.description.as_ref())
+                .map(text_content)
+        })
+        .unwrap_or_default();
+    let (body, body_entities) = truncate(body.0, body.1, MAX_ENTRY_TEXT);
+
+    // nitter and similar feeds put the beginning of text to title
+    let title_is_text = title.is_empty() || body.starts_with(title.trim_end_matches('…'));
+
+    let (text, entities) = if title_is_text {
+        (body, body_entities)
+    } else if body.is_empty() {
+        (title, vec![])
+    } else {
+        let offset = title.chars().count() + 2;
+        let entities = body_entities
+            .into_iter()
+            .map(|f| PostEntity {
+                start: f.start + offset,
+                end: f.end + offset,
+                ..f
+            })
+            .collect();
+
+        (format!("{title}\n\n{body}"), entities)
+    };
+
+    let id = hash_id(Platform::Rss, &format!("{url}#{}", entry.id));
+
+    SourcePost {
+        platform_id: time_id(date, id),
+        external_id: id.to_string(),
+        author_id: None,
+        text,
+        entities,
+        source_text: source_text.to_string(),
+        source_url: entry
+            .links
+            .first()
+            .map(|f| f.href.clone())
+            .unwrap_or_else(|| url.to_string()),
+        media: entry_media(&entry.media),
+        // entries without date have `0`
+        posted_at: Some(date).filter(|&f| f != 0),
+        reply_to: None,
+        quoted: None,
+        edit_of: None,
+    }
+}
+
+/// Text of feed text construct, converted from HTML if needed
+fn text_content(text: &Text) -> (String, Vec<PostEntity>) {
+    if text.content_type.subtype() == "html" {
+        html_to_text(&text.content)
+    } else {
+        (text.content.trim().to_string(), vec![])
+    }
+}
+
+/// Cuts `text` to `max_len` chars, dropping entities that don't fit
+fn truncate(
+    text: String,
+    mut entities: Vec<PostEntity>,
+    max_len: usize,
+) -> (String, Vec<PostEntity>) {
+    if text.chars().count() <= max_len {
+        return (text, entities);
+    }
+
+    let mut text = text.chars().take(max_len).collect::<String>();
+    text.truncate(text.trim_end().len());
+    let len = text.chars().count();
+    text.push('…');
+
+    entities.retain(|f| f.end <= len);
+
+    (text, entities)
+}
+
+/// Photos and videos of enclosures and `media:content`. Falls back to the first thumbnail
+/// if media object has no supported content (like youtube videos)
+fn entry_media(media: &[MediaObject]) -> Vec<SourceMedia> {
+    let mut res: Vec<SourceMedia> = vec![];
+
+    for object in media {
+        let mut content = object
+            .content
+            .iter()
+            .filter_map(|f| {
+                let url = f.url.as_ref()?.to_string();
+                let media_type = match f.content_type.as_ref()?.type_().as_str() {
+                    "image" => MediaType::Photo,
+                    "video" => MediaType::Video,
+                    _ => return None,
+                };
+
+                Some(SourceMedia {
+                    media_type,
+                    url,
+                    alt_text: None,
+                    width: f.width.map(|f| f as i32),
+                    height: f.height.map(|f| f as i32),
+                    duration_ms: f.duration.map(|f| f.as_millis() as i32),
+                })
+            })
+            .collect::<Vec<_>>();
+
+        if content.is_empty() {
+            content.extend(object.thumbnails.first().map(|f| SourceMedia {
+                media_type: MediaType::Photo,
+                url: f.image.uri.clone(),
+                alt_text: None,
+                width: f.image.width.map(|f| f as i32),
+                height: f.image.height.map(|f| f as i32),
+                duration_ms: None,
+            }));
+        }
+
+        for f in content {
+            if res.iter().all(|r| r.url != f.url) {
+                res.push(f);
+            }
+        }
+    }
+
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use entity::{author::Platform, post_media::MediaType};
+    use mockserver::fixture;
+
+    use super::{feed_user, hash_id, parse_timeline, INITIAL_ENTRIES};
+
+    const BLOG_URL: &str = "https://blog.example.com/rss.xml";
+    const YOUTUBE_URL: &str = "https://www.youtube.com/feeds/videos.xml?channel_id=UCexample";
+
+    #[test]
+    /// Check [`parse_timeline`] takes latest entries on the first fetch and
+    /// converts html, enclosures and `media:content`
+    fn test_parse_timeline_rss() {
+        let feed = feed_rs::parser::parse(fixture("feeds/blog.xml").as_bytes()).unwrap();
+        let user = feed_user(BLOG_URL, &feed);
+        assert_eq!(user.name, "Example Blog");
+        assert_eq!(user.username, BLOG_URL);
+        assert_eq!(
+            user.avatar_url.as_deref(),
+            Some("https://blog.example.com/logo.png")
+        );
+
+        let timeline = parse_timeline(BLOG_URL, feed, None);
+        assert_eq!(timeline.posts.len(), INITIAL_ENTRIES);
+        // 2022-11-26T10:00:00Z
+        assert_eq!(timeline.cursor.as_deref(), Some("1669456800"));
+        // platform ids follow dates of entries, identity is kept in hash of entry id
+        assert!(timeline
+            .posts
+            .windows(2)
+            .all(|f| f[0].platform_id < f[1].platform_id));
+
+        let post = timeline.posts.last().unwrap();
+        assert_eq!(post.platform_id >> 20, 1669456800);
+        assert_eq!(
+            post.external_id,
+            hash_id(
+                Platform::Rss,
+                &format!("{BLOG_URL}#https://blog.example.com/release-1-0")
+            )
+            .to_string()
+        );
+        assert_eq!(
+            post.text,
+            "Release 1.0\n\nWe released version 1.0, see changelog."
+        );
+        assert_eq!(post.source_text, "Example Blog");
+        assert_eq!(post.source_url, "https://blog.example.com/release-1-0");
+        assert_eq!(post.posted_at, Some(1669456800));
+        assert_eq!(post.entities.len(), 1);
+        assert_eq!(post.entities[0].url, "https://blog.example.com/changelog");
+        assert_eq!(post.entities[0].start, 42);
+        assert_eq!(post.entities[0].end, 51);
+
+        assert_eq!(post.media.len(), 2);
+        assert_eq!(post.media[0].media_type, MediaType::Photo);
+        assert_eq!(post.media[0].url, "https://blog.example.com/release.png");
+        assert_eq!(post.media[1].media_type, MediaType::Video);
+        assert_eq!(post.media[1].url, "https://blog.example.com/demo.mp4");
+
+        // entries are ordered oldest first
+        assert_eq!(timeline.posts[0].text, "Second post");
+    }
+
+    #[test]
+    /// Check [`parse_timeline`] returns only entries newer than cursor
+    fn test_parse_timeline_cursor() {
+        let feed = feed_rs::parser::parse(fixture("feeds/blog.xml").as_bytes()).unwrap();
+        // 2022-11-25T10:00:00Z
+        let timeline = parse_timeline(BLOG_URL, feed, Some("1669370400"));
+
+        assert_eq!(timeline.posts.len(), 1);
+        assert_eq!(timeline.cursor.as_deref(), Some("1669456800"));
+
+        let feed = feed_rs::parser::parse(fixture("feeds/blog.xml").as_bytes()).unwrap();
+        let timeline = parse_timeline(BLOG_URL, feed, Some("1669456800"));
+
+        assert!(timeline.posts.is_empty());
+        assert_eq!(timeline.cursor, None);
+    }
+
+    #[test]
+    /// Check [`parse_timeline`] uses thumbnails and descriptions of `media:group`
+    fn test_parse_timeline_atom() {
+        let feed = feed_rs::parser::parse(fixture("feeds/youtube.xml").as_bytes()).unwrap();
+        let timeline = parse_timeline(YOUTUBE_URL, feed, None);
+
+        assert_eq!(timeline.posts.len(), 1);
+
+        let post = &timeline.posts[0];
+        assert_eq!(post.text, "Rust in 100 seconds\n\nLearn Rust quickly.");
+        assert_eq!(post.source_text, "Example Channel");
+        assert_eq!(post.source_url, "https://www.youtube.com/watch?v=abc123");
+        assert_eq!(post.media.len(), 1);
+        assert_eq!(post.media[0].media_type, MediaType::Photo);
+        assert_eq!(
+            post.media[0].url,
+            "https://i.ytimg.com/vi/abc123/hqdefault.jpg"
+        );
+    }
+}
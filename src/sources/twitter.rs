@@ -0,0 +1,442 @@
+use std::fmt::Display;
+
+use entity::author::Platform;
+
+use super::{
+    Source, SourceError, SourceMedia, SourcePost, SourceTimeline, SourceUser, TimelineOptions,
+};
+use crate::models::twitterclient::{
+    snowflake_time, TwitterClient, TwitterError, TwitterTweet, TwitterUser, MAX_LOOKUP_IDS,
+};
+
+impl From<TwitterError> for SourceError {
+    fn from(e: TwitterError) -> Self {
+        match e {
+            TwitterError::NotFound(_) => SourceError::NotFound,
+            TwitterError::Suspended(_) => SourceError::Suspended,
+            TwitterError::Protected(_) => SourceError::Protected,
+            TwitterError::Unauthorized { .. } => SourceError::Unauthorized,
+            TwitterError::RateLimited { reset_at } => SourceError::RateLimited { reset_at },
+            e => SourceError::Other(Box::new(e)),
+        }
+    }
+}
+
+impl From<TwitterUser> for SourceUser {
+    fn from(user: TwitterUser) -> Self {
+        SourceUser {
+            platform_id: user.id,
+            external_id: user.id.to_string(),
+            name: user.name,
+            username: user.username,
+            avatar_url: user.profile_image_url,
+        }
+    }
+}
+
+#[rocket::async_trait]
+impl Source for TwitterClient {
+    fn platform(&self) -> Platform {
+        Platform::Twitter
+    }
+
+    fn owns_username(&self, username: &str) -> bool {
+        (1..=15).contains(&username.len())
+            && username
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_')
+    }
+
+    async fn fetch_user(&self, id: &str) -> Result<SourceUser, SourceError> {
+        let id = id.parse::<u64>().map_err(|_| SourceError::NotFound)?;
+
+        Ok(TwitterClient::fetch_user(self, id).await?.into())
+    }
+
+    async fn fetch_users(&self, ids: &[String]) -> Result<Vec<SourceUser>, SourceError> {
+        let ids = ids
+            .iter()
+            .flat_map(|f| f.parse::<u64>())
+            .collect::<Vec<_>>();
+
+        let mut users = vec![];
+        for chunk in ids.chunks(MAX_LOOKUP_IDS) {
+            let res = TwitterClient::fetch_users(self, chunk).await?;
+            users.extend(res.into_iter().map(SourceUser::from));
+        }
+
+        Ok(users)
+    }
+
+    async fn resolve_username(&self, username: &str) -> Result<SourceUser, SourceError> {
+        Ok(self.fetch_user_by_username(username).await?.into())
+    }
+
+    async fn fetch_timeline(
+        &self,
+        id: &str,
+        cursor: Option<&str>,
+        options: TimelineOptions,
+    ) -> Result<SourceTimeline, SourceError> {
+        let cursor = cursor.and_then(TimelineCursor::parse);
+        let last_id = cursor.as_ref().map(|f| f.last_id);
+        let token = cursor.as_ref().and_then(|f| f.token.as_deref());
+
+        let timeline =
+            TwitterClient::fetch_timeline(self, id, last_id, options.threads, token).await?;
+        let mut tweets = timeline.tweets;
+        tweets.sort_by_key(|f| f.id);
+
+        let newest_id = tweets
+            .last()
+            .map(|f| f.id)
+            .max(cursor.as_ref().map(|f| f.newest_id));
+        let cursor = match (last_id, newest_id, timeline.next_token) {
+            (Some(last_id), Some(newest_id), Some(token)) => Some(
+                TimelineCursor {
+                    last_id,
+                    newest_id,
+                    token: Some(token),
+                }
+                .to_string(),
+            ),
+            (_, newest_id, _) => newest_id.map(|f| f.to_string()),
+        };
+        let posts = tweets
+            .into_iter()
+            .map(|mut tweet| {
+                if !options.quotes {
+                    tweet.quoted = None;
+                }
+
+                tweet_post(tweet)
+            })
+            .collect();
+
+        Ok(SourceTimeline { posts, cursor })
+    }
+}
+
+/// Cursor of twitter timeline: ID of the newest collected tweet. If there were more new
+/// tweets than [`TwitterClient::max_pages`] pages, it is
+/// `{last_id}:{newest_id}:{token}`, and older tweets after `last_id` are fetched from
+/// page `token` before new ones
+struct TimelineCursor {
+    last_id: i64,
+    newest_id: i64,
+    token: Option<String>,
+}
+
+impl TimelineCursor {
+    fn parse(cursor: &str) -> Option<Self> {
+        let mut parts = cursor.splitn(3, ':');
+        let last_id = parts.next()?.parse().ok()?;
+        let newest_id = match parts.next() {
+            Some(id) => id.parse().ok()?,
+            None => last_id,
+        };
+
+        Some(Self {
+            last_id,
+            newest_id,
+            token: parts.next().map(str::to_string),
+        })
+    }
+}
+
+impl Display for TimelineCursor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.token {
+            Some(token) => write!(f, "{}:{}:{token}", self.last_id, self.newest_id),
+            None => write!(f, "{}", self.newest_id),
+        }
+    }
+}
+
+/// Converts tweet (with quoted tweet) to [`SourcePost`]
+pub fn tweet_post(tweet: TwitterTweet) -> SourcePost {
+    SourcePost {
+        platform_id: tweet.id,
+        external_id: tweet.id.to_string(),
+        author_id: Some(tweet.author_id.to_string()),
+        source_url: format!(
+            "https://twitter.com/{}/status/{}",
+            tweet.author_username, tweet.id
+        ),
+        text: tweet.text,
+        entities: tweet.entities,
+        source_text: tweet.author_name,
+        media: tweet
+            .media
+            .into_iter()
+            .map(|f| SourceMedia {
+                media_type: f.media_type,
+                url: f.url,
+                alt_text: f.alt_text,
+                width: f.width,
+                height: f.height,
+                duration_ms: f.duration_ms,
+            })
+            .collect(),
+        posted_at: Some(snowflake_time(tweet.id)),
+        reply_to: tweet.reply_to,
+        quoted: tweet.quoted.map(|f| Box::new(tweet_post(*f))),
+        edit_of: tweet
+            .edit_history
+            .first()
+            .copied()
+            .filter(|&f| f != tweet.id),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use entity::{post::EntityKind, post_media::MediaType};
+    use mockserver::{fixture, MockRoute, MockServer};
+
+    use crate::{
+        models::twitterclient::TwitterClient,
+        sources::{Source, SourceError, TimelineOptions},
+    };
+
+    async fn client(routes: Vec<MockRoute>) -> (MockServer, TwitterClient) {
+        let server = MockServer::start(routes).await;
+        let mut client = TwitterClient::new("token".to_string());
+        client.base_url = server.url.clone();
+
+        (server, client)
+    }
+
+    #[rocket::async_test]
+    /// Check [`TwitterClient::resolve_username`] and classification of user errors
+    async fn test_resolve_username() {
+        let (_server, client) = client(vec![
+            MockRoute::new(
+                "/2/users/by/username/jack",
+                200,
+                fixture("twitter/user.json"),
+            ),
+            MockRoute::new(
+                "/2/users/by/username/nobody",
+                200,
+                fixture("twitter/user_not_found.json"),
+            ),
+            MockRoute::new(
+                "/2/users/by/username/spam",
+                200,
+                fixture("twitter/user_suspended.json"),
+            ),
+            MockRoute::new(
+                "/2/users/by/username/secret",
+                401,
+                fixture("twitter/unauthorized.json"),
+            ),
+        ])
+        .await;
+
+        let user = Source::resolve_username(&client, "jack").await.unwrap();
+        assert_eq!(user.platform_id, 12);
+        assert_eq!(user.external_id, "12");
+        assert_eq!(user.username, "jack");
+
+        let res = Source::resolve_username(&client, "nobody").await;
+        assert!(matches!(res, Err(SourceError::NotFound)));
+        let res = Source::resolve_username(&client, "spam").await;
+        assert!(matches!(res, Err(SourceError::Suspended)));
+        let res = Source::resolve_username(&client, "secret").await;
+        assert!(matches!(res, Err(SourceError::Unauthorized)));
+    }
+
+    #[rocket::async_test]
+    /// Check [`TwitterClient::fetch_users`] skips users, that are not found
+    async fn test_fetch_users() {
+        let (server, client) = client(vec![MockRoute::new(
+            "/2/users?ids=12%2C13",
+            200,
+            fixture("twitter/users.json"),
+        )])
+        .await;
+
+        let users = Source::fetch_users(&client, &["12".to_string(), "13".to_string()])
+            .await
+            .unwrap();
+
+        assert_eq!(server.requests().len(), 1);
+        assert_eq!(users.len(), 1);
+        assert_eq!(users[0].username, "jack_renamed");
+    }
+
+    #[rocket::async_test]
+    /// Check [`TwitterClient::fetch_timeline`] follows pages until known tweet, keeps
+    /// self-replies and quotes and picks best video variants
+    async fn test_fetch_timeline() {
+        let (server, client) = client(vec![
+            MockRoute::new(
+                "/2/users/12/tweets?pagination_token=page2",
+                200,
+                fixture("twitter/timeline_page2.json"),
+            ),
+            MockRoute::new(
+                "/2/users/12/tweets?since_id=1000",
+                200,
+                fixture("twitter/timeline.json"),
+            ),
+        ])
+        .await;
+
+        let options = TimelineOptions {
+            threads: true,
+            quotes: true,
+        };
+        let timeline = Source::fetch_timeline(&client, "12", Some("1000"), options)
+            .await
+            .unwrap();
+
+        let requests = server.requests();
+        assert_eq!(requests.len(), 2);
+        assert!(
+            requests[0].contains("exclude=retweets&") || requests[0].ends_with("exclude=retweets")
+        );
+        assert_eq!(timeline.cursor.as_deref(), Some("1005"));
+
+        let ids = timeline
+            .posts
+            .iter()
+            .map(|f| f.platform_id)
+            .collect::<Vec<_>>();
+        // reply to other user and known tweet are skipped
+        assert_eq!(ids, [1001, 1003, 1004, 1005]);
+
+        let post = &timeline.posts[0];
+        assert_eq!(post.text, "hello @biz #rust");
+        assert_eq!(post.entities.len(), 2);
+        assert_eq!(post.entities[1].kind, EntityKind::Hashtag);
+        assert_eq!(post.source_url, "https://twitter.com/jack/status/1001");
+
+        let post = &timeline.posts[1];
+        assert_eq!(post.media.len(), 2);
+        assert_eq!(post.media[0].media_type, MediaType::Animation);
+        assert_eq!(post.media[1].media_type, MediaType::Video);
+        assert_eq!(
+            post.media[1].url,
+            "https://video.twimg.com/ext_tw_video/1003/pu/vid/1280x720/high.mp4"
+        );
+        assert_eq!(post.media[1].duration_ms, Some(12345));
+
+        let post = &timeline.posts[2];
+        assert_eq!(post.reply_to, Some(1003));
+        assert_eq!(post.text, "2/2 and a photo");
+        assert_eq!(post.media[0].alt_text.as_deref(), Some("A sunset"));
+
+        let post = &timeline.posts[3];
+        let quoted = post.quoted.as_ref().unwrap();
+        assert_eq!(quoted.platform_id, 900);
+        assert_eq!(quoted.source_url, "https://twitter.com/biz/status/900");
+        assert_eq!(quoted.source_text, "Biz Stone");
+    }
+
+    #[rocket::async_test]
+    /// Check [`TwitterClient::fetch_timeline`] stops at [`TwitterClient::max_pages`]
+    /// without skipping older new tweets, and fetches them on the next call
+    async fn test_fetch_timeline_max_pages() {
+        let (server, mut client) = client(vec![
+            MockRoute::new(
+                "/2/users/12/tweets?pagination_token=page2",
+                200,
+                fixture("twitter/timeline_page2.json"),
+            ),
+            MockRoute::new(
+                "/2/users/12/tweets?since_id=1000",
+                200,
+                fixture("twitter/timeline.json"),
+            ),
+        ])
+        .await;
+        client.max_pages = 1;
+
+        let options = TimelineOptions {
+            threads: true,
+            quotes: true,
+        };
+        let timeline = Source::fetch_timeline(&client, "12", Some("1000"), options)
+            .await
+            .unwrap();
+        assert_eq!(server.requests().len(), 1);
+        assert_eq!(timeline.posts.len(), 3);
+        assert_eq!(timeline.cursor.as_deref(), Some("1000:1005:page2"));
+
+        // older page is fetched from token, still after the old cursor
+        let timeline = Source::fetch_timeline(&client, "12", timeline.cursor.as_deref(), options)
+            .await
+            .unwrap();
+        let requests = server.requests();
+        assert_eq!(requests.len(), 2);
+        assert!(requests[1].contains("since_id=1000"));
+        let ids = timeline
+            .posts
+            .iter()
+            .map(|f| f.platform_id)
+            .collect::<Vec<_>>();
+        assert_eq!(ids, [1001]);
+        assert_eq!(timeline.cursor.as_deref(), Some("1005"));
+    }
+
+    #[rocket::async_test]
+    /// Check [`TwitterClient::fetch_timeline`] takes [`TwitterClient::backfill`] latest
+    /// tweets of new author
+    async fn test_fetch_timeline_backfill() {
+        let (server, mut client) = client(vec![MockRoute::new(
+            "/2/users/12/tweets",
+            200,
+            fixture("twitter/timeline.json"),
+        )])
+        .await;
+        client.backfill = 2;
+
+        let options = TimelineOptions {
+            threads: true,
+            quotes: false,
+        };
+        let timeline = Source::fetch_timeline(&client, "12", None, options)
+            .await
+            .unwrap();
+
+        let requests = server.requests();
+        assert_eq!(requests.len(), 1);
+        assert!(!requests[0].contains("since_id"));
+        assert!(requests[0].contains("max_results=5"));
+        let ids = timeline
+            .posts
+            .iter()
+            .map(|f| f.platform_id)
+            .collect::<Vec<_>>();
+        assert_eq!(ids, [1004, 1005]);
+        assert!(timeline.posts[1].quoted.is_none());
+        assert_eq!(timeline.cursor.as_deref(), Some("1005"));
+    }
+
+    #[rocket::async_test]
+    /// Check exhausted rate limit is reported and remembered until reset
+    async fn test_rate_limit() {
+        let (server, client) = client(vec![MockRoute::new(
+            "/2/users/12/tweets",
+            429,
+            fixture("twitter/rate_limited.json"),
+        )
+        .header("x-rate-limit-reset", 4102444800i64)])
+        .await;
+
+        for _ in 0..2 {
+            let res = Source::fetch_timeline(&client, "12", None, TimelineOptions::default()).await;
+            assert!(matches!(
+                res,
+                Err(SourceError::RateLimited {
+                    reset_at: 4102444800
+                })
+            ));
+        }
+
+        // second request is not sent at all
+        assert_eq!(server.requests().len(), 1);
+    }
+}
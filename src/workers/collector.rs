@@ -0,0 +1,746 @@
+use std::{collections::HashMap, time::Duration};
+
+use entity::{
+    author::Platform,
+    post::{PostEditHistory, PostEntities, PostVersion},
+    scheduled_post::PostStatus,
+};
+use migration::Condition;
+use rocket::tokio::time::sleep;
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter, QueryOrder, Set,
+};
+
+use crate::{
+    models::time::unix_now,
+    sources::{SourceError, SourceMedia, SourcePost, Sources, TimelineOptions},
+};
+
+/// Delay between checks for authors to poll
+const TICK: Duration = Duration::from_secs(10);
+/// Minimum delay between timeline fetches of one author in seconds
+const MIN_POLL_INTERVAL: i32 = 60;
+/// Maximum delay between timeline fetches of one author in seconds
+const MAX_POLL_INTERVAL: i32 = 60 * 60;
+
+pub async fn start_collector(db: &DatabaseConnection, sources: &Sources) {
+    // platforms with exhausted rate limit and time of reset
+    let mut paused = HashMap::new();
+
+    loop {
+        collect_due(db, sources, &mut paused).await;
+
+        sleep(TICK).await;
+    }
+}
+
+/// Fetches timelines of authors, that are due to poll. Platforms in `paused` are skipped
+/// until their rate limit resets
+async fn collect_due(
+    db: &DatabaseConnection,
+    sources: &Sources,
+    paused: &mut HashMap<Platform, i64>,
+) {
+    let is_due = Condition::any()
+        .add(entity::author::Column::NextPollAt.is_null())
+        .add(entity::author::Column::NextPollAt.lte(unix_now()));
+
+    // authors that wait longest go first
+    let authors = entity::author::Entity::find()
+        .filter(is_due)
+        .order_by_asc(entity::author::Column::NextPollAt)
+        .all(db)
+        .await
+        .unwrap();
+
+    for author in authors {
+        if matches!(paused.get(&author.platform), Some(&until) if until > unix_now()) {
+            continue;
+        }
+
+        let Some(source) = sources.get(author.platform) else {
+            eprintln!(
+                "Warning: no source registered for {:?}, skipping author {}",
+                author.platform, author.id
+            );
+
+            continue;
+        };
+
+        let mut active = entity::author::ActiveModel {
+            id: Set(author.id),
+            ..Default::default()
+        };
+
+        let options = TimelineOptions {
+            threads: author.collect_threads,
+            quotes: author.collect_quotes,
+        };
+
+        let timeline = match source
+            .fetch_timeline(&author.external_id, author.cursor.as_deref(), options)
+            .await
+        {
+            Ok(r) => r,
+            Err(SourceError::RateLimited { reset_at }) => {
+                eprintln!(
+                    "Warning: {:?} rate limit exhausted, pausing until {reset_at}",
+                    author.platform
+                );
+                paused.insert(author.platform, reset_at);
+
+                continue;
+            }
+            Err(e) => {
+                eprintln!(
+                    "Warning: fetch_timeline of {:?} author {} failed: {e}",
+                    author.platform, author.external_id
+                );
+
+                // don't retry broken author on every tick
+                active.next_poll_at = Set(Some(unix_now() + author.poll_interval as i64));
+                active.update(db).await.unwrap();
+
+                continue;
+            }
+        };
+
+        let interval = next_poll_interval(author.poll_interval, timeline.posts.len());
+
+        for post in timeline.posts {
+            store_post(db, &author, post).await;
+        }
+
+        if let Some(cursor) = timeline.cursor {
+            active.cursor = Set(Some(cursor));
+        }
+        active.poll_interval = Set(interval);
+        active.next_poll_at = Set(Some(unix_now() + interval as i64));
+        active.update(db).await.unwrap();
+    }
+}
+
+/// Stores collected post of `author` with its quoted post and links it to previous post
+/// of thread, if that post is known
+async fn store_post(db: &DatabaseConnection, author: &entity::author::Model, mut post: SourcePost) {
+    // edited posts are new posts on some platforms
+    if let Some(original) = post.edit_of {
+        let original = entity::post::Entity::find()
+            .filter(entity::post::Column::Platform.eq(author.platform))
+            .filter(entity::post::Column::PlatformId.eq(original))
+            .one(db)
+            .await
+            .unwrap();
+
+        if let Some(original) = original {
+            store_edit(db, original, post).await;
+            return;
+        }
+    }
+
+    let quoted_post_id = match post.quoted.take() {
+        Some(quoted) => Some(store_quoted(db, author, *quoted).await),
+        None => None,
+    };
+    let parent_post_id = match post.reply_to {
+        Some(id) => entity::post::Entity::find()
+            .filter(entity::post::Column::Platform.eq(author.platform))
+            .filter(entity::post::Column::PlatformId.eq(id))
+            .one(db)
+            .await
+            .unwrap()
+            .map(|f| f.id),
+        None => None,
+    };
+
+    insert_post(db, author, post, parent_post_id, quoted_post_id, false).await;
+}
+
+/// Stores post quoted by post of `author` with its own author and returns its internal
+/// ID. If author of quoted post is not tracked, post is stored with `author` as
+/// `quoted_only`
+async fn store_quoted(
+    db: &DatabaseConnection,
+    author: &entity::author::Model,
+    quoted: SourcePost,
+) -> i64 {
+    let quoted_author = match &quoted.author_id {
+        Some(id) if *id != author.external_id => entity::author::Entity::find()
+            .filter(entity::author::Column::Platform.eq(author.platform))
+            .filter(entity::author::Column::ExternalId.eq(id.as_str()))
+            .one(db)
+            .await
+            .unwrap(),
+        _ => return insert_post(db, author, quoted, None, None, false).await,
+    };
+
+    match quoted_author {
+        Some(quoted_author) => insert_post(db, &quoted_author, quoted, None, None, false).await,
+        None => insert_post(db, author, quoted, None, None, true).await,
+    }
+}
+
+/// Inserts post with its media and returns its internal ID. Already stored posts (like
+/// quoted posts of tracked authors) are left as is, except `quoted_only` posts, that are
+/// moved to their collected author
+async fn insert_post(
+    db: &DatabaseConnection,
+    author: &entity::author::Model,
+    post: SourcePost,
+    parent_post_id: Option<i64>,
+    quoted_post_id: Option<i64>,
+    quoted_only: bool,
+) -> i64 {
+    if let Some(stored) = find_post(db, author.platform, &post.external_id).await {
+        if !stored.quoted_only || quoted_only {
+            return stored.id;
+        }
+
+        let mut active: entity::post::ActiveModel = stored.into();
+        active.author_id = Set(author.id);
+        active.quoted_only = Set(false);
+        if parent_post_id.is_some() {
+            active.parent_post_id = Set(parent_post_id);
+        }
+        if quoted_post_id.is_some() {
+            active.quoted_post_id = Set(quoted_post_id);
+        }
+
+        return active.update(db).await.unwrap().id;
+    }
+
+    let active = entity::post::ActiveModel {
+        platform_id: Set(post.platform_id),
+        external_id: Set(post.external_id),
+        platform: Set(author.platform),
+        author_id: Set(author.id),
+        parent_post_id: Set(parent_post_id),
+        quoted_post_id: Set(quoted_post_id),
+        quoted_only: Set(quoted_only),
+        text: Set(post.text),
+        entities: Set(PostEntities(post.entities)),
+        source_text: Set(post.source_text),
+        source_url: Set(post.source_url),
+        // listings are ordered by publication time
+        posted_at: Set(Some(post.posted_at.unwrap_or_else(unix_now))),
+        ..Default::default()
+    };
+    let model = active.insert(db).await.unwrap();
+
+    insert_media(db, model.id, post.media).await;
+
+    model.id
+}
+
+/// Links `media` to post with id `post_id`. Media with the same url (see
+/// [`entity::post_media::url_key`]) is stored once and shared by posts
+async fn insert_media(db: &DatabaseConnection, post_id: i64, media: Vec<SourceMedia>) {
+    let mut links = vec![];
+    for media in media {
+        let url_key = entity::post_media::url_key(&media.url);
+        let existing = entity::post_media::Entity::find()
+            .filter(entity::post_media::Column::UrlKey.eq(url_key.as_str()))
+            .one(db)
+            .await
+            .unwrap();
+        let media_id = match existing {
+            Some(m) => m.id,
+            None => {
+                let active = entity::post_media::ActiveModel {
+                    url_key: Set(url_key),
+                    media_type: Set(media.media_type),
+                    media_url: Set(media.url),
+                    alt_text: Set(media.alt_text),
+                    width: Set(media.width),
+                    height: Set(media.height),
+                    duration_ms: Set(media.duration_ms),
+                    ..Default::default()
+                };
+                active.insert(db).await.unwrap().id
+            }
+        };
+
+        // post may contain the same media twice
+        if !links.contains(&media_id) {
+            links.push(media_id);
+        }
+    }
+
+    let links = links
+        .into_iter()
+        .enumerate()
+        .map(
+            |(position, media_id)| entity::post_media_link::ActiveModel {
+                post_id: Set(post_id),
+                media_id: Set(media_id),
+                position: Set(position as i32),
+            },
+        )
+        .collect::<Vec<_>>();
+
+    if !links.is_empty() {
+        entity::post_media_link::Entity::insert_many(links)
+            .exec(db)
+            .await
+            .unwrap();
+    }
+}
+
+/// Replaces text of `post` with its newer `version`, keeping the previous one in edit
+/// history. Pending scheduled posts with unchanged text are updated too
+pub(super) async fn store_edit(
+    db: &DatabaseConnection,
+    post: entity::post::Model,
+    version: SourcePost,
+) {
+    let current_id = post.version_id.unwrap_or(post.platform_id);
+    if version.platform_id <= current_id {
+        return;
+    }
+
+    let scheduled = entity::scheduled_post::Entity::find()
+        .filter(entity::scheduled_post::Column::PostId.eq(post.id))
+        .filter(entity::scheduled_post::Column::Status.eq(PostStatus::Pending))
+        .filter(entity::scheduled_post::Column::PostText.eq(post.text.as_str()))
+        .all(db)
+        .await
+        .unwrap();
+
+    for scheduled in scheduled {
+        let mut active: entity::scheduled_post::ActiveModel = scheduled.into();
+        active.post_text = Set(version.text.clone());
+        active.post_entities = Set(PostEntities(version.entities.clone()));
+        active.update(db).await.unwrap();
+    }
+
+    let mut history = post.edit_history.0.clone();
+    history.push(PostVersion {
+        platform_id: current_id,
+        text: post.text.clone(),
+        entities: post.entities.0.clone(),
+    });
+
+    let mut active: entity::post::ActiveModel = post.into();
+    active.text = Set(version.text);
+    active.entities = Set(PostEntities(version.entities));
+    active.version_id = Set(Some(version.platform_id));
+    active.edit_history = Set(PostEditHistory(history));
+    active.update(db).await.unwrap();
+}
+
+/// Stored post of `platform` with platform-native `external_id`
+async fn find_post(
+    db: &DatabaseConnection,
+    platform: Platform,
+    external_id: &str,
+) -> Option<entity::post::Model> {
+    entity::post::Entity::find()
+        .filter(entity::post::Column::Platform.eq(platform))
+        .filter(entity::post::Column::ExternalId.eq(external_id))
+        .one(db)
+        .await
+        .unwrap()
+}
+
+/// Next poll interval of author: halves if author posted something since last poll,
+/// otherwise grows by half
+fn next_poll_interval(interval: i32, new_posts: usize) -> i32 {
+    let interval = if new_posts > 0 {
+        interval / 2
+    } else {
+        interval + interval / 2
+    };
+
+    interval.clamp(MIN_POLL_INTERVAL, MAX_POLL_INTERVAL)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use entity::author::Platform;
+    use entity::post_media::MediaType;
+    use migration::{Migrator, MigratorTrait};
+    use mockserver::{fixture, MockRoute, MockServer};
+    use sea_orm::{
+        ActiveModelTrait, ColumnTrait, Database, EntityTrait, ModelTrait, QueryFilter, QueryOrder,
+        Set,
+    };
+
+    use super::{
+        collect_due, insert_post, next_poll_interval, store_post, MAX_POLL_INTERVAL,
+        MIN_POLL_INTERVAL,
+    };
+    use crate::{
+        models::{
+            feedclient::FeedClient,
+            postfilter::{find_posts, PostFilter},
+            time::unix_now,
+            twitterclient::TwitterClient,
+        },
+        sources::{SourceMedia, SourcePost, Sources},
+    };
+
+    /// Database with single twitter author `jack`, collecting threads and quotes
+    async fn database() -> (sea_orm::DatabaseConnection, entity::author::Model) {
+        let db = Database::connect("sqlite::memory:").await.unwrap();
+        Migrator::up(&db, None).await.unwrap();
+
+        let author = entity::author::ActiveModel {
+            platform_id: Set(12),
+            platform: Set(Platform::Twitter),
+            external_id: Set("12".to_string()),
+            cursor: Set(Some("1000".to_string())),
+            collect_threads: Set(true),
+            collect_quotes: Set(true),
+            name: Set("jack".to_string()),
+            username: Set("jack".to_string()),
+            ..Default::default()
+        }
+        .insert(&db)
+        .await
+        .unwrap();
+
+        (db, author)
+    }
+
+    fn sources(server: &MockServer) -> Sources {
+        let mut twitter = TwitterClient::new("token".to_string());
+        twitter.base_url = server.url.clone();
+
+        let mut sources = Sources::default();
+        sources.register(twitter);
+
+        sources
+    }
+
+    #[rocket::async_test]
+    /// Check [`collect_due`] stores new tweets with media, threads and quotes and moves cursor
+    async fn test_collect_due() {
+        let (db, author) = database().await;
+        let server = MockServer::start(vec![
+            MockRoute::new(
+                "/2/users/12/tweets?pagination_token=page2",
+                200,
+                fixture("twitter/timeline_page2.json"),
+            ),
+            MockRoute::new("/2/users/12/tweets", 200, fixture("twitter/timeline.json")),
+        ])
+        .await;
+        let sources = sources(&server);
+
+        let mut paused = HashMap::new();
+        collect_due(&db, &sources, &mut paused).await;
+
+        let posts = entity::post::Entity::find()
+            .all(&db)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|f| (f.platform_id, f))
+            .collect::<HashMap<_, _>>();
+
+        let mut ids = posts.keys().copied().collect::<Vec<_>>();
+        ids.sort();
+        assert_eq!(ids, [900, 1001, 1003, 1004, 1005]);
+        assert!(posts.values().all(|f| f.author_id == author.id));
+        // quoted tweet of biz, who is not tracked
+        assert!(posts[&900].quoted_only);
+        assert!(posts
+            .values()
+            .filter(|f| f.platform_id != 900)
+            .all(|f| !f.quoted_only));
+        assert_eq!(posts[&1004].parent_post_id, Some(posts[&1003].id));
+        assert_eq!(posts[&1005].quoted_post_id, Some(posts[&900].id));
+
+        let listed = find_posts(
+            &db,
+            author.find_related(entity::post::Entity),
+            &PostFilter::default(),
+        )
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|(f, _)| f.platform_id)
+        .collect::<Vec<_>>();
+        assert_eq!(listed, [1001, 1003, 1004, 1005]);
+
+        let media = posts[&1003]
+            .find_related(entity::post_media::Entity)
+            .all(&db)
+            .await
+            .unwrap();
+        assert_eq!(media.len(), 2);
+
+        let author = entity::author::Entity::find_by_id(author.id)
+            .one(&db)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(author.cursor.as_deref(), Some("1005"));
+        assert_eq!(author.poll_interval, next_poll_interval(120, 4));
+        assert!(author.next_poll_at.is_some());
+        assert!(paused.is_empty());
+
+        // author is not due anymore
+        collect_due(&db, &sources, &mut paused).await;
+        assert_eq!(server.requests().len(), 2);
+    }
+
+    #[rocket::async_test]
+    /// Check [`collect_due`] stores quoted tweet of tracked author with that author, and
+    /// moves quoted-only tweet to its author, once it is collected from its timeline
+    async fn test_collect_due_quoted_author() {
+        let (db, _) = database().await;
+        let server = MockServer::start(vec![
+            MockRoute::new(
+                "/2/users/12/tweets?pagination_token=page2",
+                200,
+                fixture("twitter/timeline_page2.json"),
+            ),
+            MockRoute::new("/2/users/12/tweets", 200, fixture("twitter/timeline.json")),
+        ])
+        .await;
+        let sources = sources(&server);
+        let biz = entity::author::ActiveModel {
+            platform_id: Set(99),
+            platform: Set(Platform::Twitter),
+            external_id: Set("99".to_string()),
+            name: Set("Biz Stone".to_string()),
+            username: Set("biz".to_string()),
+            next_poll_at: Set(Some(unix_now() + 3600)),
+            ..Default::default()
+        }
+        .insert(&db)
+        .await
+        .unwrap();
+
+        collect_due(&db, &sources, &mut HashMap::new()).await;
+
+        let quoted = entity::post::Entity::find()
+            .filter(entity::post::Column::PlatformId.eq(900))
+            .one(&db)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(quoted.author_id, biz.id);
+        assert!(!quoted.quoted_only);
+
+        // quoted-only tweet is collected from timeline of its author
+        let (db, jack) = database().await;
+        collect_due(&db, &sources, &mut HashMap::new()).await;
+        let biz = entity::author::ActiveModel {
+            platform_id: Set(99),
+            platform: Set(Platform::Twitter),
+            external_id: Set("99".to_string()),
+            name: Set("Biz Stone".to_string()),
+            username: Set("biz".to_string()),
+            ..Default::default()
+        }
+        .insert(&db)
+        .await
+        .unwrap();
+        store_post(&db, &biz, source_post(900, &[])).await;
+
+        let posts = entity::post::Entity::find()
+            .filter(entity::post::Column::PlatformId.eq(900))
+            .all(&db)
+            .await
+            .unwrap();
+        assert_eq!(posts.len(), 1);
+        assert_eq!(posts[0].author_id, biz.id);
+        assert_ne!(posts[0].author_id, jack.id);
+        assert!(!posts[0].quoted_only);
+    }
+
+    #[rocket::async_test]
+    /// Check [`collect_due`] fetches every author from source of its platform and skips
+    /// authors of platforms without registered source
+    async fn test_collect_due_sources() {
+        let (db, twitter_author) = database().await;
+        let server = MockServer::start(vec![
+            MockRoute::new("/2/users/12/tweets", 200, fixture("twitter/timeline.json")),
+            MockRoute::new("/blog.xml", 200, fixture("feeds/blog.xml")),
+        ])
+        .await;
+        let mut sources = sources(&server);
+        sources.register(FeedClient::new());
+
+        let feed_url = format!("{}/blog.xml", server.url);
+        let mut authors = vec![];
+        for (platform, external_id) in [
+            (Platform::Rss, feed_url.as_str()),
+            (Platform::Mastodon, "109302"),
+        ] {
+            let author = entity::author::ActiveModel {
+                platform_id: Set(authors.len() as i64 + 100),
+                platform: Set(platform),
+                external_id: Set(external_id.to_string()),
+                name: Set(format!("{platform:?}")),
+                username: Set(external_id.to_string()),
+                ..Default::default()
+            }
+            .insert(&db)
+            .await
+            .unwrap();
+            authors.push(author);
+        }
+
+        let mut paused = HashMap::new();
+        collect_due(&db, &sources, &mut paused).await;
+
+        let posts = entity::post::Entity::find().all(&db).await.unwrap();
+        let feed_posts = posts
+            .iter()
+            .filter(|f| f.author_id == authors[0].id)
+            .collect::<Vec<_>>();
+        // latest entries of new feed
+        assert_eq!(feed_posts.len(), 5);
+        assert!(feed_posts.iter().all(|f| f.platform == Platform::Rss));
+        assert!(posts
+            .iter()
+            .filter(|f| f.author_id == twitter_author.id)
+            .all(|f| f.platform == Platform::Twitter));
+        assert!(posts.iter().all(|f| f.author_id != authors[1].id));
+
+        let release = feed_posts
+            .iter()
+            .find(|f| f.source_url == "https://blog.example.com/release-1-0")
+            .unwrap();
+        let media = release
+            .find_related(entity::post_media::Entity)
+            .all(&db)
+            .await
+            .unwrap();
+        assert_eq!(media.len(), 2);
+
+        let reload = |id| entity::author::Entity::find_by_id(id).one(&db);
+        let feed_author = reload(authors[0].id).await.unwrap().unwrap();
+        assert_eq!(feed_author.cursor.as_deref(), Some("1669456800"));
+        assert!(feed_author.next_poll_at.is_some());
+        let twitter_author = reload(twitter_author.id).await.unwrap().unwrap();
+        assert!(twitter_author.next_poll_at.is_some());
+        // no mastodon source, so author stays due
+        let mastodon_author = reload(authors[1].id).await.unwrap().unwrap();
+        assert_eq!(mastodon_author.next_poll_at, None);
+    }
+
+    /// Post with photos by `urls`
+    fn source_post(platform_id: i64, urls: &[&str]) -> SourcePost {
+        SourcePost {
+            platform_id,
+            external_id: platform_id.to_string(),
+            author_id: None,
+            text: "photos".to_string(),
+            entities: vec![],
+            source_text: "jack".to_string(),
+            source_url: format!("https://twitter.com/jack/status/{platform_id}"),
+            media: urls
+                .iter()
+                .map(|url| SourceMedia {
+                    media_type: MediaType::Photo,
+                    url: url.to_string(),
+                    alt_text: None,
+                    width: None,
+                    height: None,
+                    duration_ms: None,
+                })
+                .collect(),
+            posted_at: None,
+            reply_to: None,
+            quoted: None,
+            edit_of: None,
+        }
+    }
+
+    #[rocket::async_test]
+    /// Check [`insert_post`] stores media with the same content once and keeps its order
+    /// in every post
+    async fn test_insert_post_shares_media() {
+        let (db, author) = database().await;
+
+        let first = source_post(
+            2001,
+            &[
+                "https://pbs.twimg.com/media/a.jpg?name=small",
+                "https://pbs.twimg.com/media/b.jpg",
+            ],
+        );
+        let second = source_post(
+            2002,
+            &[
+                "https://pbs.twimg.com/media/c.jpg",
+                "https://pbs.twimg.com/media/b.jpg?name=orig",
+                "https://pbs.twimg.com/media/c.jpg",
+            ],
+        );
+        insert_post(&db, &author, first, None, None, false).await;
+        let id = insert_post(&db, &author, second, None, None, false).await;
+
+        let media = entity::post_media::Entity::find().all(&db).await.unwrap();
+        assert_eq!(media.len(), 3);
+
+        let post = entity::post::Entity::find_by_id(id)
+            .one(&db)
+            .await
+            .unwrap()
+            .unwrap();
+        let urls = post
+            .find_related(entity::post_media::Entity)
+            .order_by_asc(entity::post_media_link::Column::Position)
+            .all(&db)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|f| f.media_url)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            urls,
+            [
+                "https://pbs.twimg.com/media/c.jpg",
+                "https://pbs.twimg.com/media/b.jpg",
+            ]
+        );
+    }
+
+    #[rocket::async_test]
+    /// Check [`collect_due`] pauses platform with exhausted rate limit
+    async fn test_collect_due_rate_limited() {
+        let (db, author) = database().await;
+        let server = MockServer::start(vec![MockRoute::new(
+            "/2/users/12/tweets",
+            429,
+            fixture("twitter/rate_limited.json"),
+        )
+        .header("x-rate-limit-reset", 4102444800i64)])
+        .await;
+        let sources = sources(&server);
+
+        let mut paused = HashMap::new();
+        collect_due(&db, &sources, &mut paused).await;
+
+        assert_eq!(paused.get(&Platform::Twitter), Some(&4102444800));
+
+        let author = entity::author::Entity::find_by_id(author.id)
+            .one(&db)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(author.cursor.as_deref(), Some("1000"));
+        assert_eq!(author.next_poll_at, None);
+        assert!(entity::post::Entity::find()
+            .all(&db)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    /// Check [`next_poll_interval`] adapts to posting frequency and stays in bounds
+    fn test_next_poll_interval() {
+        assert_eq!(next_poll_interval(120, 0), 180);
+        assert_eq!(next_poll_interval(180, 3), 90);
+        assert_eq!(next_poll_interval(90, 1), MIN_POLL_INTERVAL);
+        assert_eq!(next_poll_interval(MAX_POLL_INTERVAL, 0), MAX_POLL_INTERVAL);
+    }
+}
@@ -1,2 +1,4 @@
+pub mod collector;
 pub mod posting;
-pub mod twitter;
+pub mod profiles;
+pub mod revalidation;
@@ -1,127 +1,1412 @@
-use std::ops::Deref;
 use std::time::Duration;
 
-use entity::post_media::MediaType;
-use migration::Condition;
+use entity::{post_media::MediaType, scheduled_post::PostStatus};
+use itertools::Itertools;
+use migration::{Condition, Expr, Query};
 use reqwest::Url;
 use rocket::tokio::time::sleep;
-use sea_orm::{ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter};
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter, QueryOrder, Set,
+};
+use sha2::{Digest, Sha256};
 use telegrambot::teloxide::{
-    payloads::SendMessageSetters,
+    payloads::{SendAnimationSetters, SendMediaGroupSetters, SendMessageSetters},
     requests::Requester,
-    types::{ChatId, InputFile, InputMedia, InputMediaPhoto, InputMediaVideo, ParseMode},
+    types::{
+        ChatId, InputFile, InputMedia, InputMediaAnimation, InputMediaPhoto, InputMediaVideo,
+        MessageId, ParseMode,
+    },
     Bot, RequestError,
 };
 
-fn escape_html(text: &str) -> String {
-    let mut buff = String::with_capacity(text.len());
+use crate::models::{
+    postformat::{
+        format_post, post_length, split_post, MAX_CAPTION_LENGTH, MAX_MEDIA_GROUP_SIZE,
+        MAX_TEXT_LENGTH,
+    },
+    time::unix_now,
+};
 
-    for c in text.chars() {
-        match c {
-            '<' => buff.push_str("&lt;"),
-            '>' => buff.push_str("&gt;"),
-            '&' => buff.push_str("&amp;"),
-            _ => buff.push(c),
-        }
-    }
+/// Maximum number of posting attempts before post marked as failed
+const MAX_ATTEMPTS: i32 = 5;
+/// Delay before first retry, doubled on every next attempt
+const RETRY_BASE_DELAY: i64 = 60;
+
+/// Telegram limit for files uploaded by bots
+const MAX_UPLOAD_SIZE: usize = 50 * 1024 * 1024;
+/// Appended to post text when some media was dropped because of upload limit
+const MEDIA_TOO_LARGE_NOTE: &str = "⚠️ Some media is too large for Telegram, see it in source";
 
-    buff
+/// Error of thread posts, that can't be sent as replies, since previous post of thread
+/// failed or was held
+pub const THREAD_STOPPED_NOTE: &str = "Previous post of thread was not sent";
+
+/// Options of posting worker
+#[derive(Clone, Copy)]
+pub struct PostingOptions {
+    /// Download media and upload it to telegram, instead of passing urls
+    pub upload_media: bool,
+    /// Append alternative texts of media to post text
+    pub show_alt_text: bool,
+}
+
+/// Telegram messages of successfully sent post
+struct SentPost {
+    message_ids: Vec<i32>,
+    text_message_id: i32,
+    text_in_caption: bool,
 }
 
-pub async fn start_posting_worker(db: &DatabaseConnection, bot: &Bot) {
+/// Starts posting worker
+pub async fn start_posting_worker(db: &DatabaseConnection, bot: &Bot, options: PostingOptions) {
     loop {
-        let posts = entity::scheduled_post::Entity::find()
-            .find_also_related(entity::telegram_user::Entity)
-            .all(db.deref())
-            .await
-            .unwrap()
-            .into_iter()
-            .filter(|f| f.1.is_some())
-            .map(|f| (f.0, f.1.unwrap()))
-            .filter(|f| f.1.channel.is_some());
-
-        let mut del_cond = Condition::any();
-
-        for (post, user) in posts {
-            let chat_id = ChatId(user.channel.unwrap());
-
-            let text = format!(
-                "{}\n\n<b><a href=\"{}\">{}</a></b>",
-                escape_html(&post.post_text),
-                post.post_source_url,
-                escape_html(&post.post_source)
-            );
-
-            let media_ids = post
-                .media_ids
-                .split(',')
-                .flat_map(|f| f.parse::<i64>())
-                .collect::<Vec<_>>();
-
-            let err = if !media_ids.is_empty() {
-                let mut cond = Condition::any();
-                for media in media_ids {
-                    cond = cond.add(entity::post_media::Column::Id.eq(media));
+        hash_pending_media(db).await;
+        publish_due(db, bot, options).await;
+
+        sleep(Duration::from_secs(120)).await;
+    }
+}
+
+/// Downloads media of pending posts, that wasn't hashed yet, and stores hash of its
+/// content. Posts with media, that turns out to be already published in their channel
+/// under other url, are marked as duplicate
+async fn hash_pending_media(db: &DatabaseConnection) {
+    let posts = entity::scheduled_post::Entity::find()
+        .filter(entity::scheduled_post::Column::Status.eq(PostStatus::Pending))
+        .all(db)
+        .await
+        .unwrap();
+
+    for post in posts {
+        let media = post.find_media().all(db).await.unwrap();
+
+        let mut hashed = false;
+        for f in media.iter().filter(|f| f.content_hash.is_none()) {
+            let hash = match hash_media(&f.media_url).await {
+                Ok(hash) => hash,
+                Err(e) => {
+                    eprintln!("Failed to hash media {}: {e}", f.media_url);
+                    continue;
                 }
-                let mut media = entity::post_media::Entity::find()
-                    .filter(cond)
-                    .all(db.deref())
+            };
+
+            entity::post_media::ActiveModel {
+                id: Set(f.id),
+                content_hash: Set(Some(hash)),
+                ..Default::default()
+            }
+            .update(db)
+            .await
+            .unwrap();
+            hashed = true;
+        }
+
+        let media_ids = media.iter().map(|f| f.id).collect::<Vec<_>>();
+        if hashed && !post.duplicate && is_duplicate(db, post.user_id, &media_ids).await {
+            entity::scheduled_post::ActiveModel {
+                id: Set(post.id),
+                duplicate: Set(true),
+                ..Default::default()
+            }
+            .update(db)
+            .await
+            .unwrap();
+        }
+    }
+}
+
+/// Checks whether channel of user already published any of `media_ids`, or the same
+/// files under other urls (by content hash)
+pub async fn is_duplicate(db: &DatabaseConnection, user_id: i64, media_ids: &[i64]) -> bool {
+    if media_ids.is_empty() {
+        return false;
+    }
+
+    let channel = entity::telegram_user::Entity::find_by_id(user_id)
+        .one(db)
+        .await
+        .unwrap()
+        .and_then(|f| f.channel);
+    let Some(channel) = channel else {
+        return false;
+    };
+
+    let hashes = Query::select()
+        .column(entity::post_media::Column::ContentHash)
+        .from(entity::post_media::Entity)
+        .and_where(entity::post_media::Column::Id.is_in(media_ids.to_vec()))
+        .and_where(entity::post_media::Column::ContentHash.is_not_null())
+        .to_owned();
+    let same_media = Query::select()
+        .column(entity::post_media::Column::Id)
+        .from(entity::post_media::Entity)
+        .cond_where(
+            Condition::any()
+                .add(entity::post_media::Column::Id.is_in(media_ids.to_vec()))
+                .add(entity::post_media::Column::ContentHash.in_subquery(hashes)),
+        )
+        .to_owned();
+    let scheduled_ids = Query::select()
+        .column(entity::scheduled_post_media::Column::ScheduledPostId)
+        .from(entity::scheduled_post_media::Entity)
+        .and_where(entity::scheduled_post_media::Column::MediaId.in_subquery(same_media))
+        .to_owned();
+
+    entity::published_post::Entity::find()
+        .filter(entity::published_post::Column::ChatId.eq(channel))
+        .filter(entity::published_post::Column::ScheduledPostId.in_subquery(scheduled_ids))
+        .one(db)
+        .await
+        .unwrap()
+        .is_some()
+}
+
+/// Publishes scheduled posts, that are due, to channels of their users
+async fn publish_due(db: &DatabaseConnection, bot: &Bot, options: PostingOptions) {
+    let now = unix_now();
+
+    // Posts without `publish_at` are published on the next tick
+    let is_due = Condition::all()
+        .add(entity::scheduled_post::Column::Status.eq(PostStatus::Pending))
+        .add(
+            Condition::any()
+                .add(entity::scheduled_post::Column::PublishAt.is_null())
+                .add(entity::scheduled_post::Column::PublishAt.lte(now)),
+        )
+        .add(
+            Condition::any()
+                .add(entity::scheduled_post::Column::NextAttemptAt.is_null())
+                .add(entity::scheduled_post::Column::NextAttemptAt.lte(now)),
+        );
+
+    let posts = entity::scheduled_post::Entity::find()
+        .filter(is_due)
+        .find_also_related(entity::telegram_user::Entity)
+        .order_by_asc(entity::scheduled_post::Column::Id)
+        .all(db)
+        .await
+        .unwrap()
+        .into_iter()
+        .filter(|f| f.1.is_some())
+        .map(|f| (f.0, f.1.unwrap()))
+        .filter(|f| f.1.channel.is_some());
+
+    for (post, user) in posts {
+        let chat_id = ChatId(user.channel.unwrap());
+
+        // next posts of thread are replies to previous ones, so they wait for them
+        let reply_to = match post.reply_to_id {
+            Some(id) => {
+                let parent = entity::published_post::Entity::find()
+                    .filter(entity::published_post::Column::ScheduledPostId.eq(id))
+                    .one(db)
+                    .await
+                    .unwrap();
+                let status = entity::scheduled_post::Entity::find_by_id(id)
+                    .one(db)
                     .await
                     .unwrap()
-                    .into_iter()
-                    .map(|f| match f.media_type {
-                        MediaType::Photo => InputMedia::Photo(InputMediaPhoto::new(
-                            InputFile::url(Url::parse(&f.media_url).unwrap()),
-                        )),
-                        MediaType::Video => InputMedia::Video(InputMediaVideo::new(
-                            InputFile::url(Url::parse(&f.media_url).unwrap()),
-                        )),
-                    })
-                    .collect::<Vec<_>>();
-
-                // little trolling, but
-                // <@nanoqsh> Зато DRY :molodec:
-                if let Some(
-                    InputMedia::Photo(InputMediaPhoto {
-                        caption,
-                        parse_mode,
-                        ..
-                    })
-                    | InputMedia::Video(InputMediaVideo {
-                        caption,
-                        parse_mode,
-                        ..
-                    }),
-                ) = media.get_mut(0)
-                {
-                    *caption = Some(text);
-                    *parse_mode = Some(ParseMode::Html);
+                    .map(|f| f.status);
+
+                match (parent, status) {
+                    (Some(parent), _) => Some(parent.text_message_id),
+                    (None, Some(PostStatus::Pending)) => continue,
+                    (None, Some(status @ (PostStatus::Failed | PostStatus::Held))) => {
+                        stop_thread(db, id, status).await;
+                        continue;
+                    }
+                    // published previous post was deleted from channel
+                    (None, _) => None,
                 }
+            }
+            None => None,
+        };
+
+        let res = send_post(db, bot, chat_id, &post, reply_to, options).await;
+
+        let mut active = entity::scheduled_post::ActiveModel {
+            id: Set(post.id),
+            ..Default::default()
+        };
+
+        match res {
+            Ok(sent) => {
+                active.status = Set(PostStatus::Sent);
+                active.last_error = Set(None);
+                active.next_attempt_at = Set(None);
+
+                let published = entity::published_post::ActiveModel {
+                    user_id: Set(post.user_id),
+                    post_id: Set(post.post_id),
+                    scheduled_post_id: Set(Some(post.id)),
+                    chat_id: Set(chat_id.0),
+                    message_ids: Set(sent.message_ids.iter().join(",")),
+                    text_message_id: Set(sent.text_message_id),
+                    text_in_caption: Set(sent.text_in_caption),
+                    post_text: Set(post.post_text),
+                    post_source: Set(post.post_source),
+                    post_source_url: Set(post.post_source_url),
+                    published_at: Set(unix_now()),
+                    ..Default::default()
+                };
+                published.insert(db).await.unwrap();
+            }
+            Err(RequestError::RetryAfter(d)) => {
+                // flood control is not a failure of post itself
+                active.next_attempt_at = Set(Some(unix_now() + d.as_secs() as i64));
+            }
+            Err(err) => {
+                eprintln!("Failed to post message: {err}");
+
+                let attempts = post.attempts + 1;
+                active.attempts = Set(attempts);
+                active.last_error = Set(Some(err.to_string()));
+
+                if attempts >= MAX_ATTEMPTS {
+                    active.status = Set(PostStatus::Failed);
+                    active.next_attempt_at = Set(None);
+
+                    // sent parts are not published post, retry sends post from start
+                    let sent = entity::scheduled_post::Entity::find_by_id(post.id)
+                        .one(db)
+                        .await
+                        .unwrap()
+                        .unwrap();
+                    delete_sent_parts(bot, chat_id, &sent).await;
+                    active.sent_parts = Set(0);
+                    active.sent_message_ids = Set(String::new());
+                } else {
+                    let delay = RETRY_BASE_DELAY << (attempts - 1);
+                    active.next_attempt_at = Set(Some(unix_now() + delay));
+                }
+            }
+        }
+
+        let model = active.update(db).await.unwrap();
+        if model.status == PostStatus::Failed {
+            stop_thread(db, model.id, PostStatus::Failed).await;
+        }
+    }
+}
+
+/// Gives pending next posts of thread after scheduled post `id` its `status` (failed or
+/// held), since they can't be sent as replies to it
+pub async fn stop_thread(db: &DatabaseConnection, id: i64, status: PostStatus) {
+    let mut ids = vec![id];
+    loop {
+        ids = next_thread_posts(db, ids, PostStatus::Pending).await;
+        if ids.is_empty() {
+            break;
+        }
+
+        entity::scheduled_post::Entity::update_many()
+            .col_expr(
+                entity::scheduled_post::Column::Status,
+                Expr::value(status.clone()),
+            )
+            .col_expr(
+                entity::scheduled_post::Column::LastError,
+                Expr::value(THREAD_STOPPED_NOTE),
+            )
+            .col_expr(
+                entity::scheduled_post::Column::NextAttemptAt,
+                Expr::value(Option::<i64>::None),
+            )
+            .filter(entity::scheduled_post::Column::Id.is_in(ids.clone()))
+            .exec(db)
+            .await
+            .unwrap();
+    }
+}
+
+/// Makes next posts of thread after scheduled post `id`, that were stopped with it,
+/// pending again
+pub async fn resume_thread(db: &DatabaseConnection, id: i64, status: PostStatus) {
+    let mut ids = vec![id];
+    loop {
+        ids = next_thread_posts(db, ids, status.clone()).await;
+        if ids.is_empty() {
+            break;
+        }
+
+        entity::scheduled_post::Entity::update_many()
+            .col_expr(
+                entity::scheduled_post::Column::Status,
+                Expr::value(PostStatus::Pending),
+            )
+            .col_expr(
+                entity::scheduled_post::Column::LastError,
+                Expr::value(Option::<String>::None),
+            )
+            .col_expr(entity::scheduled_post::Column::Attempts, Expr::value(0))
+            .filter(entity::scheduled_post::Column::Id.is_in(ids.clone()))
+            .exec(db)
+            .await
+            .unwrap();
+    }
+}
+
+/// IDs of scheduled posts with `status`, that are replies to posts `ids`. Posts stopped
+/// with their thread are taken only with their note
+async fn next_thread_posts(db: &DatabaseConnection, ids: Vec<i64>, status: PostStatus) -> Vec<i64> {
+    let stopped = status != PostStatus::Pending;
+    let mut posts = entity::scheduled_post::Entity::find()
+        .filter(entity::scheduled_post::Column::ReplyToId.is_in(ids))
+        .filter(entity::scheduled_post::Column::Status.eq(status));
+    if stopped {
+        posts = posts.filter(entity::scheduled_post::Column::LastError.eq(THREAD_STOPPED_NOTE));
+    }
+
+    posts
+        .all(db)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|f| f.id)
+        .collect()
+}
+
+/// Part of post, that is sent with one request
+enum PostPart {
+    Group(Vec<InputMedia>),
+    /// Animations can't be sent in media groups
+    Animation(Box<InputMediaAnimation>),
+    /// Text message (or its part, if text is too long)
+    Text(String),
+}
+
+/// Sends scheduled post to `chat_id`. First message is sent as reply to `reply_to`, if
+/// present. Parts of post, that were sent on previous attempts, are skipped
+async fn send_post(
+    db: &DatabaseConnection,
+    bot: &Bot,
+    chat_id: ChatId,
+    post: &entity::scheduled_post::Model,
+    reply_to: Option<i32>,
+    options: PostingOptions,
+) -> Result<SentPost, RequestError> {
+    let raw_media = post.find_media().all(db).await.unwrap();
+
+    let mut parts = vec![];
+    let mut media_count = 0;
+    let mut text_in_caption = false;
+
+    if !raw_media.is_empty() {
+        let mut media = Vec::with_capacity(raw_media.len());
+        let mut alt_texts = vec![];
+        let mut too_large = false;
 
-                bot.send_media_group(chat_id, media).await.err()
+        for f in raw_media {
+            let file = if options.upload_media {
+                match download_media(&f.media_url).await? {
+                    Some(file) => file,
+                    None => {
+                        too_large = true;
+                        continue;
+                    }
+                }
             } else {
-                bot.send_message(chat_id, text)
-                    .parse_mode(ParseMode::Html)
-                    .await
-                    .err()
+                InputFile::url(Url::parse(&f.media_url).unwrap())
             };
 
-            if let Some(err) = err {
-                match err {
-                    RequestError::RetryAfter(d) => sleep(d).await,
-                    _ => eprintln!("Failed to post message: {err}"),
-                }
+            let size = |f: Option<i32>| f.and_then(|f| u16::try_from(f).ok());
+            let duration = size(f.duration_ms.map(|f| f / 1000));
+
+            media.push(match f.media_type {
+                MediaType::Photo => InputMedia::Photo(InputMediaPhoto::new(file)),
+                MediaType::Video => InputMedia::Video(InputMediaVideo {
+                    width: size(f.width),
+                    height: size(f.height),
+                    duration,
+                    ..InputMediaVideo::new(file)
+                }),
+                MediaType::Animation => InputMedia::Animation(InputMediaAnimation {
+                    width: size(f.width),
+                    height: size(f.height),
+                    duration,
+                    ..InputMediaAnimation::new(file)
+                }),
+            });
+            alt_texts.extend(f.alt_text);
+        }
+
+        let mut text = post.post_text.clone();
+        if options.show_alt_text && !alt_texts.is_empty() {
+            text.push_str("\n\n");
+            text.push_str(&alt_texts.iter().map(|f| format!("🖼 {f}")).join("\n"));
+        }
+        if too_large {
+            text.push_str("\n\n");
+            text.push_str(MEDIA_TOO_LARGE_NOTE);
+        }
+
+        // otherwise text is sent as reply to media
+        text_in_caption =
+            !media.is_empty() && post_length(&text, &post.post_source) <= MAX_CAPTION_LENGTH;
+        media_count = media.len();
+
+        // little trolling, but
+        // <@nanoqsh> Зато DRY :molodec:
+        if let (
+            true,
+            Some(
+                InputMedia::Photo(InputMediaPhoto {
+                    caption,
+                    parse_mode,
+                    ..
+                })
+                | InputMedia::Video(InputMediaVideo {
+                    caption,
+                    parse_mode,
+                    ..
+                })
+                | InputMedia::Animation(InputMediaAnimation {
+                    caption,
+                    parse_mode,
+                    ..
+                }),
+            ),
+        ) = (text_in_caption, media.get_mut(0))
+        {
+            *caption = Some(format_post(
+                &text,
+                &post.post_entities.0,
+                &post.post_source,
+                &post.post_source_url,
+            ));
+            *parse_mode = Some(ParseMode::Html);
+        }
+
+        for group in media_groups(media) {
+            parts.push(match <[_; 1]>::try_from(group) {
+                Ok([InputMedia::Animation(animation)]) => PostPart::Animation(Box::new(animation)),
+                Ok(group) => PostPart::Group(group.into()),
+                Err(group) => PostPart::Group(group),
+            });
+        }
+        if !text_in_caption {
+            parts.extend(text_parts(post, &text).into_iter().map(PostPart::Text));
+        }
+    } else {
+        parts.extend(
+            text_parts(post, &post.post_text)
+                .into_iter()
+                .map(PostPart::Text),
+        );
+    }
+
+    let media_parts = parts.len()
+        - parts
+            .iter()
+            .filter(|f| matches!(f, PostPart::Text(_)))
+            .count();
+    let mut message_ids = post.sent_message_ids();
+    for (i, part) in parts.into_iter().enumerate().skip(post.sent_parts as usize) {
+        // text after media replies to it
+        let reply_to = match (i, &part) {
+            (0, _) => reply_to,
+            (i, PostPart::Text(_)) if i == media_parts => message_ids.first().copied(),
+            _ => None,
+        };
+
+        match part {
+            PostPart::Group(group) => {
+                message_ids.extend(send_media_group(bot, chat_id, group, reply_to).await?)
+            }
+            PostPart::Animation(animation) => {
+                message_ids.push(send_animation(bot, chat_id, *animation, reply_to).await?)
+            }
+            PostPart::Text(text) => {
+                message_ids.push(send_text(bot, chat_id, text, reply_to).await?)
             }
+        }
 
-            del_cond = del_cond.add(entity::scheduled_post::Column::Id.eq(post.id));
+        // next attempt must not send this part again
+        entity::scheduled_post::ActiveModel {
+            id: Set(post.id),
+            sent_parts: Set(i as i32 + 1),
+            sent_message_ids: Set(message_ids.iter().join(",")),
+            ..Default::default()
         }
+        .update(db)
+        .await
+        .unwrap();
+    }
+
+    Ok(SentPost {
+        text_message_id: message_ids[if text_in_caption { 0 } else { media_count }],
+        text_in_caption,
+        message_ids,
+    })
+}
+
+/// Deletes messages of partly sent post, that won't be sent anymore, from channel
+async fn delete_sent_parts(bot: &Bot, chat_id: ChatId, post: &entity::scheduled_post::Model) {
+    for message_id in post.sent_message_ids() {
+        if let Err(e) = bot.delete_message(chat_id, MessageId(message_id)).await {
+            eprintln!("Failed to delete message of failed post: {e}");
+        }
+    }
+}
+
+/// Splits media into media groups of [`MAX_MEDIA_GROUP_SIZE`], keeping order.
+/// Animations are always alone in their groups
+fn media_groups(media: Vec<InputMedia>) -> Vec<Vec<InputMedia>> {
+    let is_animation = |f: &InputMedia| matches!(f, InputMedia::Animation(_));
+
+    let mut groups: Vec<Vec<InputMedia>> = vec![];
+    for f in media {
+        match groups.last_mut() {
+            Some(group)
+                if group.len() < MAX_MEDIA_GROUP_SIZE
+                    && !is_animation(&group[0])
+                    && !is_animation(&f) =>
+            {
+                group.push(f)
+            }
+            _ => groups.push(vec![f]),
+        }
+    }
+
+    groups
+}
+
+/// Sends media group as reply to `reply_to` (if present), returns ids of messages
+async fn send_media_group(
+    bot: &Bot,
+    chat_id: ChatId,
+    group: impl IntoIterator<Item = InputMedia>,
+    reply_to: Option<i32>,
+) -> Result<Vec<i32>, RequestError> {
+    let mut req = bot.send_media_group(chat_id, group);
+    if let Some(reply_to) = reply_to {
+        req = req.reply_to_message_id(MessageId(reply_to));
+    }
+
+    Ok(req.await?.iter().map(|m| m.id.0).collect())
+}
+
+/// Sends animation as separate message (as reply to `reply_to`, if present), returns
+/// its id
+async fn send_animation(
+    bot: &Bot,
+    chat_id: ChatId,
+    animation: InputMediaAnimation,
+    reply_to: Option<i32>,
+) -> Result<i32, RequestError> {
+    let mut req = bot.send_animation(chat_id, animation.media);
+    if let Some(reply_to) = reply_to {
+        req = req.reply_to_message_id(MessageId(reply_to));
+    }
+    if let Some(caption) = animation.caption {
+        req = req.caption(caption);
+    }
+    if let Some(parse_mode) = animation.parse_mode {
+        req = req.parse_mode(parse_mode);
+    }
+    if let Some(width) = animation.width {
+        req = req.width(width as u32);
+    }
+    if let Some(height) = animation.height {
+        req = req.height(height as u32);
+    }
+    if let Some(duration) = animation.duration {
+        req = req.duration(duration as u32);
+    }
+
+    Ok(req.await?.id.0)
+}
+
+/// Splits post `text` (with source) into formatted text messages
+fn text_parts(post: &entity::scheduled_post::Model, text: &str) -> Vec<String> {
+    split_post(
+        text,
+        &post.post_entities.0,
+        &post.post_source,
+        &post.post_source_url,
+        MAX_TEXT_LENGTH,
+    )
+}
+
+/// Sends formatted text message as reply to `reply_to` (if present), returns its id
+async fn send_text(
+    bot: &Bot,
+    chat_id: ChatId,
+    text: String,
+    reply_to: Option<i32>,
+) -> Result<i32, RequestError> {
+    let mut req = bot.send_message(chat_id, text).parse_mode(ParseMode::Html);
+    if let Some(reply_to) = reply_to {
+        req = req.reply_to_message_id(MessageId(reply_to));
+    }
+
+    Ok(req.await?.id.0)
+}
+
+/// Downloads media without keeping it, returns hex encoded SHA-256 of its content
+async fn hash_media(url: &str) -> Result<String, reqwest::Error> {
+    let mut res = reqwest::get(url).await?.error_for_status()?;
+
+    let mut hasher = Sha256::new();
+    while let Some(chunk) = res.chunk().await? {
+        hasher.update(&chunk);
+    }
+
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// Downloads media to memory. Returns `None` if media exceeds [`MAX_UPLOAD_SIZE`]
+async fn download_media(url: &str) -> Result<Option<InputFile>, RequestError> {
+    let mut res = reqwest::get(url)
+        .await
+        .and_then(|r| r.error_for_status())
+        .map_err(RequestError::Network)?;
+
+    if matches!(res.content_length(), Some(len) if len > MAX_UPLOAD_SIZE as u64) {
+        return Ok(None);
+    }
+
+    // Content-Length may be missing, so check size while downloading
+    let mut bytes = Vec::new();
+    while let Some(chunk) = res.chunk().await.map_err(RequestError::Network)? {
+        if bytes.len() + chunk.len() > MAX_UPLOAD_SIZE {
+            return Ok(None);
+        }
+        bytes.extend_from_slice(&chunk);
+    }
+
+    let file_name = res
+        .url()
+        .path_segments()
+        .and_then(|mut f| f.next_back())
+        .unwrap_or("media")
+        .to_string();
 
-        entity::scheduled_post::Entity::delete_many()
-            .filter(del_cond)
-            .exec(db.deref())
+    Ok(Some(InputFile::memory(bytes).file_name(file_name)))
+}
+
+#[cfg(test)]
+mod tests {
+    use entity::{post::PostEntities, post_media::MediaType, scheduled_post::PostStatus};
+    use migration::{Migrator, MigratorTrait};
+    use mockserver::{fixture, MockRoute, MockServer};
+    use sea_orm::{ActiveModelTrait, Database, DatabaseConnection, EntityTrait, Set};
+    use sha2::{Digest, Sha256};
+    use telegrambot::teloxide::types::{
+        InputFile, InputMedia, InputMediaAnimation, InputMediaPhoto, InputMediaVideo,
+    };
+
+    use super::{
+        hash_pending_media, is_duplicate, media_groups, publish_due, resume_thread, PostingOptions,
+        MAX_ATTEMPTS, MAX_MEDIA_GROUP_SIZE, MAX_TEXT_LENGTH, MAX_UPLOAD_SIZE, MEDIA_TOO_LARGE_NOTE,
+        THREAD_STOPPED_NOTE,
+    };
+    use crate::models::time::unix_now;
+
+    const OPTIONS: PostingOptions = PostingOptions {
+        upload_media: false,
+        show_alt_text: false,
+    };
+
+    fn file() -> InputFile {
+        InputFile::url("https://example.com/f".parse().unwrap())
+    }
+
+    #[test]
+    /// Check [`media_groups`] keeps order, limits group size and sends animations alone
+    fn test_media_groups() {
+        let mut media = vec![
+            InputMedia::Photo(InputMediaPhoto::new(file())),
+            InputMedia::Video(InputMediaVideo::new(file())),
+            InputMedia::Animation(InputMediaAnimation::new(file())),
+        ];
+        media.extend(
+            (0..MAX_MEDIA_GROUP_SIZE + 1).map(|_| InputMedia::Photo(InputMediaPhoto::new(file()))),
+        );
+
+        let sizes = media_groups(media)
+            .iter()
+            .map(|f| f.len())
+            .collect::<Vec<_>>();
+        assert_eq!(sizes, [2, 1, MAX_MEDIA_GROUP_SIZE, 1]);
+    }
+
+    /// Database with user 1, that linked channel -1001
+    async fn database() -> DatabaseConnection {
+        let db = Database::connect("sqlite::memory:").await.unwrap();
+        Migrator::up(&db, None).await.unwrap();
+
+        entity::telegram_user::ActiveModel {
+            id: Set(1),
+            channel: Set(Some(-1001)),
+            ..Default::default()
+        }
+        .insert(&db)
+        .await
+        .unwrap();
+
+        db
+    }
+
+    async fn add_media(db: &DatabaseConnection, media_type: MediaType, url: &str) -> i64 {
+        let media = entity::post_media::ActiveModel {
+            url_key: Set(entity::post_media::url_key(url)),
+            media_type: Set(media_type),
+            media_url: Set(url.to_string()),
+            ..Default::default()
+        };
+
+        media.insert(db).await.unwrap().id
+    }
+
+    async fn schedule(
+        db: &DatabaseConnection,
+        text: &str,
+        media_ids: &[i64],
+        reply_to_id: Option<i64>,
+    ) -> entity::scheduled_post::Model {
+        let post = entity::scheduled_post::ActiveModel {
+            user_id: Set(1),
+            reply_to_id: Set(reply_to_id),
+            post_text: Set(text.to_string()),
+            post_entities: Set(PostEntities(vec![])),
+            post_source: Set("jack & co".to_string()),
+            post_source_url: Set("https://twitter.com/jack/status/1".to_string()),
+            ..Default::default()
+        };
+        let post = post.insert(db).await.unwrap();
+
+        for (i, &media_id) in media_ids.iter().enumerate() {
+            entity::scheduled_post_media::ActiveModel {
+                scheduled_post_id: Set(post.id),
+                media_id: Set(media_id),
+                position: Set(i as i32),
+            }
+            .insert(db)
             .await
             .unwrap();
+        }
 
-        sleep(Duration::from_secs(120)).await;
+        post
+    }
+
+    async fn reload(
+        db: &DatabaseConnection,
+        post: &entity::scheduled_post::Model,
+    ) -> entity::scheduled_post::Model {
+        entity::scheduled_post::Entity::find_by_id(post.id)
+            .one(db)
+            .await
+            .unwrap()
+            .unwrap()
+    }
+
+    #[rocket::async_test]
+    /// Check [`publish_due`] sends escaped text with source link and replies of thread
+    async fn test_publish_text() {
+        let db = database().await;
+        let server = MockServer::start(vec![MockRoute::new(
+            "/bottoken/SendMessage",
+            200,
+            fixture("telegram/message.json"),
+        )])
+        .await;
+
+        let first = schedule(&db, "1 < 2 && <b>", &[], None).await;
+        let second = schedule(&db, "and reply", &[], Some(first.id)).await;
+
+        publish_due(&db, &server.bot(), OPTIONS).await;
+
+        let bodies = server
+            .bodies("/bottoken/SendMessage")
+            .iter()
+            .map(|f| serde_json::from_str::<serde_json::Value>(f).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(bodies.len(), 2);
+        assert_eq!(bodies[0]["chat_id"], -1001);
+        assert_eq!(bodies[0]["parse_mode"], "HTML");
+        assert_eq!(
+            bodies[0]["text"],
+            "1 &lt; 2 &amp;&amp; &lt;b&gt;\n\n\
+             <b><a href=\"https://twitter.com/jack/status/1\">jack &amp; co</a></b>"
+        );
+        assert!(bodies[0].get("reply_to_message_id").is_none());
+        assert_eq!(bodies[1]["reply_to_message_id"], 10);
+
+        for post in [&first, &second] {
+            let post = reload(&db, post).await;
+            assert_eq!(post.status, PostStatus::Sent);
+        }
+
+        let published = entity::published_post::Entity::find()
+            .all(&db)
+            .await
+            .unwrap();
+        assert_eq!(published.len(), 2);
+        assert_eq!(published[0].scheduled_post_id, Some(first.id));
+        assert_eq!(published[0].chat_id, -1001);
+        assert_eq!(published[0].message_ids(), [10]);
+        assert!(!published[0].text_in_caption);
+    }
+
+    #[rocket::async_test]
+    /// Check [`publish_due`] records all messages of splitted post with its source post
+    /// and keeps scheduled post as sent
+    async fn test_publish_records_post() {
+        let db = database().await;
+        let server = MockServer::start(vec![MockRoute::new(
+            "/bottoken/SendMessage",
+            200,
+            fixture("telegram/message.json"),
+        )])
+        .await;
+
+        let author = entity::author::ActiveModel {
+            platform_id: Set(12),
+            name: Set("jack".to_string()),
+            username: Set("jack".to_string()),
+            ..Default::default()
+        }
+        .insert(&db)
+        .await
+        .unwrap();
+        let source = entity::post::ActiveModel {
+            platform_id: Set(1),
+            external_id: Set("1".to_string()),
+            author_id: Set(author.id),
+            text: Set("hello".to_string()),
+            source_url: Set("https://twitter.com/jack/status/1".to_string()),
+            source_text: Set("jack & co".to_string()),
+            ..Default::default()
+        }
+        .insert(&db)
+        .await
+        .unwrap();
+
+        let text = "word ".repeat(MAX_TEXT_LENGTH / 4);
+        let mut post: entity::scheduled_post::ActiveModel =
+            schedule(&db, &text, &[], None).await.into();
+        post.post_id = Set(Some(source.id));
+        let post = post.update(&db).await.unwrap();
+
+        let before = unix_now();
+        publish_due(&db, &server.bot(), OPTIONS).await;
+
+        let bodies = server.bodies("/bottoken/SendMessage");
+        assert_eq!(bodies.len(), 2);
+
+        let post = reload(&db, &post).await;
+        assert_eq!(post.status, PostStatus::Sent);
+        assert_eq!(post.last_error, None);
+
+        let published = entity::published_post::Entity::find()
+            .all(&db)
+            .await
+            .unwrap();
+        assert_eq!(published.len(), 1);
+        let published = &published[0];
+        assert_eq!(published.user_id, 1);
+        assert_eq!(published.post_id, Some(source.id));
+        assert_eq!(published.scheduled_post_id, Some(post.id));
+        assert_eq!(published.chat_id, -1001);
+        assert_eq!(published.message_ids(), [10, 10]);
+        assert_eq!(published.text_message_id, 10);
+        assert!(!published.text_in_caption);
+        assert_eq!(published.post_text, text);
+        assert_eq!(published.post_source, "jack & co");
+        assert!(published.published_at >= before);
+
+        // sent post is not published again
+        publish_due(&db, &server.bot(), OPTIONS).await;
+        assert_eq!(server.requests().len(), 2);
+    }
+
+    #[rocket::async_test]
+    /// Check [`publish_due`] keeps media order, puts caption on first media and sends
+    /// animations separately
+    async fn test_publish_media() {
+        let db = database().await;
+        let server = MockServer::start(vec![
+            MockRoute::new(
+                "/bottoken/SendMediaGroup",
+                200,
+                fixture("telegram/media_group.json"),
+            ),
+            MockRoute::new(
+                "/bottoken/SendAnimation",
+                200,
+                fixture("telegram/animation.json"),
+            ),
+        ])
+        .await;
+
+        let media = [
+            add_media(&db, MediaType::Photo, "https://pbs.twimg.com/media/a.jpg").await,
+            add_media(&db, MediaType::Photo, "https://pbs.twimg.com/media/b.jpg").await,
+            add_media(&db, MediaType::Animation, "https://video.twimg.com/c.mp4").await,
+        ];
+        // photos are reordered by user
+        let post = schedule(&db, "look <here>", &[media[1], media[0], media[2]], None).await;
+
+        publish_due(&db, &server.bot(), OPTIONS).await;
+
+        let groups = server.bodies("/bottoken/SendMediaGroup");
+        assert_eq!(groups.len(), 1);
+        let a = groups[0].find("https://pbs.twimg.com/media/a.jpg").unwrap();
+        let b = groups[0].find("https://pbs.twimg.com/media/b.jpg").unwrap();
+        assert!(b < a);
+        assert!(groups[0].contains(r#""caption":"look &lt;here&gt;\n\n<b><a href="#));
+        assert!(groups[0].contains(r#""parse_mode":"HTML""#));
+        assert_eq!(groups[0].matches("caption").count(), 1);
+
+        let animations = server.bodies("/bottoken/SendAnimation");
+        assert_eq!(animations.len(), 1);
+        assert!(animations[0].contains("https://video.twimg.com/c.mp4"));
+        assert!(!animations[0].contains("caption"));
+
+        assert_eq!(server.requests().len(), 2);
+        assert_eq!(reload(&db, &post).await.status, PostStatus::Sent);
+
+        let published = entity::published_post::Entity::find()
+            .one(&db)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(published.message_ids(), [20, 21, 30]);
+        assert_eq!(published.text_message_id, 20);
+        assert!(published.text_in_caption);
+    }
+
+    #[rocket::async_test]
+    /// Check [`publish_due`] uploads downloaded media, skips media over upload limit with
+    /// note and posts text only, if all media is skipped
+    async fn test_publish_upload_media() {
+        let db = database().await;
+        let server = MockServer::start(vec![
+            MockRoute::new("/media/small.jpg", 200, "small photo"),
+            MockRoute::new("/media/large.mp4", 200, "x".repeat(MAX_UPLOAD_SIZE + 1)),
+            MockRoute::new(
+                "/bottoken/SendMediaGroup",
+                200,
+                fixture("telegram/media_group.json"),
+            ),
+            MockRoute::new(
+                "/bottoken/SendMessage",
+                200,
+                fixture("telegram/message.json"),
+            ),
+        ])
+        .await;
+        let options = PostingOptions {
+            upload_media: true,
+            ..OPTIONS
+        };
+
+        let small = format!("{}/media/small.jpg", server.url);
+        let large = format!("{}/media/large.mp4", server.url);
+        let small = add_media(&db, MediaType::Photo, &small).await;
+        let large = add_media(&db, MediaType::Video, &large).await;
+        let mixed = schedule(&db, "mixed", &[large, small], None).await;
+        let only_large = schedule(&db, "only large", &[large], None).await;
+
+        publish_due(&db, &server.bot(), options).await;
+
+        let groups = server.bodies("/bottoken/SendMediaGroup");
+        assert_eq!(groups.len(), 1);
+        assert!(groups[0].contains("small photo"));
+        assert!(groups[0].contains(r#"filename="small.jpg""#));
+        assert!(!groups[0].contains("large.mp4"));
+        assert!(groups[0].contains(MEDIA_TOO_LARGE_NOTE));
+
+        let messages = server.bodies("/bottoken/SendMessage");
+        assert_eq!(messages.len(), 1);
+        assert!(messages[0].contains("only large"));
+        assert!(messages[0].contains(MEDIA_TOO_LARGE_NOTE));
+
+        for post in [&mixed, &only_large] {
+            assert_eq!(reload(&db, post).await.status, PostStatus::Sent);
+        }
+    }
+
+    #[rocket::async_test]
+    /// Check [`publish_due`] postpones post on flood control without counting attempt
+    async fn test_publish_retry_after() {
+        let db = database().await;
+        let server = MockServer::start(vec![MockRoute::new(
+            "/bottoken/SendMessage",
+            429,
+            fixture("telegram/retry_after.json"),
+        )])
+        .await;
+
+        let post = schedule(&db, "hello", &[], None).await;
+        publish_due(&db, &server.bot(), OPTIONS).await;
+
+        let post = reload(&db, &post).await;
+        assert_eq!(post.status, PostStatus::Pending);
+        assert_eq!(post.attempts, 0);
+        assert!(post.next_attempt_at.unwrap() >= unix_now() + 25);
+
+        // post waits for retry
+        publish_due(&db, &server.bot(), OPTIONS).await;
+        assert_eq!(server.requests().len(), 1);
+        assert!(entity::published_post::Entity::find()
+            .all(&db)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    /// Schedules post with one more photo, than fits in media group
+    async fn schedule_photos(db: &DatabaseConnection) -> entity::scheduled_post::Model {
+        let mut media = vec![];
+        for i in 0..=MAX_MEDIA_GROUP_SIZE {
+            let url = format!("https://pbs.twimg.com/media/p{i}.jpg");
+            media.push(add_media(db, MediaType::Photo, &url).await);
+        }
+
+        schedule(db, "photos", &media, None).await
+    }
+
+    #[rocket::async_test]
+    /// Check [`publish_due`] keeps sent media groups of post on flood control and sends
+    /// only the rest of post on next attempt
+    async fn test_publish_resume_after_retry_after() {
+        let db = database().await;
+        let server = MockServer::start(vec![
+            MockRoute::new(
+                "/bottoken/SendMediaGroup",
+                200,
+                fixture("telegram/media_group.json"),
+            )
+            .times(1),
+            MockRoute::new(
+                "/bottoken/SendMediaGroup",
+                429,
+                fixture("telegram/retry_after.json"),
+            )
+            .times(1),
+            MockRoute::new(
+                "/bottoken/SendMediaGroup",
+                200,
+                fixture("telegram/media_group.json"),
+            ),
+        ])
+        .await;
+
+        let post = schedule_photos(&db).await;
+        publish_due(&db, &server.bot(), OPTIONS).await;
+
+        let post = reload(&db, &post).await;
+        assert_eq!(post.status, PostStatus::Pending);
+        assert_eq!(post.attempts, 0);
+        assert_eq!(post.sent_parts, 1);
+        assert_eq!(post.sent_message_ids(), [20, 21]);
+        assert!(entity::published_post::Entity::find()
+            .all(&db)
+            .await
+            .unwrap()
+            .is_empty());
+
+        let mut active: entity::scheduled_post::ActiveModel = post.clone().into();
+        active.next_attempt_at = Set(None);
+        active.update(&db).await.unwrap();
+        publish_due(&db, &server.bot(), OPTIONS).await;
+
+        let groups = server.bodies("/bottoken/SendMediaGroup");
+        assert_eq!(groups.len(), 3);
+        assert!(groups[0].contains("p0.jpg"));
+        assert!(groups[0].contains("caption"));
+        assert!(groups[2].contains("p10.jpg"));
+        assert!(!groups[2].contains("p0.jpg"));
+        assert!(!groups[2].contains("caption"));
+        assert!(!groups[2].contains("reply_to_message_id"));
+
+        assert_eq!(reload(&db, &post).await.status, PostStatus::Sent);
+        let published = entity::published_post::Entity::find()
+            .one(&db)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(published.message_ids(), [20, 21, 20, 21]);
+        assert_eq!(published.text_message_id, 20);
+        assert!(published.text_in_caption);
+    }
+
+    #[rocket::async_test]
+    /// Check [`publish_due`] deletes sent media groups of post, that failed on its last
+    /// attempt
+    async fn test_publish_failed_deletes_sent() {
+        let db = database().await;
+        let server = MockServer::start(vec![
+            MockRoute::new(
+                "/bottoken/SendMediaGroup",
+                200,
+                fixture("telegram/media_group.json"),
+            )
+            .times(1),
+            MockRoute::new(
+                "/bottoken/SendMediaGroup",
+                400,
+                fixture("telegram/chat_not_found.json"),
+            ),
+            MockRoute::new(
+                "/bottoken/DeleteMessage",
+                200,
+                fixture("telegram/true.json"),
+            ),
+        ])
+        .await;
+
+        let post = schedule_photos(&db).await;
+        let mut active: entity::scheduled_post::ActiveModel = post.clone().into();
+        active.attempts = Set(MAX_ATTEMPTS - 1);
+        active.update(&db).await.unwrap();
+
+        publish_due(&db, &server.bot(), OPTIONS).await;
+
+        let deleted = server.bodies("/bottoken/DeleteMessage");
+        assert_eq!(deleted.len(), 2);
+        assert!(deleted[0].contains(r#""message_id":20"#));
+        assert!(deleted[1].contains(r#""message_id":21"#));
+
+        let post = reload(&db, &post).await;
+        assert_eq!(post.status, PostStatus::Failed);
+        assert_eq!(post.sent_parts, 0);
+        assert!(post.sent_message_ids().is_empty());
+    }
+
+    #[rocket::async_test]
+    /// Check [`publish_due`] records error of failed post and retries it later
+    async fn test_publish_chat_not_found() {
+        let db = database().await;
+        let server = MockServer::start(vec![MockRoute::new(
+            "/bottoken/SendMessage",
+            400,
+            fixture("telegram/chat_not_found.json"),
+        )])
+        .await;
+
+        let first = schedule(&db, "hello", &[], None).await;
+        let second = schedule(&db, "reply", &[], Some(first.id)).await;
+        publish_due(&db, &server.bot(), OPTIONS).await;
+
+        let first = reload(&db, &first).await;
+        assert_eq!(first.status, PostStatus::Pending);
+        assert_eq!(first.attempts, 1);
+        assert!(first.last_error.unwrap().contains("chat not found"));
+        assert!(first.next_attempt_at.unwrap() > unix_now());
+
+        // reply waits for its parent
+        assert_eq!(server.requests().len(), 1);
+        assert_eq!(reload(&db, &second).await.attempts, 0);
+        assert!(entity::published_post::Entity::find()
+            .all(&db)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    /// Sets `status` and `publish_at` of scheduled post
+    async fn update(
+        db: &DatabaseConnection,
+        post: &entity::scheduled_post::Model,
+        status: PostStatus,
+        publish_at: Option<i64>,
+    ) {
+        let mut active: entity::scheduled_post::ActiveModel = post.clone().into();
+        active.status = Set(status);
+        active.publish_at = Set(publish_at);
+        active.update(db).await.unwrap();
+    }
+
+    #[rocket::async_test]
+    /// Check [`publish_due`] keeps reply of thread pending until its parent is sent, and
+    /// sends reply without threading, if published parent was deleted
+    async fn test_publish_thread_waits() {
+        let db = database().await;
+        let server = MockServer::start(vec![MockRoute::new(
+            "/bottoken/SendMessage",
+            200,
+            fixture("telegram/message.json"),
+        )])
+        .await;
+
+        let first = schedule(&db, "hello", &[], None).await;
+        let second = schedule(&db, "reply", &[], Some(first.id)).await;
+        update(&db, &first, PostStatus::Pending, Some(unix_now() + 3600)).await;
+
+        publish_due(&db, &server.bot(), OPTIONS).await;
+        assert!(server.requests().is_empty());
+        let second = reload(&db, &second).await;
+        assert_eq!(second.status, PostStatus::Pending);
+        assert_eq!(second.last_error, None);
+
+        // parent is sent, but its messages were deleted with published post
+        update(&db, &first, PostStatus::Sent, None).await;
+        publish_due(&db, &server.bot(), OPTIONS).await;
+
+        let bodies = server.bodies("/bottoken/SendMessage");
+        assert_eq!(bodies.len(), 1);
+        assert!(bodies[0].contains("reply"));
+        assert!(!bodies[0].contains("reply_to_message_id"));
+        assert_eq!(reload(&db, &second).await.status, PostStatus::Sent);
+    }
+
+    #[rocket::async_test]
+    /// Check [`publish_due`] fails (or holds) the rest of thread with failed (or held)
+    /// parent, and [`resume_thread`] makes it pending again
+    async fn test_publish_thread_parent_failed() {
+        let db = database().await;
+        let server = MockServer::start(vec![MockRoute::new(
+            "/bottoken/SendMessage",
+            400,
+            fixture("telegram/chat_not_found.json"),
+        )])
+        .await;
+
+        let first = schedule(&db, "hello", &[], None).await;
+        let second = schedule(&db, "reply", &[], Some(first.id)).await;
+        let third = schedule(&db, "and reply", &[], Some(second.id)).await;
+        let mut active: entity::scheduled_post::ActiveModel = first.clone().into();
+        active.attempts = Set(MAX_ATTEMPTS - 1);
+        active.update(&db).await.unwrap();
+
+        publish_due(&db, &server.bot(), OPTIONS).await;
+
+        assert_eq!(server.requests().len(), 1);
+        assert_eq!(reload(&db, &first).await.status, PostStatus::Failed);
+        for post in [&second, &third] {
+            let post = reload(&db, post).await;
+            assert_eq!(post.status, PostStatus::Failed);
+            assert_eq!(post.last_error.as_deref(), Some(THREAD_STOPPED_NOTE));
+            assert_eq!(post.next_attempt_at, None);
+        }
+
+        resume_thread(&db, first.id, PostStatus::Failed).await;
+        for post in [&second, &third] {
+            let post = reload(&db, post).await;
+            assert_eq!(post.status, PostStatus::Pending);
+            assert_eq!(post.last_error, None);
+        }
+
+        // held parent holds replies, that become due
+        update(&db, &first, PostStatus::Held, None).await;
+        publish_due(&db, &server.bot(), OPTIONS).await;
+
+        assert_eq!(server.requests().len(), 1);
+        for post in [&second, &third] {
+            let post = reload(&db, post).await;
+            assert_eq!(post.status, PostStatus::Held);
+            assert_eq!(post.last_error.as_deref(), Some(THREAD_STOPPED_NOTE));
+        }
+    }
+
+    /// Records `post` as published in channel -1001
+    async fn mark_published(db: &DatabaseConnection, post: &entity::scheduled_post::Model) {
+        entity::scheduled_post::ActiveModel {
+            id: Set(post.id),
+            status: Set(PostStatus::Sent),
+            ..Default::default()
+        }
+        .update(db)
+        .await
+        .unwrap();
+        entity::published_post::ActiveModel {
+            user_id: Set(post.user_id),
+            scheduled_post_id: Set(Some(post.id)),
+            chat_id: Set(-1001),
+            message_ids: Set("10".to_string()),
+            text_message_id: Set(10),
+            text_in_caption: Set(true),
+            post_text: Set(post.post_text.clone()),
+            post_source: Set(post.post_source.clone()),
+            post_source_url: Set(post.post_source_url.clone()),
+            published_at: Set(0),
+            ..Default::default()
+        }
+        .insert(db)
+        .await
+        .unwrap();
+    }
+
+    #[rocket::async_test]
+    /// Check [`is_duplicate`] finds media published in channel of user only, by id or by
+    /// content hash
+    async fn test_is_duplicate() {
+        let db = database().await;
+        for (id, channel) in [(2, Some(-1002)), (3, None)] {
+            entity::telegram_user::ActiveModel {
+                id: Set(id),
+                channel: Set(channel),
+                ..Default::default()
+            }
+            .insert(&db)
+            .await
+            .unwrap();
+        }
+
+        let published = add_media(&db, MediaType::Photo, "https://pbs.twimg.com/media/a.jpg").await;
+        let other = add_media(&db, MediaType::Photo, "https://pbs.twimg.com/media/b.jpg").await;
+        let same = add_media(&db, MediaType::Photo, "https://example.com/a.jpg").await;
+        for id in [published, same] {
+            entity::post_media::ActiveModel {
+                id: Set(id),
+                content_hash: Set(Some("a".to_string())),
+                ..Default::default()
+            }
+            .update(&db)
+            .await
+            .unwrap();
+        }
+        let post = schedule(&db, "photo", &[published], None).await;
+        mark_published(&db, &post).await;
+
+        assert!(is_duplicate(&db, 1, &[other, published]).await);
+        assert!(is_duplicate(&db, 1, &[same]).await);
+        assert!(!is_duplicate(&db, 1, &[other]).await);
+        assert!(!is_duplicate(&db, 1, &[]).await);
+        assert!(!is_duplicate(&db, 2, &[published]).await);
+        assert!(!is_duplicate(&db, 3, &[published]).await);
+    }
+
+    #[rocket::async_test]
+    /// Check [`hash_pending_media`] hashes media of pending posts once and marks posts
+    /// with file, that was published under other url, as duplicate
+    async fn test_hash_pending_media() {
+        let db = database().await;
+        let server = MockServer::start(vec![
+            MockRoute::new("/media/a.jpg", 200, "photo"),
+            MockRoute::new("/media/b.jpg", 200, "photo"),
+            MockRoute::new("/media/c.jpg", 200, "other photo"),
+        ])
+        .await;
+        let url = |name: &str| format!("{}/media/{name}", server.url);
+
+        let a = add_media(&db, MediaType::Photo, &url("a.jpg")).await;
+        let b = add_media(&db, MediaType::Photo, &url("b.jpg")).await;
+        let c = add_media(&db, MediaType::Photo, &url("c.jpg")).await;
+        let published = schedule(&db, "a", &[a], None).await;
+        hash_pending_media(&db).await;
+        mark_published(&db, &published).await;
+
+        let same = schedule(&db, "b", &[b], None).await;
+        let other = schedule(&db, "c", &[c], None).await;
+        hash_pending_media(&db).await;
+
+        assert!(reload(&db, &same).await.duplicate);
+        assert!(!reload(&db, &other).await.duplicate);
+        assert_eq!(
+            server.requests(),
+            ["/media/a.jpg", "/media/b.jpg", "/media/c.jpg"]
+        );
+
+        let media = entity::post_media::Entity::find_by_id(a)
+            .one(&db)
+            .await
+            .unwrap()
+            .unwrap();
+        let hash = format!("{:x}", Sha256::digest("photo"));
+        assert_eq!(media.content_hash, Some(hash));
     }
 }
@@ -0,0 +1,170 @@
+use std::time::Duration;
+
+use entity::author::Platform;
+use migration::Condition;
+use rocket::tokio::time::sleep;
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait, Iterable, QueryFilter,
+    QueryOrder, QuerySelect, Set,
+};
+
+use crate::{models::time::unix_now, sources::Sources};
+
+/// Delay between checks for profiles to refresh
+const TICK: Duration = Duration::from_secs(10 * 60);
+/// Minimum delay between refreshes of one author in seconds
+const REFRESH_INTERVAL: i64 = 24 * 60 * 60;
+/// Maximum number of authors of one platform refreshed at once
+const BATCH_SIZE: u64 = 100;
+
+/// Starts worker, that refreshes names, usernames and avatars of authors
+pub async fn start_profile_worker(db: &DatabaseConnection, sources: &Sources) {
+    loop {
+        refresh_due(db, sources).await;
+
+        sleep(TICK).await;
+    }
+}
+
+/// Refreshes batch of profiles of every platform, that were not refreshed for longest time
+async fn refresh_due(db: &DatabaseConnection, sources: &Sources) {
+    let now = unix_now();
+    let is_due = Condition::any()
+        .add(entity::author::Column::RefreshedAt.is_null())
+        .add(entity::author::Column::RefreshedAt.lte(now - REFRESH_INTERVAL));
+
+    for platform in Platform::iter() {
+        let Some(source) = sources.get(platform) else {
+            continue;
+        };
+
+        let authors = entity::author::Entity::find()
+            .filter(entity::author::Column::Platform.eq(platform))
+            .filter(is_due.clone())
+            .order_by_asc(entity::author::Column::RefreshedAt)
+            .limit(BATCH_SIZE)
+            .all(db)
+            .await
+            .unwrap();
+
+        if authors.is_empty() {
+            continue;
+        }
+
+        let ids = authors
+            .iter()
+            .map(|f| f.external_id.clone())
+            .collect::<Vec<_>>();
+        let users = match source.fetch_users(&ids).await {
+            Ok(r) => r,
+            Err(e) => {
+                eprintln!("Warning: refresh of {platform:?} profiles failed: {e}");
+                continue;
+            }
+        };
+
+        for author in authors {
+            let user = users.iter().find(|f| f.external_id == author.external_id);
+
+            // missing authors are kept as is until the next refresh
+            let mut active = entity::author::ActiveModel {
+                id: Set(author.id),
+                refreshed_at: Set(Some(now)),
+                ..Default::default()
+            };
+            if let Some(user) = user {
+                record_rename(db, &author, &user.username).await;
+
+                active.name = Set(user.name.clone());
+                active.username = Set(user.username.clone());
+                active.avatar_url = Set(user.avatar_url.clone());
+            }
+            active.update(db).await.unwrap();
+        }
+    }
+}
+
+/// Remembers current username of `author`, if it differs from `username`, so author can
+/// be found by its past usernames
+pub async fn record_rename(
+    db: &DatabaseConnection,
+    author: &entity::author::Model,
+    username: &str,
+) {
+    if author.username == username {
+        return;
+    }
+
+    let active = entity::author_username::ActiveModel {
+        author_id: Set(author.id),
+        username: Set(author.username.clone()),
+        replaced_at: Set(unix_now()),
+        ..Default::default()
+    };
+    active.insert(db).await.unwrap();
+}
+
+/// Finds author by its current username or, if there is no such author, by past
+/// username (latest rename wins)
+pub async fn find_by_username(
+    db: &DatabaseConnection,
+    username: &str,
+) -> Option<entity::author::Model> {
+    let author = entity::author::Entity::find()
+        .filter(entity::author::Column::Username.eq(username))
+        .one(db)
+        .await
+        .unwrap();
+    if author.is_some() {
+        return author;
+    }
+
+    entity::author_username::Entity::find()
+        .filter(entity::author_username::Column::Username.eq(username))
+        .order_by_desc(entity::author_username::Column::ReplacedAt)
+        .find_also_related(entity::author::Entity)
+        .one(db)
+        .await
+        .unwrap()
+        .and_then(|f| f.1)
+}
+
+#[cfg(test)]
+mod tests {
+    use entity::author::Platform;
+    use migration::{Migrator, MigratorTrait};
+    use sea_orm::{ActiveModelTrait, Database, Set};
+
+    use super::{find_by_username, record_rename};
+
+    #[rocket::async_test]
+    /// Check [`find_by_username`] resolves past usernames, recorded by [`record_rename`]
+    async fn test_find_by_username() {
+        let db = Database::connect("sqlite::memory:").await.unwrap();
+        Migrator::up(&db, None).await.unwrap();
+
+        let author = entity::author::ActiveModel {
+            platform_id: Set(1),
+            platform: Set(Platform::Twitter),
+            external_id: Set("1".to_string()),
+            name: Set("Jack".to_string()),
+            username: Set("jack".to_string()),
+            ..Default::default()
+        }
+        .insert(&db)
+        .await
+        .unwrap();
+
+        record_rename(&db, &author, "jack").await;
+        record_rename(&db, &author, "jack2").await;
+
+        let mut active: entity::author::ActiveModel = author.into();
+        active.username = Set("jack2".to_string());
+        active.update(&db).await.unwrap();
+
+        let found = find_by_username(&db, "jack").await.unwrap();
+        assert_eq!(found.username, "jack2");
+        assert!(find_by_username(&db, "jack2").await.is_some());
+        assert!(find_by_username(&db, "nobody").await.is_none());
+    }
+}
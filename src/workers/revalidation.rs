@@ -0,0 +1,346 @@
+use std::{collections::HashSet, time::Duration};
+
+use entity::{author::Platform, scheduled_post::PostStatus};
+use migration::{Condition, Expr};
+use rocket::tokio::time::sleep;
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter, QueryOrder,
+    QuerySelect, Set,
+};
+
+use super::{collector::store_edit, posting::stop_thread};
+use crate::{
+    models::{
+        time::unix_now,
+        twitterclient::{snowflake_at, TwitterClient, MAX_LOOKUP_IDS},
+    },
+    sources::twitter::tweet_post,
+};
+
+/// Delay between revalidations
+const TICK: Duration = Duration::from_secs(5 * 60);
+/// Posts older than this (in seconds) are not revalidated
+const MAX_POST_AGE: i64 = 7 * 24 * 60 * 60;
+/// Minimum delay between revalidations of one post in seconds
+const MIN_CHECK_INTERVAL: i64 = 60 * 60;
+/// Error of scheduled posts, that are held because source post was deleted
+const DELETED_NOTE: &str = "Source post was deleted";
+
+/// Starts worker, that re-checks recent tweets and marks deleted and edited ones
+pub async fn start_revalidation_worker(db: &DatabaseConnection, twitter: &TwitterClient) {
+    loop {
+        revalidate(db, twitter).await;
+
+        sleep(TICK).await;
+    }
+}
+
+/// Revalidates batch of recent tweets, that were not checked for longest time
+async fn revalidate(db: &DatabaseConnection, twitter: &TwitterClient) {
+    let now = unix_now();
+    let is_due = Condition::any()
+        .add(entity::post::Column::CheckedAt.is_null())
+        .add(entity::post::Column::CheckedAt.lte(now - MIN_CHECK_INTERVAL));
+
+    let posts = entity::post::Entity::find()
+        .filter(entity::post::Column::Platform.eq(Platform::Twitter))
+        .filter(entity::post::Column::Deleted.eq(false))
+        .filter(entity::post::Column::PlatformId.gte(snowflake_at(now - MAX_POST_AGE)))
+        .filter(is_due)
+        .order_by_asc(entity::post::Column::CheckedAt)
+        .limit(MAX_LOOKUP_IDS as u64)
+        .all(db)
+        .await
+        .unwrap();
+
+    if posts.is_empty() {
+        return;
+    }
+
+    let ids = posts
+        .iter()
+        .map(|f| f.version_id.unwrap_or(f.platform_id))
+        .collect::<Vec<_>>();
+    let lookup = match twitter.lookup_tweets(&ids).await {
+        Ok(r) => r,
+        Err(e) => {
+            eprintln!("Warning: revalidation of tweets failed: {e}");
+            return;
+        }
+    };
+
+    // lookup returns requested versions, so newer versions are fetched separately
+    let newer = lookup
+        .tweets
+        .iter()
+        .filter_map(|f| f.edit_history.last().filter(|&&latest| latest != f.id))
+        .copied()
+        .collect::<HashSet<_>>()
+        .into_iter()
+        .collect::<Vec<_>>();
+    let versions = if newer.is_empty() {
+        vec![]
+    } else {
+        match twitter.lookup_tweets(&newer).await {
+            Ok(r) => r.tweets,
+            Err(e) => {
+                eprintln!("Warning: lookup of edited tweets failed: {e}");
+                vec![]
+            }
+        }
+    };
+
+    for post in posts {
+        let id = post.version_id.unwrap_or(post.platform_id);
+
+        let mut active = entity::post::ActiveModel {
+            id: Set(post.id),
+            checked_at: Set(Some(now)),
+            ..Default::default()
+        };
+        if lookup.deleted.contains(&id) {
+            active.deleted = Set(true);
+            hold_scheduled_posts(db, post.id).await;
+        }
+        active.update(db).await.unwrap();
+
+        let version = versions
+            .iter()
+            .filter(|f| f.edit_history.contains(&id))
+            .max_by_key(|f| f.id);
+        if let Some(version) = version {
+            store_edit(db, post, tweet_post(version.clone())).await;
+        }
+    }
+}
+
+/// Holds back pending scheduled posts of deleted post `post_id` with the rest of their
+/// threads
+async fn hold_scheduled_posts(db: &DatabaseConnection, post_id: i64) {
+    let held = entity::scheduled_post::Entity::find()
+        .filter(entity::scheduled_post::Column::PostId.eq(post_id))
+        .filter(entity::scheduled_post::Column::Status.eq(PostStatus::Pending))
+        .all(db)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|f| f.id)
+        .collect::<Vec<_>>();
+    if held.is_empty() {
+        return;
+    }
+
+    entity::scheduled_post::Entity::update_many()
+        .col_expr(
+            entity::scheduled_post::Column::Status,
+            Expr::value(PostStatus::Held),
+        )
+        .col_expr(
+            entity::scheduled_post::Column::LastError,
+            Expr::value(DELETED_NOTE),
+        )
+        .filter(entity::scheduled_post::Column::Id.is_in(held.clone()))
+        .exec(db)
+        .await
+        .unwrap();
+
+    for id in held {
+        stop_thread(db, id, PostStatus::Held).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use entity::{author::Platform, post::PostEntities, scheduled_post::PostStatus};
+    use migration::{Migrator, MigratorTrait};
+    use mockserver::{fixture, MockRoute, MockServer};
+    use sea_orm::{ActiveModelTrait, Database, DatabaseConnection, EntityTrait, Set};
+
+    use super::{revalidate, DELETED_NOTE};
+    use crate::{
+        models::{
+            time::unix_now,
+            twitterclient::{snowflake_at, TwitterClient},
+        },
+        workers::posting::THREAD_STOPPED_NOTE,
+    };
+
+    /// Text of tweets in `twitter/tweets.json`
+    const TEXT: &str = "hello @biz #rust";
+
+    /// Database with user 1 and tweets `ids` of jack
+    async fn database(ids: &[i64]) -> (DatabaseConnection, Vec<entity::post::Model>) {
+        let db = Database::connect("sqlite::memory:").await.unwrap();
+        Migrator::up(&db, None).await.unwrap();
+
+        entity::telegram_user::ActiveModel {
+            id: Set(1),
+            channel: Set(Some(-1001)),
+            ..Default::default()
+        }
+        .insert(&db)
+        .await
+        .unwrap();
+        let author = entity::author::ActiveModel {
+            platform_id: Set(12),
+            platform: Set(Platform::Twitter),
+            external_id: Set("12".to_string()),
+            name: Set("jack".to_string()),
+            username: Set("jack".to_string()),
+            ..Default::default()
+        }
+        .insert(&db)
+        .await
+        .unwrap();
+
+        let mut posts = vec![];
+        for &id in ids {
+            let post = entity::post::ActiveModel {
+                platform_id: Set(id),
+                external_id: Set(id.to_string()),
+                author_id: Set(author.id),
+                text: Set(TEXT.to_string()),
+                source_url: Set(format!("https://twitter.com/jack/status/{id}")),
+                source_text: Set("jack".to_string()),
+                ..Default::default()
+            }
+            .insert(&db)
+            .await
+            .unwrap();
+            posts.push(post);
+        }
+
+        (db, posts)
+    }
+
+    /// Fixture with tweet ids replaced by `ids` (pairs of fixture id and new id). Fixture
+    /// ids are too old to be revalidated
+    fn lookup_fixture(name: &str, ids: &[(i64, i64)]) -> String {
+        let mut body = fixture(name);
+        for (id, new_id) in ids {
+            body = body.replace(&format!("\"{id}\""), &format!("\"{new_id}\""));
+        }
+
+        body
+    }
+
+    async fn schedule(
+        db: &DatabaseConnection,
+        post_id: i64,
+        text: &str,
+        reply_to_id: Option<i64>,
+    ) -> entity::scheduled_post::Model {
+        entity::scheduled_post::ActiveModel {
+            user_id: Set(1),
+            post_id: Set(Some(post_id)),
+            reply_to_id: Set(reply_to_id),
+            post_text: Set(text.to_string()),
+            post_entities: Set(PostEntities(vec![])),
+            post_source: Set("jack".to_string()),
+            post_source_url: Set("https://twitter.com/jack/status/1".to_string()),
+            ..Default::default()
+        }
+        .insert(db)
+        .await
+        .unwrap()
+    }
+
+    async fn twitter(routes: Vec<MockRoute>) -> (MockServer, TwitterClient) {
+        let server = MockServer::start(routes).await;
+        let mut twitter = TwitterClient::new("token".to_string());
+        twitter.base_url = server.url.clone();
+
+        (server, twitter)
+    }
+
+    #[rocket::async_test]
+    /// Check [`revalidate`] flags deleted tweet and holds its scheduled post with the
+    /// rest of its thread
+    async fn test_revalidate_deleted() {
+        let kept_id = snowflake_at(unix_now() - 60);
+        let deleted_id = kept_id + 2;
+        let lookup = lookup_fixture(
+            "twitter/tweets.json",
+            &[(1001, kept_id), (1003, deleted_id), (1006, kept_id)],
+        );
+        let (server, twitter) = twitter(vec![MockRoute::new("/2/tweets", 200, lookup)]).await;
+        let (db, posts) = database(&[kept_id, deleted_id]).await;
+
+        let kept = schedule(&db, posts[0].id, TEXT, None).await;
+        let deleted = schedule(&db, posts[1].id, TEXT, None).await;
+        let reply = schedule(&db, posts[0].id, TEXT, Some(deleted.id)).await;
+
+        revalidate(&db, &twitter).await;
+
+        assert_eq!(server.requests().len(), 1);
+        assert!(server.requests()[0].contains(&format!("ids={kept_id}%2C{deleted_id}")));
+        let reload = |id| entity::post::Entity::find_by_id(id).one(&db);
+        let post = reload(posts[0].id).await.unwrap().unwrap();
+        assert!(!post.deleted);
+        assert!(post.checked_at.is_some());
+        assert!(reload(posts[1].id).await.unwrap().unwrap().deleted);
+
+        let reload = |id| entity::scheduled_post::Entity::find_by_id(id).one(&db);
+        let kept = reload(kept.id).await.unwrap().unwrap();
+        assert_eq!(kept.status, PostStatus::Pending);
+        let deleted = reload(deleted.id).await.unwrap().unwrap();
+        assert_eq!(deleted.status, PostStatus::Held);
+        assert_eq!(deleted.last_error.as_deref(), Some(DELETED_NOTE));
+        let reply = reload(reply.id).await.unwrap().unwrap();
+        assert_eq!(reply.status, PostStatus::Held);
+        assert_eq!(reply.last_error.as_deref(), Some(THREAD_STOPPED_NOTE));
+    }
+
+    #[rocket::async_test]
+    /// Check [`revalidate`] fetches the latest version of edited tweet, keeps previous
+    /// one in edit history and updates pending scheduled posts with unchanged text
+    async fn test_revalidate_edited() {
+        let original_id = snowflake_at(unix_now() - 60);
+        let edited_id = original_id + 5;
+        let ids = [(1001, original_id), (1006, edited_id)];
+        let (server, twitter) = twitter(vec![
+            MockRoute::new(
+                "/2/tweets",
+                200,
+                lookup_fixture("twitter/tweets.json", &ids),
+            )
+            .times(1),
+            MockRoute::new(
+                "/2/tweets",
+                200,
+                lookup_fixture("twitter/tweets_edited.json", &ids),
+            ),
+        ])
+        .await;
+        let (db, posts) = database(&[original_id]).await;
+
+        let unchanged = schedule(&db, posts[0].id, TEXT, None).await;
+        let changed = schedule(&db, posts[0].id, "my text", None).await;
+
+        revalidate(&db, &twitter).await;
+
+        let requests = server.requests();
+        assert_eq!(requests.len(), 2);
+        assert!(requests[0].contains(&format!("ids={original_id}")));
+        assert!(requests[1].contains(&format!("ids={edited_id}")));
+
+        let post = entity::post::Entity::find_by_id(posts[0].id)
+            .one(&db)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(!post.deleted);
+        assert_eq!(post.text, "hello @biz #rust, edited");
+        assert_eq!(post.version_id, Some(edited_id));
+        assert_eq!(post.edit_history.0.len(), 1);
+        assert_eq!(post.edit_history.0[0].platform_id, original_id);
+        assert_eq!(post.edit_history.0[0].text, TEXT);
+
+        let reload = |id| entity::scheduled_post::Entity::find_by_id(id).one(&db);
+        let unchanged = reload(unchanged.id).await.unwrap().unwrap();
+        assert_eq!(unchanged.post_text, "hello @biz #rust, edited");
+        assert_eq!(unchanged.status, PostStatus::Pending);
+        let changed = reload(changed.id).await.unwrap().unwrap();
+        assert_eq!(changed.post_text, "my text");
+    }
+}
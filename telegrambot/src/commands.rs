@@ -1,5 +1,5 @@
 use sea_orm::{sea_query::OnConflict, DatabaseConnection, EntityTrait, Set};
-use teloxide::{requests::Requester, types::ChatId, Bot};
+use teloxide::{requests::Requester, types::ChatId, ApiError, Bot, RequestError};
 
 #[derive(Debug, Clone, Copy)]
 #[repr(u16)]
@@ -45,6 +45,7 @@ pub async fn link_channel(
 
     let members = match bot.get_chat_administrators(ChatId(chat_id)).await {
         Ok(m) => m,
+        Err(RequestError::Api(ApiError::ChatNotFound)) => return Err(LinkChannelError::NotFound),
         _ => return Err(LinkChannelError::BotNotAdmin),
     };
     if !members
@@ -73,3 +74,98 @@ pub async fn link_channel(
 
     Ok(user)
 }
+
+#[cfg(test)]
+mod tests {
+    use migration::{Migrator, MigratorTrait};
+    use mockserver::{fixture, MockRoute, MockServer};
+    use sea_orm::{Database, DatabaseConnection, EntityTrait};
+
+    use super::{link_channel, LinkChannelError};
+
+    async fn database() -> DatabaseConnection {
+        let db = Database::connect("sqlite::memory:").await.unwrap();
+        Migrator::up(&db, None).await.unwrap();
+
+        db
+    }
+
+    #[tokio::test]
+    /// Check [`link_channel`] links channel only for administrators, that can post
+    async fn test_link_channel() {
+        let db = database().await;
+        let server = MockServer::start(vec![
+            MockRoute::new(
+                "/bottoken/GetChatAdministrators",
+                200,
+                fixture("telegram/chat_administrators.json"),
+            ),
+            MockRoute::new("/bottoken/GetChat", 200, fixture("telegram/chat.json")),
+        ])
+        .await;
+        let bot = server.bot();
+
+        // creator and administrator with `can_post_messages`
+        let user = link_channel(1, "-1001", &bot, &db).await.unwrap();
+        assert_eq!(user.channel, Some(-1001));
+        let user = link_channel(2, "@news", &bot, &db).await.unwrap();
+        assert_eq!(user.channel, Some(-1001));
+
+        let res = link_channel(3, "-1001", &bot, &db).await;
+        assert!(matches!(res, Err(LinkChannelError::UserNoPermissions)));
+        let res = link_channel(4, "news", &bot, &db).await;
+        assert!(matches!(res, Err(LinkChannelError::InvalidFormat)));
+
+        let bodies = server.bodies("/bottoken/GetChatAdministrators");
+        assert_eq!(bodies.len(), 3);
+        assert!(bodies.iter().all(|f| f.contains("-1001")));
+        // username is resolved first
+        assert_eq!(
+            server.requests(),
+            [
+                "/bottoken/GetChatAdministrators",
+                "/bottoken/GetChat",
+                "/bottoken/GetChatAdministrators",
+                "/bottoken/GetChatAdministrators",
+            ]
+        );
+
+        let users = entity::telegram_user::Entity::find()
+            .all(&db)
+            .await
+            .unwrap();
+        assert_eq!(users.len(), 2);
+    }
+
+    #[tokio::test]
+    /// Check [`link_channel`] reports unknown chats and keeps linked channel
+    async fn test_link_channel_not_found() {
+        let db = database().await;
+        let server = MockServer::start(vec![
+            MockRoute::new(
+                "/bottoken/GetChatAdministrators",
+                400,
+                fixture("telegram/chat_not_found.json"),
+            ),
+            MockRoute::new(
+                "/bottoken/GetChat",
+                400,
+                fixture("telegram/chat_not_found.json"),
+            ),
+        ])
+        .await;
+        let bot = server.bot();
+
+        let res = link_channel(1, "-1002", &bot, &db).await;
+        assert!(matches!(res, Err(LinkChannelError::NotFound)));
+        let res = link_channel(1, "@nothing", &bot, &db).await;
+        assert!(matches!(res, Err(LinkChannelError::NotFound)));
+
+        assert_eq!(server.requests().len(), 2);
+        assert!(entity::telegram_user::Entity::find()
+            .all(&db)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+}